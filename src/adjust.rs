@@ -0,0 +1,220 @@
+// Copyright 2025 Grish
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Brightness and contrast adjustments.
+//!
+//! The converter and the viewer both go through this module, so a preview in
+//! the viewer matches what a conversion with the same values writes. Each
+//! channel value `v` is mapped independently:
+//!
+//! ```text
+//! factor = 259 * (contrast + 255) / (255 * (259 - contrast))
+//! v'     = clamp(factor * (v - 128) + 128 + brightness, 0, 255)
+//! ```
+//!
+//! Both inputs are clamped to -255..=255 first. Contrast therefore stretches
+//! values away from mid-gray (or pulls them towards it when negative), and
+//! brightness is then added as a plain offset.
+//!
+//! With [`ContrastCurve::Sigmoid`] the linear stretch is replaced by an
+//! S-curve with the same slope at mid-gray, so shadows and highlights roll
+//! off smoothly instead of clipping (an inverse S-curve for negative
+//! contrast).
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use serde::Deserialize;
+
+/// Shape of the contrast mapping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContrastCurve {
+    /// Straight line through mid-gray, clamped at black and white.
+    #[default]
+    Linear,
+    /// S-curve that compresses shadows and highlights instead of clipping.
+    Sigmoid,
+}
+
+/// Returns the factor by which `contrast` scales the distance from mid-gray.
+///
+/// ```rust
+/// use nor_image::adjust::contrast_factor;
+///
+/// assert_eq!(contrast_factor(0), 1.0);
+/// assert!(contrast_factor(50) > 1.0);
+/// assert!(contrast_factor(-50) < 1.0);
+/// ```
+pub fn contrast_factor(contrast: i32) -> f32 {
+    let contrast = contrast.clamp(-255, 255) as f32;
+    (259.0 * (contrast + 255.0)) / (255.0 * (259.0 - contrast))
+}
+
+/// Builds a lookup table mapping every channel value through the adjustment.
+///
+/// Both curves keep black and white fixed when brightness is zero, but the
+/// sigmoid leaves detail in values the linear curve would clip:
+///
+/// ```rust
+/// use nor_image::adjust::{adjustment_lut, ContrastCurve};
+///
+/// let linear = adjustment_lut(0, 100, ContrastCurve::Linear);
+/// let sigmoid = adjustment_lut(0, 100, ContrastCurve::Sigmoid);
+/// assert_eq!(sigmoid[0], 0);
+/// assert_eq!(sigmoid[255], 255);
+/// assert_eq!((linear[230], linear[240]), (255, 255));
+/// assert!(sigmoid[230] < sigmoid[240]);
+/// ```
+pub fn adjustment_lut(brightness: i32, contrast: i32, curve: ContrastCurve) -> [u8; 256] {
+    let factor = contrast_factor(contrast);
+    let brightness = brightness.clamp(-255, 255) as f32;
+    let mut lut = [0u8; 256];
+    for (value, out) in lut.iter_mut().enumerate() {
+        let contrasted = match curve {
+            ContrastCurve::Linear => factor * (value as f32 - 128.0) + 128.0,
+            ContrastCurve::Sigmoid => 255.0 * sigmoid_contrast(value as f64 / 255.0, factor as f64) as f32,
+        };
+        *out = (contrasted + brightness)
+            .round()
+            .clamp(0.0, 255.0) as u8;
+    }
+    lut
+}
+
+/// Maps `value` (0.0 to 1.0) through an S-curve whose slope at mid-gray is
+/// `factor`, normalized so 0.0 and 1.0 are fixed. Factors below 1.0 use the
+/// inverse curve.
+fn sigmoid_contrast(value: f64, factor: f64) -> f64 {
+    if (factor - 1.0).abs() < 1e-6 {
+        return value;
+    }
+    let gain = sigmoid_gain(factor.max(1.0 / factor));
+    let sigmoid = |x: f64| 1.0 / (1.0 + (-gain * (x - 0.5)).exp());
+    let (low, high) = (sigmoid(0.0), sigmoid(1.0));
+    let mapped = if factor > 1.0 {
+        (sigmoid(value) - low) / (high - low)
+    } else {
+        let y = low + value * (high - low);
+        0.5 + (y / (1.0 - y)).ln() / gain
+    };
+    mapped.clamp(0.0, 1.0)
+}
+
+/// Finds the sigmoid gain whose normalized curve has `slope` at mid-gray.
+///
+/// The slope, `gain / (4 * tanh(gain / 4))`, grows monotonically from 1, so
+/// it is solved by bisection; very steep requests saturate at the upper
+/// bound.
+fn sigmoid_gain(slope: f64) -> f64 {
+    let (mut low, mut high) = (1e-6_f64, 1000.0);
+    for _ in 0..60 {
+        let mid = (low + high) / 2.0;
+        if mid / (4.0 * (mid / 4.0).tanh()) < slope {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    (low + high) / 2.0
+}
+
+/// Adjusts raw 8-bit channel data in place, in parallel.
+pub fn adjust_channels(data: &mut [u8], brightness: i32, contrast: i32, curve: ContrastCurve) {
+    let lut = adjustment_lut(brightness, contrast, curve);
+    #[cfg(feature = "parallel")]
+    let values = data.par_iter_mut();
+    #[cfg(not(feature = "parallel"))]
+    let values = data.iter_mut();
+    values.for_each(|value| *value = lut[*value as usize]);
+}
+
+/// Adjusts the first three channels of interleaved pixel data in place, each
+/// with its own brightness and contrast, in parallel. Any further channels,
+/// such as alpha, are left untouched.
+///
+/// Useful for correcting a color cast:
+///
+/// ```rust
+/// use nor_image::adjust::{adjust_rgb_channels, ContrastCurve};
+///
+/// let mut data = vec![100, 100, 100, 200, 200, 200, 200, 77];
+/// adjust_rgb_channels(&mut data, 4, [-20, 0, 30], [0; 3], ContrastCurve::Linear);
+/// assert_eq!(data, [80, 100, 130, 200, 180, 200, 230, 77]);
+/// ```
+///
+/// # Panics
+///
+/// Panics if `channels` is less than 3; use `adjust_channels` for grayscale
+/// data.
+pub fn adjust_rgb_channels(
+    data: &mut [u8],
+    channels: usize,
+    brightness: [i32; 3],
+    contrast: [i32; 3],
+    curve: ContrastCurve,
+) {
+    assert!(channels >= 3, "adjust_rgb_channels needs at least 3 channels, got {}", channels);
+    let luts: [[u8; 256]; 3] = std::array::from_fn(|c| adjustment_lut(brightness[c], contrast[c], curve));
+    #[cfg(feature = "parallel")]
+    let pixels = data.par_chunks_exact_mut(channels);
+    #[cfg(not(feature = "parallel"))]
+    let pixels = data.chunks_exact_mut(channels);
+    pixels.for_each(|pixel| {
+        for (value, lut) in pixel.iter_mut().zip(&luts) {
+            *value = lut[*value as usize];
+        }
+    });
+}
+
+/// Maps each channel of packed `0x00RRGGBB` pixels through a lookup table.
+///
+/// The viewer adjusts its display buffer this way, so with the table from
+/// [`adjustment_lut`] it produces the same pixels as a conversion:
+///
+/// ```rust
+/// use nor_image::adjust::{adjustment_lut, map_packed_rgb, ContrastCurve};
+/// use nor_image::converter::{png_to_custom, ConversionConfig};
+///
+/// let dir = std::env::temp_dir().join(format!("nor-adjust-{}", std::process::id()));
+/// std::fs::create_dir_all(&dir)?;
+/// let png = dir.join("ramp.png");
+/// let pixels: Vec<u8> = (0..=255u8).flat_map(|v| [v, v / 2, 255 - v]).collect();
+/// image::RgbImage::from_raw(256, 1, pixels.clone()).unwrap().save(&png)?;
+///
+/// let config = ConversionConfig { brightness: Some(20), contrast: Some(60), use_cache: false, ..Default::default() };
+/// let converted = png_to_custom(&png, None, Some(config))?;
+///
+/// let mut viewer_buffer: Vec<u32> = pixels
+///     .chunks_exact(3)
+///     .map(|p| (p[0] as u32) << 16 | (p[1] as u32) << 8 | p[2] as u32)
+///     .collect();
+/// map_packed_rgb(&mut viewer_buffer, &adjustment_lut(20, 60, ContrastCurve::Linear));
+/// let previewed: Vec<u8> = viewer_buffer
+///     .iter()
+///     .flat_map(|&p| [(p >> 16) as u8, (p >> 8) as u8, p as u8])
+///     .collect();
+///
+/// assert_eq!(converted.data, previewed);
+/// std::fs::remove_dir_all(&dir)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[cfg_attr(not(feature = "viewer"), allow(dead_code))]
+pub fn map_packed_rgb(buffer: &mut [u32], lut: &[u8; 256]) {
+    for pixel in buffer.iter_mut() {
+        let r = lut[((*pixel >> 16) & 0xFF) as usize] as u32;
+        let g = lut[((*pixel >> 8) & 0xFF) as usize] as u32;
+        let b = lut[(*pixel & 0xFF) as usize] as u32;
+        *pixel = (r << 16) | (g << 8) | b;
+    }
+}
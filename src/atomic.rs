@@ -0,0 +1,98 @@
+// Copyright 2025 Grish
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Atomic file output.
+//!
+//! Outputs are written to a temporary file next to the target and renamed
+//! over it once complete, so a conversion that fails or is killed mid-write
+//! never leaves a truncated file behind, and an existing output is only
+//! replaced by a finished one:
+//! - The temporary file lives in the target's directory, so the rename stays
+//!   on one filesystem and replaces the target in a single step
+//! - On error the temporary file is removed and the target is untouched
+//!
+//! The data is not synced to disk before the rename, so this guards against
+//! interrupted processes rather than power loss.
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Distinguishes temporary files of concurrent writes within this process.
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Writes `path` atomically: `write` fills a buffered temporary file, which
+/// is flushed and renamed to `path` only if `write` succeeds.
+///
+/// ```rust
+/// use std::io::Write;
+/// use nor_image::atomic::write_atomic;
+///
+/// let path = std::env::temp_dir().join(format!("nor-atomic-{}.txt", std::process::id()));
+/// write_atomic(&path, |w| w.write_all(b"first"))?;
+///
+/// // A failed write leaves the previous contents in place, with no
+/// // temporary file behind.
+/// let failed = write_atomic(&path, |w| {
+///     w.write_all(b"partial")?;
+///     Err(std::io::Error::other("interrupted"))
+/// });
+/// assert!(failed.is_err());
+/// assert_eq!(std::fs::read(&path)?, b"first");
+///
+/// let dir = path.parent().unwrap();
+/// let name = path.file_name().unwrap().to_string_lossy().into_owned();
+/// let leftovers = std::fs::read_dir(dir)?
+///     .filter_map(|entry| entry.ok())
+///     .filter(|entry| entry.file_name().to_string_lossy().starts_with(&format!(".{}.", name)))
+///     .count();
+/// assert_eq!(leftovers, 0);
+/// std::fs::remove_file(&path)?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+///
+/// # Errors
+///
+/// Returns the error of `write`, or an I/O error if the temporary file
+/// cannot be created, flushed or renamed.
+pub fn write_atomic<P, F, E>(path: P, write: F) -> Result<(), E>
+where
+    P: AsRef<Path>,
+    F: FnOnce(&mut BufWriter<File>) -> Result<(), E>,
+    E: From<io::Error>,
+{
+    let path = path.as_ref();
+    let temp = temp_path(path);
+    let result = File::create(&temp).map_err(E::from).and_then(|file| {
+        let mut writer = BufWriter::new(file);
+        write(&mut writer)?;
+        writer.flush()?;
+        drop(writer);
+        fs::rename(&temp, path)?;
+        Ok(())
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+/// Returns a hidden temporary path next to `path`, unique to this process
+/// and call: `.<name>.<pid>.<n>.tmp`.
+fn temp_path(path: &Path) -> PathBuf {
+    let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    let n = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+    path.with_file_name(format!(".{}.{}.{}.tmp", name, std::process::id(), n))
+}
@@ -0,0 +1,174 @@
+// Copyright 2025 Grish
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Contact sheets.
+//!
+//! Tiles thumbnails of several images into one grid for quick review:
+//! - Each image is downscaled to fit a square cell, keeping its aspect ratio
+//!   and centered on a dark background (letterboxed)
+//! - Cells are filled row by row, with a small gap between them
+//! - An optional label under each cell is drawn with the watermark font
+//!
+//! Images are decoded and scaled in parallel, using their pyramid levels
+//! when they have them.
+
+use image::imageops::FilterType;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::format::{ColorType, CompressionType, CustomImage, FormatError};
+use crate::watermark::render_text;
+
+/// Background of the sheet and of letterboxed cell areas.
+const BACKGROUND: [u8; 3] = [32, 32, 32];
+/// Space between cells and around the sheet, in pixels.
+const GAP: u32 = 4;
+/// Height reserved under each cell for its label, in pixels.
+const LABEL_HEIGHT: u32 = 12;
+/// Horizontal space taken by one label character, in pixels.
+const LABEL_ADVANCE: u32 = 6;
+
+/// Builds a contact sheet of `images`, each with its label, `columns` cells
+/// wide and as many rows as needed. Cells are `cell` pixels square; images
+/// smaller than a cell are not scaled up. Labels too long for a cell are
+/// shortened, and left out entirely when `labels` is false.
+///
+/// The sheet is returned as an uncompressed RGB image.
+///
+/// ```rust
+/// use nor_image::contact::contact_sheet;
+/// use nor_image::format::{CustomImage, ColorType, CompressionType};
+///
+/// let wide = CustomImage::new(40, 20, ColorType::Gray, vec![255; 800], None, CompressionType::None)?;
+/// let tall = CustomImage::new(10, 30, ColorType::Rgb, vec![200; 900], None, CompressionType::None)?;
+/// let images = vec![("wide.nor".to_string(), wide.clone()), ("tall.nor".to_string(), tall), ("c".to_string(), wide)];
+///
+/// let sheet = contact_sheet(&images, 2, 20, false)?;
+/// assert_eq!(sheet.color_type, ColorType::Rgb);
+/// // Two columns and two rows of 20px cells with 4px gaps.
+/// assert_eq!((sheet.width, sheet.height), (4 + 2 * (20 + 4), 4 + 2 * (20 + 4)));
+///
+/// // The wide image is letterboxed: 20x10, centered vertically in its cell.
+/// let pixel = |x: u32, y: u32| &sheet.data[((y * sheet.width + x) * 3) as usize..][..3];
+/// assert_eq!(pixel(4, 4), [32, 32, 32]);
+/// assert_eq!(pixel(4, 9), [255, 255, 255]);
+///
+/// let labeled = contact_sheet(&images, 2, 20, true)?;
+/// assert_eq!(labeled.height, sheet.height + 2 * 12);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// # Errors
+///
+/// Returns `InvalidDimensions` if there are no images, `columns` or `cell`
+/// is zero, or the sheet would exceed `max_dimension()`, or a compression
+/// error if an image fails to decode.
+pub fn contact_sheet(
+    images: &[(String, CustomImage)],
+    columns: u32,
+    cell: u32,
+    labels: bool,
+) -> Result<CustomImage, FormatError> {
+    let columns = columns.min(images.len() as u32);
+    if columns == 0 || cell == 0 {
+        return Err(FormatError::InvalidDimensions { width: columns.saturating_mul(cell), height: 0 });
+    }
+    let rows = (images.len() as u32).div_ceil(columns);
+    let label_height = if labels { LABEL_HEIGHT } else { 0 };
+    let pitch_x = cell.saturating_add(GAP);
+    let pitch_y = cell.saturating_add(label_height + GAP);
+    let sheet_dimension = |count: u32, pitch: u32| count.saturating_mul(pitch).saturating_add(GAP);
+    let (width, height) = (sheet_dimension(columns, pitch_x), sheet_dimension(rows, pitch_y));
+    // Check before allocating the canvas.
+    CustomImage::validate_size(width, height, ColorType::Rgb)?;
+    let mut sheet = CustomImage::new(
+        width,
+        height,
+        ColorType::Rgb,
+        BACKGROUND.repeat(width as usize * height as usize),
+        None,
+        CompressionType::None,
+    )?;
+
+    #[cfg(feature = "parallel")]
+    let pending = images.par_iter();
+    #[cfg(not(feature = "parallel"))]
+    let pending = images.iter();
+    let thumbnails = pending
+        .map(|(_, image)| thumbnail(image, cell))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for (i, ((label, _), thumb)) in images.iter().zip(&thumbnails).enumerate() {
+        let (column, row) = (i as u32 % columns, i as u32 / columns);
+        let (cell_x, cell_y) = (GAP + column * pitch_x, GAP + row * pitch_y);
+        let x = cell_x + (cell - thumb.width) / 2;
+        let y = cell_y + (cell - thumb.height) / 2;
+        for (ty, src) in thumb.data.chunks_exact(thumb.width as usize * 3).enumerate() {
+            let start = (((y + ty as u32) * width + x) * 3) as usize;
+            sheet.data[start..start + src.len()].copy_from_slice(src);
+        }
+        if labels {
+            draw_label(&mut sheet, label, cell_x, cell_y + cell + 2, cell);
+        }
+    }
+    Ok(sheet)
+}
+
+/// Decodes `image` scaled to fit within a `cell` pixel square (never
+/// enlarged) and converts it to RGB, compositing alpha over the background.
+fn thumbnail(image: &CustomImage, cell: u32) -> Result<CustomImage, FormatError> {
+    let scale = (cell as f64 / image.width as f64).min(cell as f64 / image.height as f64).min(1.0);
+    let fit = |dimension: u32| ((dimension as f64 * scale).round() as u32).clamp(1, cell);
+    let scaled = image.resized(fit(image.width), fit(image.height), FilterType::Triangle)?;
+    let data = match scaled.color_type {
+        ColorType::Rgb => scaled.data,
+        ColorType::Gray => scaled.data.iter().flat_map(|&v| [v; 3]).collect(),
+        ColorType::GrayAlpha => scaled
+            .data
+            .chunks_exact(2)
+            .flat_map(|p| {
+                BACKGROUND.map(|b| ((p[0] as u32 * p[1] as u32 + b as u32 * (255 - p[1] as u32) + 127) / 255) as u8)
+            })
+            .collect(),
+    };
+    CustomImage::new(scaled.width, scaled.height, ColorType::Rgb, data, None, CompressionType::None)
+}
+
+/// Draws `label` centered under a cell starting at `x`, shortened with `..`
+/// if it is wider than `cell`.
+fn draw_label(sheet: &mut CustomImage, label: &str, x: u32, y: u32, cell: u32) {
+    let fits = (cell / LABEL_ADVANCE) as usize;
+    let chars: Vec<char> = label.chars().collect();
+    let text: String = if chars.len() <= fits {
+        label.to_string()
+    } else if fits > 2 {
+        chars[..fits - 2].iter().chain(['.', '.'].iter()).collect()
+    } else {
+        return;
+    };
+    if text.is_empty() {
+        return;
+    }
+
+    let overlay = render_text(&text, 1);
+    let left = x + cell.saturating_sub(overlay.width()) / 2;
+    for (ox, oy, pixel) in overlay.enumerate_pixels() {
+        let (px, py) = (left + ox, y + oy);
+        if pixel[3] == 0 || px >= sheet.width || py >= sheet.height {
+            continue;
+        }
+        let start = ((py * sheet.width + px) * 3) as usize;
+        sheet.data[start..start + 3].copy_from_slice(&pixel.0[..3]);
+    }
+}
@@ -12,18 +12,29 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use image::{DynamicImage, GrayImage, RgbImage, GenericImageView, imageops};
-use image::{ImageEncoder, ColorType};
-use std::path::Path;
+use image::{DynamicImage, GrayImage, GrayAlphaImage, Luma, RgbImage, RgbaImage, GenericImageView, imageops};
+use image::{ColorType, ImageDecoder, ImageFormat};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 use std::io;
-use rayon::prelude::*;
 use std::error::Error as StdError;
 use std::fmt;
 use std::fs::File;
-use std::io::Write;
+use std::io::{BufReader, Write};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use serde::Deserialize;
+use log::{debug, info, trace, warn};
 
-use crate::format::{CustomImage, FormatError, ColorType as CustomColorType, CompressionType, ImageMetadata};
-use crate::processing::{CachedImageLoader, ParallelImageProcessor, ProcessingError, CHUNK_SIZE};
+use crate::adjust::{adjust_channels, adjust_rgb_channels, ContrastCurve};
+use crate::atomic::write_atomic;
+use crate::denoise::{median_filter, MAX_MEDIAN_RADIUS};
+use crate::format::{CustomImage, FormatError, ColorType as CustomColorType, CompressionType, ImageMetadata, Region, max_dimension, DEFAULT_LOSSY_QUALITY};
+use crate::icc::to_srgb;
+use crate::watermark::{apply_watermark, Watermark};
+use crate::white_balance::auto_white_balance;
+use crate::processing::{process_chunks_mut, CachedImageLoader, ParallelImageProcessor, ProcessingError, CHUNK_SIZE};
+use crate::quantize::quantize_colors;
 
 /// Error types that can occur during image conversion.
 #[derive(Debug)]
@@ -38,6 +49,18 @@ pub enum ConversionError {
     UnsupportedFormat(String),
     /// I/O error.
     IoError(io::Error),
+    /// The configuration file could not be parsed.
+    ConfigError(String),
+    /// Two images that must match in size do not.
+    DimensionMismatch { expected: (u32, u32), actual: (u32, u32) },
+    /// A conversion option is out of range or conflicts with another.
+    InvalidOption(String),
+    /// A requested resize dimension exceeds the format's limit.
+    DimensionTooLarge { dimension: &'static str, value: u32, limit: u32 },
+    /// The output file already exists and overwriting was not allowed.
+    OutputExists(PathBuf),
+    /// A questionable outcome that `ConversionConfig::strict` turns into an error.
+    Strict(String),
 }
 
 impl fmt::Display for ConversionError {
@@ -48,6 +71,24 @@ impl fmt::Display for ConversionError {
             ConversionError::ProcessingError(e) => write!(f, "Processing error: {}", e),
             ConversionError::UnsupportedFormat(msg) => write!(f, "Unsupported format: {}", msg),
             ConversionError::IoError(e) => write!(f, "I/O error: {}", e),
+            ConversionError::ConfigError(msg) => write!(f, "Config error: {}", msg),
+            ConversionError::DimensionMismatch { expected, actual } => write!(
+                f,
+                "Dimension mismatch: expected {}x{}, got {}x{}",
+                expected.0, expected.1, actual.0, actual.1
+            ),
+            ConversionError::InvalidOption(msg) => write!(f, "Invalid option: {}", msg),
+            ConversionError::DimensionTooLarge { dimension, value, limit } => write!(
+                f,
+                "Requested resize {} of {} exceeds the maximum of {}",
+                dimension, value, limit
+            ),
+            ConversionError::OutputExists(path) => write!(
+                f,
+                "Output file {} already exists (use --overwrite to replace it)",
+                path.display()
+            ),
+            ConversionError::Strict(msg) => write!(f, "Strict mode: {}", msg),
         }
     }
 }
@@ -87,23 +128,343 @@ impl From<io::Error> for ConversionError {
     }
 }
 
+impl From<png::DecodingError> for ConversionError {
+    fn from(err: png::DecodingError) -> Self {
+        ConversionError::ImageError(image::ImageError::Decoding(
+            image::error::DecodingError::new(ImageFormat::Png.into(), err),
+        ))
+    }
+}
+
+impl From<png::EncodingError> for ConversionError {
+    fn from(err: png::EncodingError) -> Self {
+        ConversionError::ImageError(image::ImageError::Encoding(
+            image::error::EncodingError::new(ImageFormat::Png.into(), err),
+        ))
+    }
+}
+
+/// Callback receiving fractional conversion progress in the range `0.0..=1.0`.
+///
+/// The converter reports progress once per pipeline stage (decode, resize,
+/// adjust, compress, write), never per pixel, so the callback may do
+/// comparatively expensive work such as redrawing a progress bar.
+#[derive(Clone)]
+pub struct ProgressCallback(Arc<dyn Fn(f32) + Send + Sync>);
+
+impl ProgressCallback {
+    /// Wraps a closure as a progress callback.
+    pub fn new<F: Fn(f32) + Send + Sync + 'static>(callback: F) -> Self {
+        ProgressCallback(Arc::new(callback))
+    }
+
+    /// Reports the given fraction of work completed.
+    fn report(&self, fraction: f32) {
+        (self.0)(fraction.clamp(0.0, 1.0));
+    }
+}
+
+impl fmt::Debug for ProgressCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressCallback")
+    }
+}
+
+/// Shared recorder for the time spent in each conversion stage.
+///
+/// Clones share the same records, so a caller can keep one handle and pass
+/// another in `ConversionConfig::timings`.
+#[derive(Clone, Debug, Default)]
+pub struct StageTimings(Arc<Mutex<Vec<(&'static str, Duration)>>>);
+
+impl StageTimings {
+    /// Creates an empty recorder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the recorded stages in the order they completed.
+    pub fn stages(&self) -> Vec<(&'static str, Duration)> {
+        self.0.lock().map(|stages| stages.clone()).unwrap_or_default()
+    }
+
+    /// Appends the elapsed time for a stage.
+    fn record(&self, stage: &'static str, elapsed: Duration) {
+        if let Ok(mut stages) = self.0.lock() {
+            stages.push((stage, elapsed));
+        }
+    }
+}
+
+/// Compression effort used when encoding PNG output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PngCompression {
+    /// Fastest encoding, largest files.
+    Fast,
+    /// Balanced speed and size.
+    Default,
+    /// Smallest files, slowest encoding.
+    #[default]
+    Best,
+}
+
+impl PngCompression {
+    /// Returns the encoder settings for this level.
+    fn encoder_settings(self) -> (png::Compression, png::FilterType, png::AdaptiveFilterType) {
+        use png::{AdaptiveFilterType, Compression, FilterType};
+        match self {
+            PngCompression::Fast => (Compression::Fast, FilterType::NoFilter, AdaptiveFilterType::NonAdaptive),
+            PngCompression::Default => (Compression::Default, FilterType::Sub, AdaptiveFilterType::Adaptive),
+            PngCompression::Best => (Compression::Best, FilterType::Sub, AdaptiveFilterType::Adaptive),
+        }
+    }
+}
+
+/// How RGB channels are weighted when converting an image to grayscale.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GrayscaleMethod {
+    /// ITU-R BT.601 luma weights (0.299, 0.587, 0.114).
+    Luma601,
+    /// ITU-R BT.709 luma weights (0.2126, 0.7152, 0.0722), the `image`
+    /// crate's own conversion.
+    #[default]
+    Luma709,
+    /// Unweighted mean of the three channels.
+    Average,
+    /// Red, green and blue weights, scaled to sum to one.
+    Custom([f32; 3]),
+}
+
+impl GrayscaleMethod {
+    /// Converts an image to 8-bit grayscale, dropping any alpha.
+    ///
+    /// `Luma709` goes through `DynamicImage::into_luma8`; the other methods
+    /// weight the RGB channels directly.
+    ///
+    /// ```rust
+    /// use image::{DynamicImage, RgbImage};
+    /// use nor_image::converter::GrayscaleMethod;
+    ///
+    /// let red = DynamicImage::ImageRgb8(RgbImage::from_pixel(1, 1, image::Rgb([255, 0, 0])));
+    /// let gray = |method: GrayscaleMethod| method.to_luma(red.clone()).map(|img| img.into_raw());
+    /// assert_eq!(gray(GrayscaleMethod::Luma601)?, [76]);
+    /// assert_eq!(gray(GrayscaleMethod::Luma709)?, [54]);
+    /// assert_eq!(gray(GrayscaleMethod::Average)?, [85]);
+    /// assert_eq!(gray(GrayscaleMethod::Custom([2.0, 1.0, 1.0]))?, [128]);
+    /// assert!(gray(GrayscaleMethod::Custom([0.0; 3])).is_err());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidOption` if custom weights are negative, not finite or
+    /// sum to zero.
+    pub fn to_luma(self, img: DynamicImage) -> Result<GrayImage, ConversionError> {
+        let [r, g, b] = match self {
+            GrayscaleMethod::Luma709 => return Ok(img.into_luma8()),
+            GrayscaleMethod::Luma601 => [0.299, 0.587, 0.114],
+            GrayscaleMethod::Average => [1.0 / 3.0; 3],
+            GrayscaleMethod::Custom(weights) => {
+                let sum: f32 = weights.iter().sum();
+                if weights.iter().any(|w| !w.is_finite() || *w < 0.0) || sum <= 0.0 {
+                    return Err(ConversionError::InvalidOption(format!(
+                        "Grayscale weights {:?} must be non-negative with a positive sum",
+                        weights
+                    )));
+                }
+                weights.map(|w| w / sum)
+            }
+        };
+        let rgb = img.into_rgb8();
+        Ok(GrayImage::from_fn(rgb.width(), rgb.height(), |x, y| {
+            let [red, green, blue] = rgb.get_pixel(x, y).0;
+            let luma = r * red as f32 + g * green as f32 + b * blue as f32;
+            Luma([luma.round().min(255.0) as u8])
+        }))
+    }
+}
+
+/// One-step looks that expand into a combination of adjustments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Preset {
+    /// Punchy contrast with a slight lift.
+    Vivid,
+    /// Brighter, slightly more contrasty.
+    Bright,
+    /// Gentle, low-contrast look.
+    Soft,
+    /// Plain black and white.
+    Bw,
+    /// Black and white with strong contrast.
+    BwHighContrast,
+}
+
+/// Adjustments each preset expands to, as (preset, brightness, contrast,
+/// grayscale). Add a row here to add a preset.
+const PRESETS: &[(Preset, i32, i32, bool)] = &[
+    (Preset::Vivid, 5, 50, false),
+    (Preset::Bright, 30, 10, false),
+    (Preset::Soft, 10, -40, false),
+    (Preset::Bw, 0, 0, true),
+    (Preset::BwHighContrast, 0, 100, true),
+];
+
+/// Seed of the randomized conversion steps when `ConversionConfig::seed` is
+/// unset.
+pub const DEFAULT_SEED: u64 = 0x6E6F_725F_696D_6167;
+
 /// Configuration options for image conversion.
-#[derive(Clone, Debug)]
+///
+/// Can be loaded from a TOML file with `ConversionConfig::from_toml_file`; any
+/// field missing from the file keeps its default value.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct ConversionConfig {
     /// Target width for resizing (optional).
     pub resize_width: Option<u32>,
     /// Target height for resizing (optional).
     pub resize_height: Option<u32>,
-    /// Brightness adjustment (-255 to 255).
-    pub brightness: i32,
-    /// Contrast adjustment (-255 to 255).
-    pub contrast: i32,
+    /// Resize to this percentage of the source dimensions (optional;
+    /// cannot be combined with `resize_width`/`resize_height`).
+    pub scale: Option<f32>,
+    /// Center-crop imported images to this width:height ratio before any
+    /// resize (optional).
+    pub crop_aspect: Option<(u32, u32)>,
+    /// Brightness adjustment (-255 to 255; unset means the preset's value,
+    /// or 0 without a preset).
+    pub brightness: Option<i32>,
+    /// Contrast adjustment (-255 to 255; unset means the preset's value, or
+    /// 0 without a preset).
+    pub contrast: Option<i32>,
+    /// Separate red, green and blue brightness, replacing `brightness` for
+    /// color images (optional; grayscale images use `brightness`).
+    pub brightness_rgb: Option<[i32; 3]>,
+    /// Separate red, green and blue contrast, replacing `contrast` for
+    /// color images (optional; grayscale images use `contrast`).
+    pub contrast_rgb: Option<[i32; 3]>,
+    /// Whether contrast is a linear stretch or a smoother S-curve.
+    pub contrast_curve: ContrastCurve,
     /// Whether to convert to grayscale.
     pub force_grayscale: bool,
+    /// Store color images whose channels all match (within
+    /// `AUTO_GRAYSCALE_TOLERANCE`) as grayscale, a third of the size. The
+    /// check runs after adjustments, so it sees the pixels that are stored.
+    pub auto_grayscale: bool,
+    /// Channel weighting used when `force_grayscale` is set.
+    pub grayscale_method: GrayscaleMethod,
+    /// Look whose adjustments fill in `brightness` and `contrast` wherever
+    /// those are unset, and turn on `force_grayscale` for black-and-white
+    /// looks.
+    pub preset: Option<Preset>,
+    /// Median-filter imported images with this window radius right after
+    /// decoding, to clean up noisy scans (optional). Each pixel becomes the
+    /// median of the `(2r + 1)²` window around it, which is noticeably
+    /// slower than the other adjustments for large radii. At most
+    /// `MAX_MEDIAN_RADIUS`.
+    pub median_filter: Option<u32>,
+    /// Reduce the pixel data to at most this many colors with median-cut,
+    /// keeping the color type unchanged.
+    pub quantize_colors: Option<u16>,
+    /// Seed for the conversion steps that break ties or draw randomly,
+    /// `DEFAULT_SEED` when unset, so the same input and settings always give
+    /// the same bytes. Only `quantize_colors` reads it, to order colors tied
+    /// on the channel being split.
+    pub seed: Option<u64>,
+    /// Remove color casts from color images with gray-world white balance
+    /// before the brightness/contrast adjustments. Grayscale sources and
+    /// grayscale output are left alone.
+    pub auto_white_balance: bool,
     /// Compression method to use.
     pub compression: CompressionType,
+    /// Try every lossless codec and keep the smallest result, ignoring
+    /// `compression`.
+    pub auto_compression: bool,
+    /// Quality (1-100) of lossy compression; lower qualities average larger
+    /// pixel blocks. See `format::lossy_block_size`.
+    pub lossy_quality: u8,
+    /// Keep the whole `.nor` file at or under this many bytes (optional;
+    /// requires lossy compression). The highest quality up to
+    /// `lossy_quality` that fits is binary searched within
+    /// `TARGET_SEARCH_STEPS` encodings. When no quality fits, the smallest
+    /// result is kept and reported; the image is never resized.
+    pub target_bytes: Option<usize>,
+    /// Also consider lossy compression when `auto_compression` is set.
+    pub auto_allow_lossy: bool,
+    /// Store pixel data as independently compressed square tiles of this
+    /// size, so regions can be decoded without the rest of the image.
+    pub tile_size: Option<u32>,
+    /// Also store this many successively halved copies of the image, so
+    /// smaller outputs can be produced without decoding the full image.
+    pub pyramid_levels: Option<u32>,
     /// Whether to use caching for faster repeated access.
     pub use_cache: bool,
+    /// Read `.nor` inputs in chunks through the streaming loader instead of
+    /// with a single `fs::read`. Streaming holds about twice the file size
+    /// at its peak; see `CachedImageLoader::read`.
+    pub streaming: bool,
+    /// Bytes of pixel data per parallel task when applying adjustments
+    /// (rounded up to whole pixels). Must be nonzero.
+    pub chunk_size: usize,
+    /// Color that transparent PNG pixels are flattened onto during import
+    /// (white when unset).
+    pub background_color: Option<[u8; 3]>,
+    /// Compression effort for PNG output.
+    pub png_compression: PngCompression,
+    /// Quality (0-100) for lossy WebP output; `None` encodes lossless WebP.
+    pub webp_quality: Option<f32>,
+    /// Replace existing output files instead of failing with `OutputExists`.
+    pub overwrite: bool,
+    /// Stamp imported images with the current time. By default their
+    /// `creation_date` is the source's EXIF `DateTimeOriginal`, or the
+    /// source file's modification time when it has none, so converting the
+    /// same file twice gives the same result.
+    pub creation_date_now: bool,
+    /// Carry PNG text chunks (tEXt, zTXt and iTXt) through conversion: they
+    /// are stored in the metadata's custom fields on import, and custom
+    /// fields are written back as text chunks on PNG export. The standard
+    /// Title, Description and Copyright keywords go to the dedicated
+    /// metadata fields instead, which PNG export writes either way.
+    pub preserve_text: bool,
+    /// Convert the pixels of imported images with an embedded ICC profile
+    /// to sRGB and drop the profile, so every output is plain sRGB. Images
+    /// without a profile are assumed to be sRGB already. See `icc::to_srgb`
+    /// for which profiles can be converted.
+    pub to_srgb: bool,
+    /// Write the output without a SHA256 checksum (marked in its header).
+    /// Only settable from code or `--no-checksum`, never from a config file,
+    /// so integrity checking is not dropped by accident.
+    #[serde(skip)]
+    pub skip_checksum: bool,
+    /// End the output with `FOOTER_MAGIC`, so packed `.nor` blobs can be
+    /// split at file boundaries.
+    pub footer_magic: bool,
+    /// Pad the metadata block to this many bytes, so `set-meta` can later
+    /// edit it in place instead of rewriting the pixel data.
+    pub reserve_metadata: Option<u32>,
+    /// Fail instead of logging a warning when a conversion succeeds in a
+    /// questionable way:
+    /// - brightness or contrast outside -255..=255 (otherwise clamped);
+    /// - the chosen compression makes the pixel data larger than storing it raw;
+    /// - fewer pyramid levels than requested fit the image;
+    /// - a custom metadata field cannot be written as a PNG text chunk
+    ///   (otherwise skipped);
+    /// - a WebP quality outside 0-100 (otherwise clamped).
+    /// - no lossy quality fits `target_bytes` (otherwise the closest is
+    ///   kept).
+    /// - with `to_srgb`, an ICC profile cannot be converted (otherwise the
+    ///   pixels and profile are kept as they are).
+    pub strict: bool,
+    /// Text or logo stamped onto imported images after adjustments.
+    pub watermark: Option<Watermark>,
+    /// Optional callback invoked as each conversion stage completes.
+    #[serde(skip)]
+    pub progress: Option<ProgressCallback>,
+    /// Optional recorder for per-stage timings.
+    #[serde(skip)]
+    pub timings: Option<StageTimings>,
 }
 
 impl Default for ConversionConfig {
@@ -111,39 +472,443 @@ impl Default for ConversionConfig {
         ConversionConfig {
             resize_width: None,
             resize_height: None,
-            brightness: 0,
-            contrast: 0,
+            scale: None,
+            crop_aspect: None,
+            brightness: None,
+            contrast: None,
+            brightness_rgb: None,
+            contrast_rgb: None,
+            contrast_curve: ContrastCurve::default(),
             force_grayscale: false,
+            auto_grayscale: false,
+            grayscale_method: GrayscaleMethod::default(),
+            preset: None,
+            median_filter: None,
+            quantize_colors: None,
+            seed: None,
+            auto_white_balance: false,
             compression: CompressionType::None,
+            auto_compression: false,
+            lossy_quality: DEFAULT_LOSSY_QUALITY,
+            target_bytes: None,
+            auto_allow_lossy: false,
+            tile_size: None,
+            pyramid_levels: None,
             use_cache: true,
+            streaming: true,
+            chunk_size: CHUNK_SIZE,
+            background_color: None,
+            png_compression: PngCompression::default(),
+            webp_quality: None,
+            overwrite: false,
+            creation_date_now: false,
+            preserve_text: false,
+            to_srgb: false,
+            skip_checksum: false,
+            footer_magic: false,
+            reserve_metadata: None,
+            strict: false,
+            watermark: None,
+            progress: None,
+            timings: None,
         }
     }
 }
 
-/// Applies brightness and contrast adjustments on raw pixel data in parallel.
-fn apply_adjustments(data: &[u8], brightness: i32, contrast: i32) -> Vec<u8> {
-    data.par_chunks(CHUNK_SIZE)
-        .map(|chunk| {
-            let mut processed = chunk.to_vec();
-            for pixel in processed.iter_mut() {
-                // Normalize to -1.0 to 1.0 range.
-                let mut value = (*pixel as f32 / 127.5) - 1.0;
-                // Apply contrast adjustment.
-                if contrast != 0 {
-                    let contrast_factor = (contrast as f32 + 255.0) / 255.0;
-                    value *= contrast_factor;
+impl ConversionConfig {
+    /// Loads conversion defaults from a TOML file.
+    ///
+    /// Keys mirror the field names of this struct, e.g.:
+    ///
+    /// ```toml
+    /// compression = "rle"
+    /// force_grayscale = true
+    /// brightness = 10
+    /// ```
+    pub fn from_toml_file<P: AsRef<Path>>(path: P) -> Result<Self, ConversionError> {
+        let contents = std::fs::read_to_string(path)?;
+        toml::from_str(&contents).map_err(|e| ConversionError::ConfigError(e.to_string()))
+    }
+
+    /// Reports progress through the configured callback, if any.
+    fn report_progress(&self, fraction: f32) {
+        if let Some(ref progress) = self.progress {
+            progress.report(fraction);
+        }
+    }
+
+    /// Expands `preset` into the adjustment fields, keeping any adjustment
+    /// that was set explicitly, even to 0.
+    ///
+    /// ```rust
+    /// use nor_image::converter::{ConversionConfig, Preset};
+    ///
+    /// let config = ConversionConfig {
+    ///     preset: Some(Preset::BwHighContrast),
+    ///     brightness: Some(20),
+    ///     ..ConversionConfig::default()
+    /// }
+    /// .with_preset_applied();
+    /// assert!(config.force_grayscale);
+    /// assert_eq!((config.brightness, config.contrast), (Some(20), Some(100)));
+    /// ```
+    pub fn with_preset_applied(mut self) -> Self {
+        let Some(preset) = self.preset.take() else {
+            return self;
+        };
+        if let Some(&(_, brightness, contrast, grayscale)) = PRESETS.iter().find(|row| row.0 == preset) {
+            self.brightness.get_or_insert(brightness);
+            self.contrast.get_or_insert(contrast);
+            self.force_grayscale |= grayscale;
+        }
+        self
+    }
+
+    /// Returns `seed`, or `DEFAULT_SEED` when none is set.
+    pub fn rng_seed(&self) -> u64 {
+        self.seed.unwrap_or(DEFAULT_SEED)
+    }
+
+    /// Fails with `OutputExists` if `path` exists and `overwrite` is not set.
+    fn check_output(&self, path: &Path) -> Result<(), ConversionError> {
+        if !self.overwrite && path.exists() {
+            return Err(ConversionError::OutputExists(path.to_path_buf()));
+        }
+        Ok(())
+    }
+
+    /// Fails with `Strict` when `strict` is set, and logs `message` as a
+    /// warning otherwise.
+    fn questionable(&self, message: String) -> Result<(), ConversionError> {
+        if self.strict {
+            return Err(ConversionError::Strict(message));
+        }
+        warn!("{}", message);
+        Ok(())
+    }
+
+    /// Reports brightness or contrast values that will be clamped.
+    fn check_adjustments(&self) -> Result<(), ConversionError> {
+        let (brightness_rgb, contrast_rgb) = self.rgb_adjustments();
+        let (brightness, contrast) = self.adjustments();
+        let values = [("brightness", brightness), ("contrast", contrast)]
+            .into_iter()
+            .chain(brightness_rgb.into_iter().map(|value| ("brightness", value)))
+            .chain(contrast_rgb.into_iter().map(|value| ("contrast", value)));
+        for (name, value) in values {
+            if !(-255..=255).contains(&value) {
+                self.questionable(format!("{} {} is outside -255..=255 and will be clamped", name, value))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns whether any brightness or contrast adjustment is requested.
+    pub fn adjusts(&self) -> bool {
+        let (brightness_rgb, contrast_rgb) = self.rgb_adjustments();
+        self.adjustments() != (0, 0)
+            || brightness_rgb != [0; 3]
+            || contrast_rgb != [0; 3]
+    }
+
+    /// Returns the scalar brightness and contrast, with 0 for unset values.
+    fn adjustments(&self) -> (i32, i32) {
+        (self.brightness.unwrap_or(0), self.contrast.unwrap_or(0))
+    }
+
+    /// Returns the red, green and blue brightness and contrast, falling back
+    /// to the scalar values for whichever is not set per channel.
+    fn rgb_adjustments(&self) -> ([i32; 3], [i32; 3]) {
+        let (brightness, contrast) = self.adjustments();
+        (
+            self.brightness_rgb.unwrap_or([brightness; 3]),
+            self.contrast_rgb.unwrap_or([contrast; 3]),
+        )
+    }
+
+    /// Checks the lossy quality, and that a byte target is combined with
+    /// options it can work with.
+    fn validate_target(&self) -> Result<(), ConversionError> {
+        if !(1..=100).contains(&self.lossy_quality) {
+            return Err(ConversionError::InvalidOption(format!(
+                "Lossy quality {} is outside 1-100",
+                self.lossy_quality
+            )));
+        }
+        if self.target_bytes.is_none() {
+            return Ok(());
+        }
+        if self.auto_compression || self.compression != CompressionType::Lossy {
+            return Err(ConversionError::InvalidOption(
+                "A byte target requires lossy compression".to_string(),
+            ));
+        }
+        if self.tile_size.is_some() || self.pyramid_levels.is_some() {
+            return Err(ConversionError::InvalidOption(
+                "A byte target cannot be combined with tiles or pyramid levels".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Checks the requested resize dimensions against the format limits, so
+    /// a bad request fails before any decoding work is done.
+    fn validate_resize(&self) -> Result<(), ConversionError> {
+        for (dimension, value) in [("width", self.resize_width), ("height", self.resize_height)] {
+            match value {
+                Some(0) => {
+                    return Err(ConversionError::InvalidOption(format!(
+                        "resize {} must be at least 1",
+                        dimension
+                    )))
                 }
-                // Then apply brightness.
-                if brightness != 0 {
-                    value += (brightness as f32) / 127.5;
+                Some(value) if value > max_dimension() => {
+                    return Err(ConversionError::DimensionTooLarge {
+                        dimension,
+                        value,
+                        limit: max_dimension(),
+                    })
                 }
-                // Convert back with clamping.
-                *pixel = ((value + 1.0).clamp(0.0, 2.0) * 127.5).min(255.0).max(0.0) as u8;
+                _ => {}
+            }
+        }
+        if let Some((w, h)) = self.crop_aspect {
+            if w == 0 || h == 0 {
+                return Err(ConversionError::InvalidOption(format!(
+                    "crop aspect ratio {}:{} must have positive terms",
+                    w, h
+                )));
             }
-            processed
-        })
-        .collect::<Vec<_>>()
-        .concat()
+        }
+        Ok(())
+    }
+
+    /// Rejects a median filter radius above `MAX_MEDIAN_RADIUS`, which would
+    /// take very long for little visible difference.
+    fn validate_median(&self) -> Result<(), ConversionError> {
+        match self.median_filter {
+            Some(radius) if radius > MAX_MEDIAN_RADIUS => Err(ConversionError::InvalidOption(format!(
+                "median filter radius {} exceeds the limit of {}",
+                radius, MAX_MEDIAN_RADIUS
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Rejects an empty pyramid and zero-sized tiles.
+    fn validate_storage(&self) -> Result<(), ConversionError> {
+        if self.pyramid_levels == Some(0) {
+            return Err(ConversionError::InvalidOption(
+                "A pyramid needs at least one level".to_string(),
+            ));
+        }
+        if self.tile_size == Some(0) {
+            return Err(ConversionError::InvalidOption("Tile size must be positive".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Rejects quantizing to zero colors.
+    fn validate_quantize(&self) -> Result<(), ConversionError> {
+        if self.quantize_colors == Some(0) {
+            return Err(ConversionError::InvalidOption(
+                "Quantization needs at least one color".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Rejects a zero `chunk_size`, which would leave nothing to split the
+    /// pixel data by.
+    fn validate_chunk_size(&self) -> Result<(), ConversionError> {
+        if self.chunk_size == 0 {
+            return Err(ConversionError::InvalidOption("chunk size must be at least 1 byte".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Returns the largest centered rectangle of a `width`x`height` source
+    /// matching `crop_aspect`, as `(x, y, width, height)`, or `None` when no
+    /// crop is requested.
+    fn crop_region(&self, width: u32, height: u32) -> Option<(u32, u32, u32, u32)> {
+        let (aspect_w, aspect_h) = self.crop_aspect?;
+        let (w, h) = (width as u64, height as u64);
+        let (aspect_w, aspect_h) = (aspect_w as u64, aspect_h as u64);
+        // Compare the ratios without floating point: w/h > aspect_w/aspect_h
+        // means the source is too wide, so the full height is kept.
+        let (crop_w, crop_h) = if w * aspect_h > h * aspect_w {
+            (((h * aspect_w + aspect_h / 2) / aspect_h).max(1), h)
+        } else {
+            (w, ((w * aspect_h + aspect_w / 2) / aspect_w).max(1))
+        };
+        let (crop_w, crop_h) = (crop_w.min(w) as u32, crop_h.min(h) as u32);
+        Some(((width - crop_w) / 2, (height - crop_h) / 2, crop_w, crop_h))
+    }
+
+    /// Returns the size to resize a `width`x`height` source to, or `None` to
+    /// keep the source size.
+    ///
+    /// `scale` is applied to the source dimensions and the result is capped
+    /// at `max_dimension()`.
+    fn target_dimensions(&self, width: u32, height: u32) -> Result<Option<(u32, u32)>, ConversionError> {
+        match (self.scale, self.resize_width, self.resize_height) {
+            (Some(_), Some(_), _) | (Some(_), _, Some(_)) => Err(ConversionError::InvalidOption(
+                "scale cannot be combined with an explicit width or height".to_string(),
+            )),
+            (Some(scale), None, None) => {
+                if !scale.is_finite() || scale <= 0.0 {
+                    return Err(ConversionError::InvalidOption(format!(
+                        "scale must be a positive percentage, got {}",
+                        scale
+                    )));
+                }
+                let scaled = |dimension: u32| {
+                    ((dimension as f64 * scale as f64 / 100.0).round() as u32).clamp(1, max_dimension())
+                };
+                Ok(Some((scaled(width), scaled(height))))
+            }
+            (None, Some(w), Some(h)) => Ok(Some((w, h))),
+            _ => Ok(None),
+        }
+    }
+
+    /// Logs and records the time since `start` for a stage, returning the
+    /// start instant for the next stage.
+    fn finish_stage(&self, stage: &'static str, start: Instant) -> Instant {
+        let elapsed = start.elapsed();
+        debug!("Stage {} took {:.2?}", stage, elapsed);
+        if let Some(ref timings) = self.timings {
+            timings.record(stage, elapsed);
+        }
+        Instant::now()
+    }
+}
+
+/// Background used to flatten transparent PNGs when none is configured.
+const DEFAULT_BACKGROUND: [u8; 3] = [255, 255, 255];
+
+/// Composites an image with alpha over a solid background color.
+fn flatten_alpha(img: &DynamicImage, background: [u8; 3]) -> DynamicImage {
+    let rgba = img.to_rgba8();
+    let flattened = RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let [r, g, b, a] = rgba.get_pixel(x, y).0;
+        let alpha = a as u32;
+        let blend = |value: u8, bg: u8| {
+            ((value as u32 * alpha + bg as u32 * (255 - alpha) + 127) / 255) as u8
+        };
+        image::Rgb([blend(r, background[0]), blend(g, background[1]), blend(b, background[2])])
+    });
+    DynamicImage::ImageRgb8(flattened)
+}
+
+/// Applies brightness and contrast adjustments on raw pixel data with
+/// `channels` interleaved channels, in parallel chunks of about
+/// `config.chunk_size` bytes.
+///
+/// Color data (three or more channels) uses the per-channel values when set;
+/// grayscale data always uses the scalar ones. With the linear curve and no
+/// per-channel values this is the same mapping as the viewer; see
+/// [`crate::adjust`].
+fn apply_adjustments(data: &[u8], channels: usize, config: &ConversionConfig) -> Vec<u8> {
+    let mut processed = data.to_vec();
+    let chunk_size = config.chunk_size.div_ceil(channels) * channels;
+    let (brightness, contrast) = config.rgb_adjustments();
+    let (gray_brightness, gray_contrast) = config.adjustments();
+    let chunks = process_chunks_mut(&mut processed, chunk_size, |chunk| {
+        if channels >= 3 {
+            adjust_rgb_channels(chunk, channels, brightness, contrast, config.contrast_curve);
+        } else {
+            adjust_channels(chunk, gray_brightness, gray_contrast, config.contrast_curve);
+        }
+    });
+    trace!("Adjusted {} bytes in {} chunks of {} bytes", processed.len(), chunks, chunk_size);
+    processed
+}
+
+/// Like `apply_adjustments`, but carries the alpha channel of grayscale-alpha
+/// (2 channels) and RGBA (4 channels) data through untouched.
+fn adjust_pixels(data: &[u8], channels: usize, config: &ConversionConfig) -> Vec<u8> {
+    let mut adjusted = apply_adjustments(data, channels, config);
+    if channels == 2 || channels == 4 {
+        for (pixel, original) in adjusted.chunks_exact_mut(channels).zip(data.chunks_exact(channels)) {
+            pixel[channels - 1] = original[channels - 1];
+        }
+    }
+    adjusted
+}
+
+/// Largest difference between the channels of a pixel that `auto_grayscale`
+/// still treats as gray, to absorb rounding noise from lossy sources.
+const AUTO_GRAYSCALE_TOLERANCE: u8 = 2;
+
+/// Returns whether every pixel of interleaved RGB data has its channels
+/// within `tolerance` of each other.
+fn is_grayscale(rgb: &[u8], tolerance: u8) -> bool {
+    rgb.chunks_exact(3).all(|p| {
+        let (min, max) = (p[0].min(p[1]).min(p[2]), p[0].max(p[1]).max(p[2]));
+        max - min <= tolerance
+    })
+}
+
+/// Collapses interleaved RGB data to one channel by averaging each pixel.
+fn rgb_to_gray(rgb: &[u8]) -> Vec<u8> {
+    rgb.chunks_exact(3)
+        .map(|p| ((p[0] as u16 + p[1] as u16 + p[2] as u16 + 1) / 3) as u8)
+        .collect()
+}
+
+/// Median-filters every channel of a decoded image with the given radius.
+///
+/// Grayscale stays grayscale (with or without alpha); every other layout is
+/// filtered as 8-bit RGB, since transparent color images have already been
+/// flattened by then.
+fn median_filter_image(img: DynamicImage, radius: u32) -> Result<DynamicImage, ConversionError> {
+    let (width, height) = img.dimensions();
+    let filtered = match img.color() {
+        ColorType::L8 | ColorType::L16 => {
+            let data = median_filter(img.into_luma8().as_raw(), width, height, 1, radius);
+            GrayImage::from_raw(width, height, data).map(DynamicImage::ImageLuma8)
+        }
+        ColorType::La8 | ColorType::La16 => {
+            let data = median_filter(img.into_luma_alpha8().as_raw(), width, height, 2, radius);
+            GrayAlphaImage::from_raw(width, height, data).map(DynamicImage::ImageLumaA8)
+        }
+        _ => {
+            let data = median_filter(img.into_rgb8().as_raw(), width, height, 3, radius);
+            RgbImage::from_raw(width, height, data).map(DynamicImage::ImageRgb8)
+        }
+    };
+    filtered.ok_or_else(|| ConversionError::UnsupportedFormat("Failed to rebuild filtered image".to_string()))
+}
+
+/// Applies brightness and contrast to a decoded image, keeping its channel layout.
+///
+/// Grayscale stays grayscale (with or without alpha), and for images with an
+/// alpha channel only the color channels are adjusted while alpha is carried
+/// through untouched.
+fn adjust_image(img: DynamicImage, config: &ConversionConfig) -> Result<DynamicImage, ConversionError> {
+    let (width, height) = img.dimensions();
+    let adjusted = match img {
+        DynamicImage::ImageLuma8(gray) => {
+            let data = adjust_pixels(gray.as_raw(), 1, config);
+            GrayImage::from_raw(width, height, data).map(DynamicImage::ImageLuma8)
+        }
+        DynamicImage::ImageLumaA8(gray_alpha) => {
+            let data = adjust_pixels(gray_alpha.as_raw(), 2, config);
+            GrayAlphaImage::from_raw(width, height, data).map(DynamicImage::ImageLumaA8)
+        }
+        img if img.color().has_alpha() => {
+            let rgba = img.to_rgba8();
+            let data = adjust_pixels(rgba.as_raw(), 4, config);
+            RgbaImage::from_raw(width, height, data).map(DynamicImage::ImageRgba8)
+        }
+        img => {
+            let rgb = img.to_rgb8();
+            let data = adjust_pixels(rgb.as_raw(), 3, config);
+            RgbImage::from_raw(width, height, data).map(DynamicImage::ImageRgb8)
+        }
+    };
+    adjusted.ok_or_else(|| ConversionError::UnsupportedFormat("Failed to rebuild adjusted image".to_string()))
 }
 
 /// Converts a PNG file to our custom image format with optional preprocessing.
@@ -157,108 +922,649 @@ fn apply_adjustments(data: &[u8], brightness: i32, contrast: i32) -> Vec<u8> {
 /// # Returns
 ///
 /// Returns `Result<CustomImage, ConversionError>`.
+///
+/// # Errors
+///
+/// Resize dimensions are validated before the PNG is read, so an oversized
+/// request fails immediately:
+///
+/// ```
+/// use nor_image::converter::{png_to_custom, ConversionConfig, ConversionError};
+///
+/// let config = ConversionConfig {
+///     resize_width: Some(40_000),
+///     resize_height: Some(100),
+///     ..ConversionConfig::default()
+/// };
+/// let result = png_to_custom("missing.png", None, Some(config));
+/// assert!(matches!(
+///     result,
+///     Err(ConversionError::DimensionTooLarge { dimension: "width", value: 40_000, limit: 32_768 })
+/// ));
+/// ```
 pub fn png_to_custom<P: AsRef<Path>>(
     png_path: P,
     output_path: Option<P>,
     config: Option<ConversionConfig>,
 ) -> Result<CustomImage, ConversionError> {
-    let config = config.unwrap_or_default();
+    let config = config.unwrap_or_default().with_preset_applied();
+    config.validate_resize()?;
+    config.validate_target()?;
+    config.validate_median()?;
+    config.validate_quantize()?;
+    config.validate_storage()?;
+    config.validate_chunk_size()?;
+    config.check_adjustments()?;
+    if let Some(output_path) = &output_path {
+        config.check_output(output_path.as_ref())?;
+    }
     let path = png_path.as_ref();
 
-    println!("Loading PNG from {:?}", path);
+    info!("Loading PNG from {:?}", path);
+    let mut stage = Instant::now();
 
     // Attempt to load from cache if enabled
     if config.use_cache {
-        if let Ok(cached) = CachedImageLoader::load(path) {
-            println!("Loaded image from cache");
+        let cached = CachedImageLoader::load_with(path, config.streaming);
+        stage = config.finish_stage("cache lookup", stage);
+        if let Ok(cached) = cached {
+            debug!("Using cached image for {:?}", path);
+            info!("Loaded image from cache");
+            config.report_progress(1.0);
             return Ok((*cached).clone());
         }
     }
 
     // Process image with parallel chunks for better performance
     let img = image::open(path)?;
+    debug!("Decoded {}x{} {:?} PNG", img.width(), img.height(), img.color());
+    let mut icc_profile = read_icc_profile(path);
+    let img = match icc_profile.as_deref().filter(|_| config.to_srgb).map(|profile| to_srgb(&img, profile)) {
+        Some(Ok(converted)) => {
+            info!("Converted pixels from the embedded ICC profile to sRGB");
+            icc_profile = None;
+            converted
+        }
+        Some(Err(e)) => {
+            config.questionable(format!("{}; keeping the pixels and profile unconverted", e))?;
+            img
+        }
+        None => img,
+    };
+    let img = match config.crop_region(img.width(), img.height()) {
+        Some((x, y, w, h)) => {
+            debug!("Cropping to {}x{} at ({}, {})", w, h, x, y);
+            img.crop_imm(x, y, w, h)
+        }
+        None => img,
+    };
     let (width, height) = img.dimensions();
+    let target = config.target_dimensions(width, height)?;
+    // Grayscale sources with alpha keep it as `GrayAlpha`; other transparent
+    // images are flattened onto the background color.
+    let keep_alpha = matches!(img.color(), ColorType::La8 | ColorType::La16);
+    let img = if img.color().has_alpha() && !keep_alpha {
+        let background = config.background_color.unwrap_or(DEFAULT_BACKGROUND);
+        debug!("Flattening alpha onto {:?}", background);
+        flatten_alpha(&img, background)
+    } else {
+        img
+    };
+    stage = config.finish_stage("decode", stage);
+    let img = match config.median_filter {
+        Some(radius) if radius > 0 => {
+            debug!("Median filtering with radius {}", radius);
+            let img = median_filter_image(img, radius)?;
+            stage = config.finish_stage("denoise", stage);
+            img
+        }
+        _ => img,
+    };
+    config.report_progress(0.2);
+
+    if let Some((w, h)) = target {
+        debug!("Resizing {}x{} -> {}x{} (Lanczos3)", width, height, w, h);
+    }
+    if config.adjusts() {
+        debug!(
+            "Adjusting brightness={:?} contrast={:?} (rgb: {:?}, {:?})",
+            config.brightness, config.contrast, config.brightness_rgb, config.contrast_rgb
+        );
+    }
 
     // Process image into desired color type and size.
-    let processed_data = if config.force_grayscale {
-        println!("Converting image to grayscale.");
-        let gray_img = img.into_luma8();
-        let processed_img = if let (Some(w), Some(h)) = (config.resize_width, config.resize_height) {
+    let mut stored_gray = config.force_grayscale;
+    let mut processed_data = if keep_alpha {
+        let la_img = img.into_luma_alpha8();
+        let processed_img = if let Some((w, h)) = target {
+            imageops::resize(&la_img, w, h, imageops::FilterType::Lanczos3)
+        } else {
+            la_img
+        };
+        stage = config.finish_stage("resize", stage);
+        config.report_progress(0.4);
+        if config.adjusts() {
+            let img = DynamicImage::ImageLumaA8(processed_img);
+            adjust_image(img, &config)?.into_bytes()
+        } else {
+            processed_img.into_raw()
+        }
+    } else if config.force_grayscale {
+        info!("Converting image to grayscale.");
+        debug!("Grayscale method {:?}", config.grayscale_method);
+        let gray_img = config.grayscale_method.to_luma(img)?;
+        let processed_img = if let Some((w, h)) = target {
             imageops::resize(&gray_img, w, h, imageops::FilterType::Lanczos3)
         } else {
             gray_img
         };
+        stage = config.finish_stage("resize", stage);
+        config.report_progress(0.4);
         let raw_data = processed_img.into_raw();
-        if config.brightness != 0 || config.contrast != 0 {
-            apply_adjustments(&raw_data, config.brightness, config.contrast)
+        if config.adjusts() {
+            apply_adjustments(&raw_data, 1, &config)
         } else {
             raw_data
         }
     } else {
+        let has_color = img.color().has_color();
         let rgb_img = img.into_rgb8();
-        let processed_img = if let (Some(w), Some(h)) = (config.resize_width, config.resize_height) {
+        let processed_img = if let Some((w, h)) = target {
             imageops::resize(&rgb_img, w, h, imageops::FilterType::Lanczos3)
         } else {
             rgb_img
         };
-        let raw_data = processed_img.into_raw();
-        if config.brightness != 0 || config.contrast != 0 {
-            apply_adjustments(&raw_data, config.brightness, config.contrast)
+        stage = config.finish_stage("resize", stage);
+        config.report_progress(0.4);
+        let mut raw_data = processed_img.into_raw();
+        if config.auto_white_balance && has_color {
+            let gains = auto_white_balance(&mut raw_data);
+            debug!("White balance gains (gray world): {:?}", gains);
+        }
+        let raw_data = if config.adjusts() {
+            apply_adjustments(&raw_data, 3, &config)
         } else {
             raw_data
+        };
+        if config.auto_grayscale && is_grayscale(&raw_data, AUTO_GRAYSCALE_TOLERANCE) {
+            info!("Image has no color; storing it as grayscale.");
+            stored_gray = true;
+            rgb_to_gray(&raw_data)
+        } else {
+            if config.auto_grayscale {
+                info!("Image has color; keeping RGB.");
+            }
+            raw_data
         }
     };
 
-    let (final_width, final_height) = if let (Some(w), Some(h)) = (config.resize_width, config.resize_height) {
-        (w, h)
+    stage = config.finish_stage("adjust", stage);
+
+    let (final_width, final_height) = target.unwrap_or((width, height));
+    let color_type = if keep_alpha {
+        CustomColorType::GrayAlpha
+    } else if stored_gray {
+        CustomColorType::Gray
     } else {
-        (width, height)
+        CustomColorType::Rgb
     };
+    if let Some(colors) = config.quantize_colors {
+        debug!("Quantizing to {} colors with seed {}", colors, config.rng_seed());
+        quantize_colors(&mut processed_data, color_type, colors, config.rng_seed());
+        stage = config.finish_stage("quantize", stage);
+    }
+    if let Some(watermark) = &config.watermark {
+        debug!("Applying watermark {:?}", watermark);
+        apply_watermark(&mut processed_data, final_width, final_height, color_type, watermark)?;
+        stage = config.finish_stage("watermark", stage);
+    }
+    config.report_progress(0.6);
 
+    let mut metadata = ImageMetadata::default();
+    if !config.creation_date_now {
+        if let Some(date) = source_creation_date(path) {
+            metadata.creation_date = date;
+        }
+    }
+    if config.preserve_text {
+        let mut text = read_png_text(path)?;
+        debug!("Preserved {} PNG text chunks", text.len());
+        metadata.title = text.remove(PNG_TITLE);
+        metadata.description = text.remove(PNG_DESCRIPTION);
+        metadata.copyright = text.remove(PNG_COPYRIGHT);
+        metadata.custom_fields = text;
+    }
     let mut custom_img = CustomImage::new(
         final_width,
         final_height,
-        if config.force_grayscale { CustomColorType::Gray } else { CustomColorType::Rgb },
+        color_type,
         processed_data,
-        Some(ImageMetadata::default()),
-        config.compression,
+        Some(metadata),
+        CompressionType::None,
     )?;
+    custom_img.icc_profile = icc_profile
+        .filter(|profile| icc_profile_fits(profile, color_type != CustomColorType::Rgb));
+    custom_img.lossy_quality = config.lossy_quality;
 
-    // Apply compression if required.
-    if config.compression != CompressionType::None {
-        println!("Applying compression: {:?}", config.compression);
-        let compressed_data = match config.compression {
-            CompressionType::RLE => {
-                let chunk_size = if config.force_grayscale { 8 } else { 24 };
-                custom_img.data.chunks(chunk_size)
-                    .flat_map(|chunk| CustomImage::compress_rle(chunk))
-                    .collect()
+    // Build pyramid levels from the uncompressed pixels; they are stored
+    // with whichever compression the full image ends up using.
+    let pyramid = match config.pyramid_levels {
+        Some(count) => {
+            let levels = custom_img.build_pyramid(count)?;
+            debug!("Built {} pyramid levels", levels.len());
+            if (levels.len() as u32) < count {
+                config.questionable(format!(
+                    "only {} of {} pyramid levels fit a {}x{} image",
+                    levels.len(),
+                    count,
+                    final_width,
+                    final_height
+                ))?;
             }
-            CompressionType::Delta => CustomImage::compress_delta(&custom_img.data),
-            CompressionType::Lossy => custom_img.compress_lossy(90)?,
-            CompressionType::None => custom_img.data.clone(),
+            stage = config.finish_stage("pyramid", stage);
+            levels
+        }
+        None => Vec::new(),
+    };
+
+    // Apply compression if required.
+    if let Some(tile_size) = config.tile_size {
+        let compression = if config.auto_compression {
+            smallest_encoding(&custom_img, &config, false)?.0
+        } else {
+            config.compression
         };
+        if compression == CompressionType::None {
+            return Err(ConversionError::InvalidOption(
+                "Tiled storage requires a compression type".to_string(),
+            ));
+        }
+        let uncompressed_len = custom_img.data.len();
+        custom_img = custom_img.tiled(tile_size, compression)?;
+        info!(
+            "Stored as {}x{} tiles with {:?} ({} -> {} bytes)",
+            tile_size,
+            tile_size,
+            compression,
+            uncompressed_len,
+            custom_img.data.len()
+        );
+    }
+    let compression = if custom_img.tiles.is_some() {
+        custom_img.compression
+    } else if let Some(target) = config.target_bytes {
+        custom_img = fit_to_target(&custom_img, target, &config)?;
+        CompressionType::Lossy
+    } else if config.auto_compression {
+        let (compression, compressed_data) = smallest_encoding(&custom_img, &config, true)?;
+        info!(
+            "Auto compression chose {:?} ({} -> {} bytes)",
+            compression,
+            custom_img.data.len(),
+            compressed_data.len()
+        );
+        custom_img.data = compressed_data;
+        compression
+    } else if config.compression != CompressionType::None {
+        info!("Applying compression: {:?}", config.compression);
+        let compressed_data = encode_data(&custom_img, config.compression, &config)?;
+        debug!(
+            "Compressed {} -> {} bytes with {:?}",
+            custom_img.data.len(),
+            compressed_data.len(),
+            config.compression
+        );
         custom_img.data = compressed_data;
-        custom_img.compression = config.compression;
+        config.compression
+    } else {
+        CompressionType::None
+    };
+    custom_img.compression = compression;
+    if compression != CompressionType::None && custom_img.compression_ratio() < 1.0 {
+        config.questionable(format!(
+            "{:?} compression expanded the pixel data from {} to {} bytes",
+            compression,
+            custom_img.uncompressed_len(),
+            custom_img.data.len()
+        ))?;
     }
+    custom_img.set_pyramid(&pyramid)?;
+    custom_img.checksummed = !config.skip_checksum;
+    custom_img.footer = config.footer_magic;
+    custom_img.metadata_capacity = config.reserve_metadata;
+    stage = config.finish_stage("compress", stage);
+    config.report_progress(0.8);
 
     if let Some(output_path) = output_path {
-        println!("Saving converted image to {:?}", output_path.as_ref());
-        let mut file = File::create(output_path)?;
-        let bytes = custom_img.to_bytes()?;
-        file.write_all(&bytes)?;
+        info!("Saving converted image to {:?}", output_path.as_ref());
+        write_atomic(output_path, |writer| custom_img.write_to(writer))?;
+        trace!("Flushed output file");
+        config.finish_stage("write + checksum", stage);
     }
 
     if config.use_cache {
-        let _ = CachedImageLoader::load(path);
+        let _ = CachedImageLoader::load_with(path, config.streaming);
     }
 
-    println!("PNG conversion complete.");
+    config.report_progress(1.0);
+    info!("PNG conversion complete.");
     Ok(custom_img)
 }
 
+/// Most lossy encodings `fit_to_target` tries before settling.
+const TARGET_SEARCH_STEPS: u32 = 8;
+
+/// Lossy-encodes an uncompressed image so its serialized size is at most
+/// `target` bytes, at the highest quality the search can manage.
+///
+/// `config.lossy_quality` is tried first; otherwise lower qualities are
+/// binary searched. If no tried quality fits, the smallest encoding is
+/// returned and the miss is reported as questionable.
+fn fit_to_target(
+    image: &CustomImage,
+    target: usize,
+    config: &ConversionConfig,
+) -> Result<CustomImage, ConversionError> {
+    let encode = |quality: u8| -> Result<(CustomImage, usize), ConversionError> {
+        let mut candidate = CustomImage { lossy_quality: quality, ..image.clone() };
+        candidate.data = encode_data(&candidate, CompressionType::Lossy, config)?;
+        candidate.compression = CompressionType::Lossy;
+        // Match the header layout the output will be written with.
+        candidate.checksummed = !config.skip_checksum;
+        candidate.footer = config.footer_magic;
+        candidate.metadata_capacity = config.reserve_metadata;
+        let size = candidate.to_bytes()?.len();
+        trace!("Lossy at quality {}: {} bytes", quality, size);
+        Ok((candidate, size))
+    };
+
+    let (full, size) = encode(config.lossy_quality)?;
+    if size <= target {
+        info!("Lossy output is {} bytes, within the {} byte target", size, target);
+        return Ok(full);
+    }
+
+    // Search the qualities below the configured one; `high` never fits.
+    // Halving 1-100 takes at most 7 steps, so quality 1 is always reached.
+    let (mut low, mut high) = (0, config.lossy_quality);
+    let mut fitting: Option<(CustomImage, usize)> = None;
+    let mut smallest = (full, size);
+    for _ in 0..TARGET_SEARCH_STEPS {
+        if high - low <= 1 {
+            break;
+        }
+        let quality = low + (high - low) / 2;
+        let (candidate, size) = encode(quality)?;
+        if size <= target {
+            low = quality;
+            fitting = Some((candidate, size));
+        } else {
+            high = quality;
+            if size < smallest.1 {
+                smallest = (candidate, size);
+            }
+        }
+    }
+
+    match fitting {
+        Some((fitted, size)) => {
+            info!(
+                "Lowered lossy quality {} -> {} to fit the {} byte target ({} bytes)",
+                config.lossy_quality, fitted.lossy_quality, target, size
+            );
+            Ok(fitted)
+        }
+        None => {
+            let (closest, size) = smallest;
+            config.questionable(format!(
+                "could not fit {} bytes; the closest is {} bytes at lossy quality {}",
+                target, size, closest.lossy_quality
+            ))?;
+            Ok(closest)
+        }
+    }
+}
+
+/// Encodes the uncompressed pixel data of `image` with the given codec.
+fn encode_data(
+    image: &CustomImage,
+    compression: CompressionType,
+    config: &ConversionConfig,
+) -> Result<Vec<u8>, ConversionError> {
+    Ok(match compression {
+        CompressionType::RLE => {
+            let chunk_size = if config.force_grayscale { 8 } else { 24 };
+            trace!("RLE-encoding in {} byte chunks", chunk_size);
+            image.data.chunks(chunk_size)
+                .flat_map(CustomImage::compress_rle)
+                .collect()
+        }
+        CompressionType::Delta => CustomImage::compress_delta(&image.data),
+        CompressionType::Lossy => image.compress_lossy(image.lossy_quality)?,
+        CompressionType::RleVarint => CustomImage::compress_rle_varint(&image.data),
+        CompressionType::None => image.data.clone(),
+    })
+}
+
+/// Encodes the image with every candidate codec and returns the smallest
+/// result. Lossy is only a candidate when `config.auto_allow_lossy` is set;
+/// ties go to the simpler codec. Storing uncompressed is only a candidate
+/// when `allow_none` is set.
+fn smallest_encoding(
+    image: &CustomImage,
+    config: &ConversionConfig,
+    allow_none: bool,
+) -> Result<(CompressionType, Vec<u8>), ConversionError> {
+    let mut candidates = vec![
+        CompressionType::None,
+        CompressionType::RLE,
+        CompressionType::RleVarint,
+        CompressionType::Delta,
+    ];
+    if !allow_none {
+        candidates.retain(|&c| c != CompressionType::None);
+    }
+    if config.auto_allow_lossy {
+        candidates.push(CompressionType::Lossy);
+    }
+
+    let mut best: Option<(CompressionType, Vec<u8>)> = None;
+    for compression in candidates {
+        let data = encode_data(image, compression, config)?;
+        debug!("Auto compression candidate {:?}: {} bytes", compression, data.len());
+        if best.as_ref().is_none_or(|(_, best_data)| data.len() < best_data.len()) {
+            best = Some((compression, data));
+        }
+    }
+    Ok(best.unwrap_or((CompressionType::None, image.data.clone())))
+}
+
+/// Decompresses a custom image and applies the resize and brightness/contrast
+/// postprocessing shared by the export paths.
+///
+/// When resizing an image that has pyramid levels, the smallest level at
+/// least as large as the target is decoded and scaled instead of the full
+/// image.
+///
+/// Reports progress at 0.25, 0.5 and 0.75 as each stage completes.
+fn decode_for_export(
+    custom_img: &CustomImage,
+    config: &ConversionConfig,
+) -> Result<DynamicImage, ConversionError> {
+    config.validate_resize()?;
+    config.validate_chunk_size()?;
+    config.check_adjustments()?;
+    let target = config.target_dimensions(custom_img.width, custom_img.height)?;
+    let img_data = if let Some((width, height)) = target {
+        let level = custom_img.smallest_level_for(width, height);
+        if level > 0 {
+            debug!("Decoding pyramid level {} for a {:?} target", level, target);
+        }
+        debug!("Resizing {}x{} -> {}x{} (Lanczos3)", custom_img.width, custom_img.height, width, height);
+        custom_img.resized(width, height, imageops::FilterType::Lanczos3)?
+    } else {
+        let mut img_data = custom_img.clone();
+        img_data.levels = Vec::new();
+        if img_data.compression != CompressionType::None {
+            ParallelImageProcessor::decompress(&mut img_data)?;
+        }
+        img_data
+    };
+    config.report_progress(0.25);
+
+    let mut img: DynamicImage = match img_data.color_type {
+        CustomColorType::Gray => {
+            let gray_img = GrayImage::from_raw(img_data.width, img_data.height, img_data.data)
+                .ok_or_else(|| ConversionError::UnsupportedFormat("Failed to create grayscale image".to_string()))?;
+            DynamicImage::ImageLuma8(gray_img)
+        }
+        CustomColorType::Rgb => {
+            let rgb_img = RgbImage::from_raw(img_data.width, img_data.height, img_data.data)
+                .ok_or_else(|| ConversionError::UnsupportedFormat("Failed to create RGB image".to_string()))?;
+            DynamicImage::ImageRgb8(rgb_img)
+        }
+        CustomColorType::GrayAlpha => {
+            let gray_alpha_img = GrayAlphaImage::from_raw(img_data.width, img_data.height, img_data.data)
+                .ok_or_else(|| ConversionError::UnsupportedFormat("Failed to create grayscale-alpha image".to_string()))?;
+            DynamicImage::ImageLumaA8(gray_alpha_img)
+        }
+    };
+
+    config.report_progress(0.5);
+
+    // Apply brightness/contrast adjustments if needed.
+    if config.adjusts() {
+        debug!("Adjusting brightness={:?} contrast={:?}", config.brightness, config.contrast);
+        img = adjust_image(img, config)?;
+    }
+    config.report_progress(0.75);
+
+    Ok(img)
+}
+
+/// Output file formats an image can be exported to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// PNG, written by `custom_to_png`.
+    Png,
+    /// Baseline JPEG. Transparent pixels are flattened onto
+    /// `ConversionConfig::background_color`, since JPEG has no alpha.
+    Jpeg,
+    /// Uncompressed BMP.
+    Bmp,
+    /// WebP, written by `custom_to_webp`.
+    WebP,
+}
+
+impl ExportFormat {
+    /// Picks the output format from a path's extension (case-insensitive).
+    ///
+    /// # Errors
+    ///
+    /// Returns `UnsupportedFormat` if the extension is missing or names a
+    /// format that cannot be written.
+    ///
+    /// ```rust
+    /// use std::path::Path;
+    /// use nor_image::converter::ExportFormat;
+    ///
+    /// assert_eq!(ExportFormat::from_path(Path::new("out.JPG"))?, ExportFormat::Jpeg);
+    /// assert_eq!(ExportFormat::from_path(Path::new("out.bmp"))?, ExportFormat::Bmp);
+    /// assert!(ExportFormat::from_path(Path::new("out.tiff")).is_err());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn from_path(path: &Path) -> Result<Self, ConversionError> {
+        let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+        match ImageFormat::from_extension(extension) {
+            Some(ImageFormat::Png) => Ok(ExportFormat::Png),
+            Some(ImageFormat::WebP) => Ok(ExportFormat::WebP),
+            Some(format @ ImageFormat::Jpeg) if format.writing_enabled() => Ok(ExportFormat::Jpeg),
+            Some(format @ ImageFormat::Bmp) if format.writing_enabled() => Ok(ExportFormat::Bmp),
+            _ => Err(ConversionError::UnsupportedFormat(format!(
+                "cannot write {}; expected a .png, .jpg, .jpeg, .bmp or .webp extension",
+                path.display()
+            ))),
+        }
+    }
+}
+
+/// Exports a custom image to PNG, JPEG, BMP or WebP with optional
+/// postprocessing.
+///
+/// The format is taken from `format`, or from the extension of `path` when
+/// `None`. PNG and WebP output go through `custom_to_png` and
+/// `custom_to_webp`; JPEG and BMP use the `image` encoders.
+pub fn custom_to_file<P: AsRef<Path>>(
+    custom_img: &CustomImage,
+    path: P,
+    format: Option<ExportFormat>,
+    config: Option<ConversionConfig>,
+) -> Result<(), ConversionError> {
+    let path = path.as_ref();
+    let format = match format {
+        Some(format) => format,
+        None => ExportFormat::from_path(path)?,
+    };
+    let image_format = match format {
+        ExportFormat::Png => return custom_to_png(custom_img, path, config),
+        ExportFormat::WebP => return custom_to_webp(custom_img, path, config),
+        ExportFormat::Jpeg => ImageFormat::Jpeg,
+        ExportFormat::Bmp => ImageFormat::Bmp,
+    };
+
+    let config = config.unwrap_or_default();
+    config.check_output(path)?;
+    info!("Converting custom image to {:?} at {:?}", format, path);
+
+    let mut img = decode_for_export(custom_img, &config)?;
+    if format == ExportFormat::Jpeg && img.color().has_alpha() {
+        let background = config.background_color.unwrap_or(DEFAULT_BACKGROUND);
+        debug!("Flattening alpha onto {:?} for JPEG output", background);
+        img = flatten_alpha(&img, background);
+    }
+    write_atomic(path, |writer| img.write_to(writer, image_format))?;
+    config.report_progress(1.0);
+
+    info!("{:?} conversion complete.", format);
+    Ok(())
+}
+
+/// Decoded size, in bytes, from which `custom_to_png` streams an image into
+/// the PNG encoder band by band instead of decoding it in memory first.
+pub const STREAMING_EXPORT_THRESHOLD: usize = 64 * 1024 * 1024;
+
 /// Converts our custom image format to a PNG file with optional postprocessing.
 ///
+/// Images of at least `STREAMING_EXPORT_THRESHOLD` decoded bytes that are
+/// not being resized are written with `custom_to_png_streaming`, so memory
+/// stays bounded for very large exports.
+///
+/// Brightness and contrast are applied to the raw pixel data in parallel
+/// chunks of `config.chunk_size` bytes, with the same result as mapping each
+/// pixel through [`adjustment_lut`](crate::adjust::adjustment_lut) in turn:
+///
+/// ```rust
+/// use nor_image::adjust::{adjustment_lut, ContrastCurve};
+/// use nor_image::converter::{custom_to_png, ConversionConfig};
+/// use nor_image::format::{CustomImage, ColorType, CompressionType};
+///
+/// // Noise plus a gradient, spread over many uneven chunks.
+/// let (width, height) = (97u32, 61u32);
+/// let data: Vec<u8> = (0..width * height * 3).map(|i| ((i * 7 % 256) ^ (i / 97)) as u8).collect();
+/// let image = CustomImage::new(width, height, ColorType::Rgb, data.clone(), None, CompressionType::None)?;
+/// let config = ConversionConfig { brightness: Some(25), contrast: Some(40), chunk_size: 1000, ..ConversionConfig::default() };
+///
+/// let path = std::env::temp_dir().join(format!("parallel-adjust-{}.png", std::process::id()));
+/// custom_to_png(&image, &path, Some(config))?;
+/// let parallel = image::open(&path)?.to_rgb8().into_raw();
+/// std::fs::remove_file(&path)?;
+///
+/// let lut = adjustment_lut(25, 40, ContrastCurve::Linear);
+/// let serial: Vec<u8> = data.iter().map(|&value| lut[value as usize]).collect();
+/// assert_eq!(parallel, serial);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
 /// # Arguments
 ///
 /// * `custom_img` - The source custom image.
@@ -275,67 +1581,771 @@ pub fn custom_to_png<P: AsRef<Path>>(
 ) -> Result<(), ConversionError> {
     let config = config.unwrap_or_default();
     let path = png_path.as_ref();
+    config.check_output(path)?;
 
-    println!("Converting custom image to PNG at {:?}", path);
+    let resizes = config.target_dimensions(custom_img.width, custom_img.height)?.is_some();
+    if !resizes && custom_img.uncompressed_len() >= STREAMING_EXPORT_THRESHOLD {
+        return custom_to_png_streaming(custom_img, path, Some(config));
+    }
 
-    let mut img_data = custom_img.clone();
-    if img_data.compression != CompressionType::None {
-        ParallelImageProcessor::decompress(&mut img_data)?;
+    info!("Converting custom image to PNG at {:?}", path);
+
+    let png_bytes = custom_to_png_bytes(custom_img, Some(config))?;
+    write_atomic(path, |writer| writer.write_all(&png_bytes))?;
+
+    info!("PNG conversion complete.");
+    Ok(())
+}
+
+/// Converts our custom image format to a PNG file without holding the
+/// decoded image in memory.
+///
+/// Pixels are decoded in bands and fed straight to the PNG encoder: tiled
+/// images a row of tiles at a time, chunked RLE data a chunk at a time, and
+/// uncompressed data in slices of `config.chunk_size` bytes. Other
+/// compressions are decoded in one piece, but are still never copied into
+/// a second image or an in-memory PNG. Brightness and contrast are applied
+/// per band; resizing is not supported here.
+///
+/// The output matches `custom_to_png` for the same image and config:
+///
+/// ```rust
+/// use nor_image::converter::{custom_to_png, custom_to_png_streaming, ConversionConfig};
+/// use nor_image::format::{CustomImage, ColorType, CompressionType};
+///
+/// let data: Vec<u8> = (0..48 * 40 * 3).map(|i| (i / 7 % 256) as u8).collect();
+/// let image = CustomImage::new(48, 40, ColorType::Rgb, data, None, CompressionType::None)?
+///     .tiled(16, CompressionType::RLE)?;
+/// let config = ConversionConfig { brightness: Some(10), ..ConversionConfig::default() };
+///
+/// let dir = std::env::temp_dir();
+/// let id = std::process::id();
+/// let (streamed, buffered) = (
+///     dir.join(format!("streamed-doc-{}.png", id)),
+///     dir.join(format!("buffered-doc-{}.png", id)),
+/// );
+/// custom_to_png_streaming(&image, &streamed, Some(config.clone()))?;
+/// custom_to_png(&image, &buffered, Some(config))?;
+/// assert_eq!(image::open(&streamed)?.to_rgb8(), image::open(&buffered)?.to_rgb8());
+/// # std::fs::remove_file(streamed)?;
+/// # std::fs::remove_file(buffered)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// # Errors
+///
+/// Returns `InvalidOption` if the config asks for a resize, and a format
+/// error if the pixel data does not decode to the size in the header.
+pub fn custom_to_png_streaming<P: AsRef<Path>>(
+    custom_img: &CustomImage,
+    png_path: P,
+    config: Option<ConversionConfig>,
+) -> Result<(), ConversionError> {
+    let config = config.unwrap_or_default();
+    let path = png_path.as_ref();
+    config.check_output(path)?;
+    config.validate_chunk_size()?;
+    config.check_adjustments()?;
+    if config.target_dimensions(custom_img.width, custom_img.height)?.is_some() {
+        return Err(ConversionError::InvalidOption("streaming PNG export cannot resize".to_string()));
     }
 
-    let mut img: DynamicImage = match img_data.color_type {
-        CustomColorType::Gray => {
-            let gray_img = GrayImage::from_raw(img_data.width, img_data.height, img_data.data)
-                .ok_or_else(|| ConversionError::UnsupportedFormat("Failed to create grayscale image".to_string()))?;
-            DynamicImage::ImageLuma8(gray_img)
+    info!("Streaming custom image to PNG at {:?}", path);
+
+    let channels = custom_img.color_type.channels() as usize;
+    let png_color_type = match custom_img.color_type {
+        CustomColorType::Gray => png::ColorType::Grayscale,
+        CustomColorType::Rgb => png::ColorType::Rgb,
+        CustomColorType::GrayAlpha => png::ColorType::GrayscaleAlpha,
+    };
+    let total = custom_img.uncompressed_len();
+    let band_size = config.chunk_size.div_ceil(channels) * channels;
+    write_atomic(path, |writer| {
+        let size = (custom_img.width, custom_img.height);
+        let encoder = png_encoder(writer, custom_img, size, png_color_type, &config)?;
+        let mut png_writer = encoder.write_header()?;
+        let mut stream = png_writer.stream_writer()?;
+        // Bytes of a pixel split across two bands, held back so adjustments
+        // always see whole pixels.
+        let mut pending = Vec::new();
+        let mut written = 0;
+        decode_bands(custom_img, band_size, |band, fraction| {
+            written += band.len();
+            if written > total {
+                return Err(FormatError::DataLengthMismatch { expected: total, actual: written }.into());
+            }
+            if config.adjusts() {
+                pending.extend_from_slice(band);
+                let whole = pending.len() - pending.len() % channels;
+                stream.write_all(&adjust_pixels(&pending[..whole], channels, &config))?;
+                pending.drain(..whole);
+            } else {
+                stream.write_all(band)?;
+            }
+            config.report_progress(fraction * 0.75);
+            Ok(())
+        })?;
+        if written != total {
+            return Err(FormatError::DataLengthMismatch { expected: total, actual: written }.into());
         }
-        CustomColorType::Rgb => {
-            let rgb_img = RgbImage::from_raw(img_data.width, img_data.height, img_data.data)
-                .ok_or_else(|| ConversionError::UnsupportedFormat("Failed to create RGB image".to_string()))?;
-            DynamicImage::ImageRgb8(rgb_img)
+        stream.finish()?;
+        png_writer.finish()?;
+        Ok::<(), ConversionError>(())
+    })?;
+    config.report_progress(1.0);
+
+    info!("PNG conversion complete.");
+    Ok(())
+}
+
+/// Decodes the pixel data of `custom_img` from top to bottom in bands,
+/// passing each to `write` along with the fraction of the data decoded so
+/// far. Bands are contiguous but need not end on a row or pixel boundary.
+///
+/// Tiled images are decoded a row of tiles at a time, chunked RLE data a
+/// chunk at a time, and uncompressed data is passed through in slices of
+/// `band_size` bytes. Other compressions have no independent parts and are
+/// decoded in one piece.
+fn decode_bands<F>(custom_img: &CustomImage, band_size: usize, mut write: F) -> Result<(), ConversionError>
+where
+    F: FnMut(&[u8], f32) -> Result<(), ConversionError>,
+{
+    let total = custom_img.uncompressed_len();
+    if let Some(index) = &custom_img.tiles {
+        for y in (0..custom_img.height).step_by(index.tile_size as usize) {
+            let rows = index.tile_size.min(custom_img.height - y);
+            let band = custom_img.decode_region(Region::new(0, y, custom_img.width, rows))?;
+            trace!("Decoded rows {}..{} from tiles", y, y + rows);
+            write(&band.data, (y + rows) as f32 / custom_img.height as f32)?;
+        }
+    } else if let Some(index) = &custom_img.chunks {
+        let chunk_size = index.chunk_size as usize;
+        let mut decoded = 0;
+        for range in index.ranges() {
+            let compressed = custom_img.data.get(range).ok_or(FormatError::DataTooShort)?;
+            let expected_len = chunk_size.min(total - decoded);
+            let chunk = match custom_img.compression {
+                CompressionType::RLE => CustomImage::decompress_rle_capped(compressed, expected_len)?,
+                CompressionType::RleVarint => CustomImage::decompress_rle_varint(compressed, expected_len)?,
+                other => {
+                    return Err(FormatError::CompressionError(format!("{:?} data cannot be chunked", other)).into())
+                }
+            };
+            if chunk.len() != expected_len {
+                return Err(FormatError::DataLengthMismatch { expected: expected_len, actual: chunk.len() }.into());
+            }
+            decoded += chunk.len();
+            write(&chunk, decoded as f32 / total.max(1) as f32)?;
         }
+    } else if custom_img.compression == CompressionType::None {
+        let mut decoded = 0;
+        for band in custom_img.data.chunks(band_size) {
+            decoded += band.len();
+            write(band, decoded as f32 / total.max(1) as f32)?;
+        }
+    } else {
+        let data = custom_img.decompress_pixels(
+            &custom_img.data,
+            custom_img.width,
+            custom_img.height,
+            custom_img.compression,
+        )?;
+        write(&data, 1.0)?;
+    }
+    Ok(())
+}
+
+/// Creates a PNG encoder for a `width`x`height` export of `custom_img` with
+/// the configured compression effort, its ICC profile when it fits
+/// `color_type`, and its text fields.
+fn png_encoder<'a, W: Write>(
+    writer: W,
+    custom_img: &CustomImage,
+    (width, height): (u32, u32),
+    color_type: png::ColorType,
+    config: &ConversionConfig,
+) -> Result<png::Encoder<'a, W>, ConversionError> {
+    let (compression, filter, adaptive_filter) = config.png_compression.encoder_settings();
+    debug!("Encoding PNG with {:?} compression", config.png_compression);
+    let mut info = png::Info::with_size(width, height);
+    let gray = matches!(color_type, png::ColorType::Grayscale | png::ColorType::GrayscaleAlpha);
+    if let Some(profile) = custom_img.icc_profile().filter(|profile| icc_profile_fits(profile, gray)) {
+        info.icc_profile = Some(profile.to_vec().into());
+    }
+    let mut encoder = png::Encoder::with_info(writer, info)?;
+    encoder.set_color(color_type);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_compression(compression);
+    encoder.set_filter(filter);
+    encoder.set_adaptive_filter(adaptive_filter);
+    add_png_text(&mut encoder, &png_text(&custom_img.metadata, config.preserve_text), config)?;
+    Ok(encoder)
+}
+
+/// Converts our custom image format to PNG-encoded bytes in memory, with
+/// the same postprocessing as `custom_to_png`.
+///
+/// Useful for serving images without going through a temporary file.
+///
+/// ```rust
+/// use nor_image::converter::custom_to_png_bytes;
+/// use nor_image::format::{CustomImage, ColorType, CompressionType};
+///
+/// let image = CustomImage::new(2, 2, ColorType::Gray, vec![0, 64, 128, 255], None, CompressionType::None)?;
+/// let png = custom_to_png_bytes(&image, None)?;
+/// assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// Resizing keeps the color type, so a grayscale image is still written as
+/// an 8-bit grayscale PNG:
+///
+/// ```rust
+/// use nor_image::converter::{custom_to_png_bytes, ConversionConfig};
+/// use nor_image::format::{CustomImage, ColorType, CompressionType};
+///
+/// let config = ConversionConfig { resize_width: Some(5), resize_height: Some(3), ..ConversionConfig::default() };
+/// for (color_type, expected) in [(ColorType::Gray, image::ColorType::L8), (ColorType::Rgb, image::ColorType::Rgb8)] {
+///     let data = vec![90; 10 * 6 * color_type.channels() as usize];
+///     let image = CustomImage::new(10, 6, color_type, data, None, CompressionType::None)?;
+///     let png = image::load_from_memory(&custom_to_png_bytes(&image, Some(config.clone()))?)?;
+///     assert_eq!((png.width(), png.height()), (5, 3));
+///     assert_eq!(png.color(), expected);
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// Brightness and contrast compose with resizing and leave alpha alone:
+///
+/// ```rust
+/// use nor_image::converter::{custom_to_png_bytes, ConversionConfig};
+/// use nor_image::format::{CustomImage, ColorType, CompressionType};
+///
+/// // Gray+alpha with a horizontal gray ramp and a vertical alpha ramp.
+/// let data: Vec<u8> = (0..16 * 12u32).flat_map(|i| [(i % 16 * 10) as u8, (i / 16 * 20) as u8]).collect();
+/// let image = CustomImage::new(16, 12, ColorType::GrayAlpha, data, None, CompressionType::None)?;
+/// let resize = ConversionConfig { resize_width: Some(8), resize_height: Some(6), ..ConversionConfig::default() };
+/// let brighten = ConversionConfig { brightness: Some(40), ..resize.clone() };
+///
+/// let decode = |config| -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+///     let png = image::load_from_memory(&custom_to_png_bytes(&image, Some(config))?)?;
+///     assert_eq!(png.color(), image::ColorType::La8);
+///     Ok(png.into_luma_alpha8().into_raw())
+/// };
+/// let (plain, brightened) = (decode(resize)?, decode(brighten)?);
+/// assert_eq!(plain.len(), 8 * 6 * 2);
+/// for (before, after) in plain.chunks_exact(2).zip(brightened.chunks_exact(2)) {
+///     assert_eq!(after[0], before[0].saturating_add(40));
+///     assert_eq!(after[1], before[1]);
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn custom_to_png_bytes(
+    custom_img: &CustomImage,
+    config: Option<ConversionConfig>,
+) -> Result<Vec<u8>, ConversionError> {
+    let config = config.unwrap_or_default();
+    let img = decode_for_export(custom_img, &config)?;
+
+    let mut png_bytes = Vec::new();
+    let (png_color_type, img) = match img {
+        DynamicImage::ImageLuma8(_) => (png::ColorType::Grayscale, img),
+        DynamicImage::ImageLumaA8(_) => (png::ColorType::GrayscaleAlpha, img),
+        DynamicImage::ImageRgba8(_) => (png::ColorType::Rgba, img),
+        img => (png::ColorType::Rgb, DynamicImage::ImageRgb8(img.into_rgb8())),
     };
+    let (width, height) = img.dimensions();
+    let encoder = png_encoder(&mut png_bytes, custom_img, (width, height), png_color_type, &config)?;
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(img.as_bytes())?;
+    writer.finish()?;
+    config.report_progress(1.0);
 
-    // Resize if required.
-    if let (Some(width), Some(height)) = (config.resize_width, config.resize_height) {
-        img = DynamicImage::ImageRgba8(imageops::resize(&img, width, height, imageops::FilterType::Lanczos3));
+    Ok(png_bytes)
+}
+
+/// Returns when an imported file was created, as a Unix timestamp: its EXIF
+/// `DateTimeOriginal` when it has one, otherwise its modification time.
+fn source_creation_date(path: &Path) -> Option<u64> {
+    if let Some(date) = exif_date_time_original(path) {
+        debug!("Using EXIF DateTimeOriginal {} as the creation date", date);
+        return Some(date);
     }
+    let modified = std::fs::metadata(path).and_then(|meta| meta.modified()).ok()?;
+    let date = modified.duration_since(SystemTime::UNIX_EPOCH).ok()?.as_secs();
+    debug!("Using the source modification time {} as the creation date", date);
+    Some(date)
+}
 
-    // Apply brightness/contrast adjustments if needed.
-    if config.brightness != 0 || config.contrast != 0 {
-        let mut buffer = img.to_rgb8();
-        for pixel in buffer.pixels_mut() {
-            for channel in pixel.0.iter_mut() {
-                let mut value = (*channel as f32 / 127.5) - 1.0;
-                if config.contrast != 0 {
-                    let contrast_factor = (config.contrast as f32 + 255.0) / 255.0;
-                    value *= contrast_factor;
-                }
-                if config.brightness != 0 {
-                    value += (config.brightness as f32) / 127.5;
-                }
-                *channel = ((value.clamp(-1.0, 1.0) + 1.0) * 127.5) as u8;
-            }
+/// Reads the EXIF `DateTimeOriginal` of an image file as a Unix timestamp.
+///
+/// The value is taken as UTC unless `OffsetTimeOriginal` gives its time
+/// zone. Returns `None` if the file has no EXIF data, the tag is missing or
+/// malformed, or the date is before 1970.
+fn exif_date_time_original(path: &Path) -> Option<u64> {
+    let mut reader = BufReader::new(File::open(path).ok()?);
+    let exif = exif::Reader::new().read_from_container(&mut reader).ok()?;
+    let ascii = |tag| match exif.get_field(tag, exif::In::PRIMARY).map(|field| &field.value) {
+        Some(exif::Value::Ascii(values)) => values.first().cloned(),
+        _ => None,
+    };
+
+    let mut date = exif::DateTime::from_ascii(&ascii(exif::Tag::DateTimeOriginal)?).ok()?;
+    if let Some(offset) = ascii(exif::Tag::OffsetTimeOriginal) {
+        // A malformed offset leaves the time as UTC.
+        let _ = date.parse_offset(&offset);
+    }
+    unix_timestamp(&date)
+}
+
+/// Converts a calendar date and time to seconds since the Unix epoch,
+/// or `None` if a field is out of range or the time is before 1970.
+fn unix_timestamp(date: &exif::DateTime) -> Option<u64> {
+    if !(1..=12).contains(&date.month) || !(1..=31).contains(&date.day) || date.hour > 23 || date.minute > 59 || date.second > 60 {
+        return None;
+    }
+    // Days from the epoch to the civil date, counting years from March so
+    // the leap day falls at the end (Howard Hinnant's `days_from_civil`).
+    let (month, day) = (date.month as i64, date.day as i64);
+    let year = date.year as i64 - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    let seconds = days * 86_400 + date.hour as i64 * 3_600 + date.minute as i64 * 60 + date.second as i64
+        - date.offset.unwrap_or(0) as i64 * 60;
+    u64::try_from(seconds).ok()
+}
+
+/// Reads the ICC profile embedded in an image file, if it has one.
+fn read_icc_profile(path: &Path) -> Option<Vec<u8>> {
+    let mut decoder = image::ImageReader::open(path).ok()?.with_guessed_format().ok()?.into_decoder().ok()?;
+    let profile = decoder.icc_profile().ok()??;
+    debug!("Found a {} byte ICC profile", profile.len());
+    Some(profile)
+}
+
+/// Returns whether an ICC profile describes the pixels it would be stored
+/// with: a `GRAY` profile for grayscale data, an `RGB ` profile otherwise.
+/// Profiles too short to have a header never fit.
+fn icc_profile_fits(profile: &[u8], gray: bool) -> bool {
+    let expected: &[u8] = if gray { b"GRAY" } else { b"RGB " };
+    let fits = profile.len() >= 128 && &profile[16..20] == expected;
+    if !fits {
+        debug!("ICC profile does not describe {} data; leaving it out", if gray { "grayscale" } else { "RGB" });
+    }
+    fits
+}
+
+/// Reads the tEXt, zTXt and iTXt chunks of a PNG file as keyword/text pairs.
+fn read_png_text(path: &Path) -> Result<BTreeMap<String, String>, ConversionError> {
+    let decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+    let mut reader = decoder.read_info()?;
+    // Text chunks may also follow the image data, so read to the end.
+    reader.finish()?;
+
+    let info = reader.info();
+    let mut fields = BTreeMap::new();
+    for chunk in &info.uncompressed_latin1_text {
+        fields.insert(chunk.keyword.clone(), chunk.text.clone());
+    }
+    for chunk in &info.compressed_latin1_text {
+        fields.insert(chunk.keyword.clone(), chunk.get_text()?);
+    }
+    for chunk in &info.utf8_text {
+        fields.insert(chunk.keyword.clone(), chunk.get_text()?);
+    }
+    Ok(fields)
+}
+
+/// Standard PNG text keywords of the metadata fields that have one.
+const PNG_TITLE: &str = "Title";
+const PNG_DESCRIPTION: &str = "Description";
+const PNG_COPYRIGHT: &str = "Copyright";
+
+/// Returns the text chunks to write for `metadata`: its title, description
+/// and copyright under their standard keywords, plus the custom fields when
+/// `with_custom_fields` is set. A custom field never replaces a dedicated one.
+fn png_text(metadata: &ImageMetadata, with_custom_fields: bool) -> BTreeMap<String, String> {
+    let mut text: BTreeMap<String, String> = [
+        (PNG_TITLE, &metadata.title),
+        (PNG_DESCRIPTION, &metadata.description),
+        (PNG_COPYRIGHT, &metadata.copyright),
+    ]
+    .into_iter()
+    .filter_map(|(keyword, value)| Some((keyword.to_string(), value.clone()?)))
+    .collect();
+    if with_custom_fields {
+        for (keyword, value) in &metadata.custom_fields {
+            text.entry(keyword.clone()).or_insert_with(|| value.clone());
+        }
+    }
+    text
+}
+
+/// Adds each field as a PNG text chunk: tEXt when the text is Latin-1,
+/// iTXt otherwise. Fields whose key is not a valid PNG keyword (1-79
+/// printable Latin-1 characters) are skipped, or rejected in strict mode.
+fn add_png_text<W: Write>(
+    encoder: &mut png::Encoder<'_, W>,
+    fields: &BTreeMap<String, String>,
+    config: &ConversionConfig,
+) -> Result<(), ConversionError> {
+    let is_latin1 = |text: &str| text.chars().all(|c| (c as u32) < 0x100);
+    for (keyword, text) in fields {
+        let valid_keyword = (1..=79).contains(&keyword.chars().count())
+            && is_latin1(keyword)
+            && !keyword.chars().any(char::is_control);
+        if !valid_keyword {
+            config.questionable(format!("skipping custom field {:?}: not a valid PNG keyword", keyword))?;
+            continue;
+        }
+        if is_latin1(text) {
+            encoder.add_text_chunk(keyword.clone(), text.clone())?;
+        } else {
+            encoder.add_itxt_chunk(keyword.clone(), text.clone())?;
         }
-        img = DynamicImage::ImageRgb8(buffer);
     }
+    Ok(())
+}
 
-    // Save the PNG file with best quality settings.
-    let file = File::create(path)?;
-    let encoder = image::codecs::png::PngEncoder::new_with_quality(
-        file,
-        image::codecs::png::CompressionType::Best,
-        image::codecs::png::FilterType::Adaptive,
-    );
+/// Converts our custom image format to a WebP file with optional postprocessing.
+///
+/// Encodes lossy WebP at `config.webp_quality` when set, and lossless WebP
+/// otherwise. WebP has no grayscale mode, so grayscale images are expanded
+/// to RGB, and grayscale with alpha to RGBA to keep its transparency.
+///
+/// The WebP quality, and brightness and contrast (in `decode_for_export`),
+/// are checked before anything is decoded, so `strict` rejects values that
+/// would be clamped.
+///
+/// # Arguments
+///
+/// * `custom_img` - The source custom image.
+/// * `webp_path` - Path where the WebP file should be saved.
+/// * `config` - Optional conversion configuration for postprocessing.
+///
+/// # Returns
+///
+/// Returns `Result<(), ConversionError>`.
+pub fn custom_to_webp<P: AsRef<Path>>(
+    custom_img: &CustomImage,
+    webp_path: P,
+    config: Option<ConversionConfig>,
+) -> Result<(), ConversionError> {
+    let config = config.unwrap_or_default();
+    let path = webp_path.as_ref();
+    config.check_output(path)?;
+    if let Some(quality) = config.webp_quality {
+        if !(0.0..=100.0).contains(&quality) {
+            config.questionable(format!("WebP quality {} is outside 0-100 and will be clamped", quality))?;
+        }
+    }
+
+    info!("Converting custom image to WebP at {:?}", path);
+
+    let img = decode_for_export(custom_img, &config)?;
     let (width, height) = img.dimensions();
-    let png_color_type = match img {
-        DynamicImage::ImageLuma8(_) => ColorType::L8,
-        DynamicImage::ImageRgb8(_) => ColorType::Rgb8,
-        DynamicImage::ImageRgba8(_) => ColorType::Rgba8,
-        _ => ColorType::Rgb8,
+    let (layout, data) = match img {
+        DynamicImage::ImageLumaA8(_) => (webp::PixelLayout::Rgba, img.into_rgba8().into_raw()),
+        other => (webp::PixelLayout::Rgb, other.into_rgb8().into_raw()),
     };
-    encoder.write_image(img.as_bytes(), width, height, png_color_type.into())?;
 
-    println!("PNG conversion complete.");
+    let encoder = webp::Encoder::new(&data, layout, width, height);
+    match config.webp_quality {
+        Some(quality) => debug!("Encoding lossy WebP at quality {}", quality),
+        None => debug!("Encoding lossless WebP"),
+    }
+    let encoded = match config.webp_quality {
+        Some(quality) => encoder.encode(quality.clamp(0.0, 100.0)),
+        None => encoder.encode_lossless(),
+    };
+    write_atomic(path, |writer| writer.write_all(&encoded))?;
+    config.report_progress(1.0);
+
+    info!("WebP conversion complete.");
     Ok(())
 }
+
+/// Uncompressed pixel dump formats written by `custom_to_raw`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RawFormat {
+    /// Headerless samples: rows top to bottom, pixels left to right, and
+    /// each pixel's channels interleaved (`Y`, `YA` or `RGB`), one byte per
+    /// sample.
+    Raw,
+    /// Binary Netpbm: a `P5` (grayscale) or `P6` (RGB) text header followed
+    /// by the samples in the same order as `Raw`, with a maxval of 255.
+    Netpbm,
+}
+
+impl RawFormat {
+    /// Picks the dump format from a path's extension (case-insensitive):
+    /// `.raw` for headerless data, `.pgm`, `.ppm` or `.pnm` for Netpbm.
+    ///
+    /// # Errors
+    ///
+    /// Returns `UnsupportedFormat` for any other extension.
+    pub fn from_path(path: &Path) -> Result<Self, ConversionError> {
+        let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+        match extension.to_ascii_lowercase().as_str() {
+            "raw" => Ok(RawFormat::Raw),
+            "pgm" | "ppm" | "pnm" => Ok(RawFormat::Netpbm),
+            _ => Err(ConversionError::UnsupportedFormat(format!(
+                "cannot dump to {}; expected a .raw, .pgm, .ppm or .pnm extension",
+                path.display()
+            ))),
+        }
+    }
+}
+
+/// Decompresses a custom image and writes its pixels to `path` as headerless
+/// samples or a Netpbm file, chosen by extension (see `RawFormat`).
+///
+/// No postprocessing is applied; of `config`, only the output checks such
+/// as `overwrite` are used.
+pub fn custom_to_raw<P: AsRef<Path>>(
+    custom_img: &CustomImage,
+    path: P,
+    config: Option<ConversionConfig>,
+) -> Result<(), ConversionError> {
+    let config = config.unwrap_or_default();
+    let path = path.as_ref();
+    let format = RawFormat::from_path(path)?;
+    config.check_output(path)?;
+
+    let bytes = custom_to_raw_bytes(custom_img, format)?;
+    write_atomic(path, |writer| writer.write_all(&bytes))?;
+    Ok(())
+}
+
+/// Returns the decompressed pixels of a custom image in `format`.
+///
+/// The Netpbm header follows the image's color type: `P5` for grayscale
+/// and `P6` for RGB, whichever Netpbm extension the file has.
+///
+/// # Errors
+///
+/// Returns `UnsupportedFormat` when writing a grayscale-with-alpha image as
+/// Netpbm, which has no alpha channel; use `RawFormat::Raw` instead.
+///
+/// ```rust
+/// use nor_image::converter::{custom_to_raw_bytes, RawFormat};
+/// use nor_image::format::{CustomImage, ColorType, CompressionType};
+///
+/// let image = CustomImage::new(2, 1, ColorType::Rgb, vec![255, 0, 0, 0, 0, 255], None, CompressionType::None)?;
+/// assert_eq!(custom_to_raw_bytes(&image, RawFormat::Raw)?, image.data);
+///
+/// let ppm = custom_to_raw_bytes(&image, RawFormat::Netpbm)?;
+/// assert_eq!(&ppm[..11], b"P6\n2 1\n255\n");
+/// assert_eq!(&ppm[11..], &image.data[..]);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn custom_to_raw_bytes(custom_img: &CustomImage, format: RawFormat) -> Result<Vec<u8>, ConversionError> {
+    let magic = match (format, custom_img.color_type) {
+        (RawFormat::Raw, _) => None,
+        (RawFormat::Netpbm, CustomColorType::Gray) => Some("P5"),
+        (RawFormat::Netpbm, CustomColorType::Rgb) => Some("P6"),
+        (RawFormat::Netpbm, CustomColorType::GrayAlpha) => {
+            return Err(ConversionError::UnsupportedFormat(
+                "PGM/PPM have no alpha channel; dump grayscale-alpha images to .raw".to_string(),
+            ))
+        }
+    };
+
+    let mut img_data = custom_img.clone();
+    img_data.levels = Vec::new();
+    if img_data.compression != CompressionType::None {
+        ParallelImageProcessor::decompress(&mut img_data)?;
+    }
+
+    let mut bytes = match magic {
+        Some(magic) => format!("{}\n{} {}\n255\n", magic, img_data.width, img_data.height).into_bytes(),
+        None => Vec::new(),
+    };
+    bytes.extend_from_slice(&img_data.data);
+    Ok(bytes)
+}
+
+/// Builds a custom image from headerless samples laid out as in
+/// `RawFormat::Raw`, compressing it with `compression`.
+///
+/// # Errors
+///
+/// Returns `InvalidOption` if `data` is not exactly `width * height *
+/// channels` bytes, and a format error if the dimensions are out of range.
+///
+/// ```rust
+/// use nor_image::converter::raw_to_custom;
+/// use nor_image::format::{ColorType, CompressionType};
+///
+/// let image = raw_to_custom(vec![10, 20, 30, 40], 2, 2, ColorType::Gray, CompressionType::None)?;
+/// assert_eq!((image.width, image.height), (2, 2));
+///
+/// let err = raw_to_custom(vec![0; 5], 2, 2, ColorType::Gray, CompressionType::None).unwrap_err();
+/// assert!(err.to_string().contains("5 bytes"));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn raw_to_custom(
+    data: Vec<u8>,
+    width: u32,
+    height: u32,
+    color_type: CustomColorType,
+    compression: CompressionType,
+) -> Result<CustomImage, ConversionError> {
+    let channels = color_type.channels() as usize;
+    let expected = (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|pixels| pixels.checked_mul(channels));
+    if let Some(expected) = expected.filter(|&expected| expected > 0 && expected != data.len()) {
+        return Err(ConversionError::InvalidOption(format!(
+            "raw data is {} bytes, but {}x{} {:?} needs {} ({} x {} x {} channel{})",
+            data.len(),
+            width,
+            height,
+            color_type,
+            expected,
+            width,
+            height,
+            channels,
+            if channels == 1 { "" } else { "s" }
+        )));
+    }
+
+    let mut image = CustomImage::new(width, height, color_type, data, None, CompressionType::None)?;
+    if compression != CompressionType::None {
+        ParallelImageProcessor::compress(&mut image, compression)?;
+    }
+    Ok(image)
+}
+
+/// Reads a `.nor` file as stored, streaming it when `config.streaming` is set.
+#[cfg_attr(feature = "cache", allow(dead_code))]
+pub fn read_custom<P: AsRef<Path>>(path: P, config: &ConversionConfig) -> Result<CustomImage, ConversionError> {
+    Ok(CachedImageLoader::read(path, config.streaming)?)
+}
+
+/// Loads a `.nor` or PNG file as an uncompressed `CustomImage`.
+///
+/// The file type is chosen by extension. PNGs with a grayscale color type are
+/// loaded as `Gray` (or `GrayAlpha` if they have alpha), everything else as
+/// `Rgb`.
+pub fn load_image<P: AsRef<Path>>(path: P) -> Result<CustomImage, ConversionError> {
+    let path = path.as_ref();
+    let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+    match extension {
+        ext if ext.eq_ignore_ascii_case("nor") => {
+            let bytes = std::fs::read(path)?;
+            let mut image = CustomImage::from_bytes(&bytes)?;
+            ParallelImageProcessor::decompress(&mut image)?;
+            Ok(image)
+        }
+        ext if ext.eq_ignore_ascii_case("png") => {
+            let img = image::open(path)?;
+            let (width, height) = img.dimensions();
+            let (color_type, data) = match img.color() {
+                ColorType::L8 | ColorType::L16 => (CustomColorType::Gray, img.into_luma8().into_raw()),
+                ColorType::La8 | ColorType::La16 => {
+                    (CustomColorType::GrayAlpha, img.into_luma_alpha8().into_raw())
+                }
+                _ => (CustomColorType::Rgb, img.into_rgb8().into_raw()),
+            };
+            Ok(CustomImage::new(width, height, color_type, data, None, CompressionType::None)?)
+        }
+        other => Err(ConversionError::UnsupportedFormat(format!(
+            "Expected a .nor or .png file, got extension '{}'",
+            other
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs `png_to_custom` on a file that does not exist, so only errors
+    /// raised before decoding can come back.
+    fn convert_missing(config: ConversionConfig) -> Result<CustomImage, ConversionError> {
+        png_to_custom("does-not-exist.png", None, Some(config))
+    }
+
+    #[test]
+    fn zero_quantize_colors_fails_before_decoding() {
+        let config = ConversionConfig { quantize_colors: Some(0), ..ConversionConfig::default() };
+        assert!(matches!(convert_missing(config), Err(ConversionError::InvalidOption(_))));
+    }
+
+    #[test]
+    fn zero_pyramid_levels_fail_before_decoding() {
+        let config = ConversionConfig { pyramid_levels: Some(0), ..ConversionConfig::default() };
+        assert!(matches!(convert_missing(config), Err(ConversionError::InvalidOption(_))));
+    }
+
+    #[test]
+    fn zero_tile_size_fails_before_decoding() {
+        let config = ConversionConfig {
+            tile_size: Some(0),
+            compression: CompressionType::RLE,
+            ..ConversionConfig::default()
+        };
+        assert!(matches!(convert_missing(config), Err(ConversionError::InvalidOption(_))));
+    }
+
+    #[test]
+    fn explicit_zero_adjustments_win_over_preset() {
+        let config = ConversionConfig {
+            preset: Some(Preset::Vivid),
+            brightness: Some(0),
+            contrast: Some(0),
+            ..ConversionConfig::default()
+        }
+        .with_preset_applied();
+        assert_eq!((config.brightness, config.contrast), (Some(0), Some(0)));
+
+        let config = ConversionConfig { preset: Some(Preset::Vivid), ..ConversionConfig::default() }.with_preset_applied();
+        assert_eq!((config.brightness, config.contrast), (Some(5), Some(50)));
+    }
+
+    #[test]
+    fn gray_alpha_webp_keeps_transparency() {
+        let data = [10, 0, 20, 255, 30, 128, 40, 64].repeat(8);
+        let image = CustomImage::new(4, 8, CustomColorType::GrayAlpha, data, None, CompressionType::None).unwrap();
+        let path = std::env::temp_dir().join(format!("nor-gray-alpha-{}.webp", std::process::id()));
+        let config = ConversionConfig { overwrite: true, ..ConversionConfig::default() };
+        custom_to_webp(&image, &path, Some(config)).unwrap();
+        let encoded = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let decoded = webp::Decoder::new(&encoded).decode().unwrap();
+        assert!(decoded.is_alpha());
+        let alpha: Vec<u8> = decoded.chunks_exact(4).take(4).map(|pixel| pixel[3]).collect();
+        assert_eq!(alpha, [0, 255, 128, 64]);
+    }
+
+    #[test]
+    fn strict_webp_export_rejects_out_of_range_adjustments() {
+        let image = CustomImage::new(2, 2, CustomColorType::Gray, vec![1, 2, 3, 4], None, CompressionType::None).unwrap();
+        let path = std::env::temp_dir().join(format!("nor-strict-{}.webp", std::process::id()));
+        let config = ConversionConfig { brightness: Some(300), strict: true, ..ConversionConfig::default() };
+        assert!(matches!(custom_to_webp(&image, &path, Some(config)), Err(ConversionError::Strict(_))));
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn load_image_matches_extensions_case_insensitively() {
+        let image = CustomImage::new(2, 2, CustomColorType::Gray, vec![1, 2, 3, 4], None, CompressionType::None).unwrap();
+        let dir = std::env::temp_dir();
+        let nor = dir.join(format!("nor-load-{}.NOR", std::process::id()));
+        let png = dir.join(format!("nor-load-{}.Png", std::process::id()));
+        std::fs::write(&nor, image.to_bytes().unwrap()).unwrap();
+        GrayImage::from_raw(2, 2, vec![1, 2, 3, 4]).unwrap().save_with_format(&png, image::ImageFormat::Png).unwrap();
+
+        let loaded = (load_image(&nor), load_image(&png));
+        std::fs::remove_file(&nor).unwrap();
+        std::fs::remove_file(&png).unwrap();
+        assert_eq!(loaded.0.unwrap().data, image.data);
+        assert_eq!(loaded.1.unwrap().data, image.data);
+    }
+}
@@ -17,10 +17,13 @@
 //! This module provides functionality for working with a custom image format designed
 //! for educational purposes. The format supports:
 //! 
-//! - Multiple color types (Grayscale and RGB)
+//! - Multiple color types (Grayscale, grayscale with alpha, and RGB)
 //! - Various compression methods (None, RLE, Delta, Lossy)
 //! - Embedded metadata (stored as JSON)
-//! - SHA256 checksum verification for data integrity
+//! - Optional tiled storage, so a region can be decoded without the rest
+//! - Optional reduced-resolution pyramid levels for multi-resolution delivery
+//! - SHA256 checksum verification for data integrity (can be omitted for
+//!   trusted pipelines, which is recorded in the header)
 //!
 //! # File Format Structure
 //!
@@ -31,21 +34,35 @@
 //! - Width (4 bytes, little-endian)
 //! - Height (4 bytes, little-endian)
 //! - Compression type (1 byte)
+//! - Flags (1 byte, version 3 and later)
+//! - Lossy quality (1 byte, only when the lossy quality flag is set)
+//! - Pixel data length (8 bytes, little-endian; version 4 and later, only for
+//!   RLE data that is neither tiled nor chunked)
+//! - Tile index (only when the tiled flag is set)
+//! - Chunk index (only when the chunked flag is set)
+//! - Pyramid index (only when the pyramid flag is set)
 //! - Metadata length (4 bytes, little-endian)
-//! - Metadata (JSON string)
+//! - Metadata (JSON string, padded with spaces up to the reserved capacity
+//!   when the reserved-metadata flag is set)
 //! - Pixel data (uncompressed or compressed bytes)
-//! - SHA256 checksum (32 bytes)
+//! - Pyramid level data, smallest reduction last (only with the pyramid flag)
+//! - SHA256 checksum (32 bytes, absent when the no-checksum flag is set)
+//! - Footer magic (4 bytes, only when the footer flag is set)
+//!
+//! Multi-byte integers are always little-endian, whatever the host's byte
+//! order, and are read from exactly sized buffers: a header that ends early
+//! is reported as `DataTooShort`.
 //!
 //! # Example
 //!
 //! ```rust
-//! use your_crate::format::{CustomImage, ColorType, CompressionType};
+//! use nor_image::format::{CustomImage, ColorType, CompressionType};
 //!
 //! // Create a dummy 10x10 RGB image with dummy data.
-//! let width = 10;
-//! let height = 10;
-//! let channels = ColorType::Rgb.channels() as usize;
-//! let data = vec![128u8; width * height * channels];
+//! let width = 10u32;
+//! let height = 10u32;
+//! let channels = ColorType::Rgb.channels();
+//! let data = vec![128u8; (width * height * channels) as usize];
 //! let image = CustomImage::new(width, height, ColorType::Rgb, data, None, CompressionType::None)?;
 //! let bytes = image.to_bytes()?;
 //! let decoded_image = CustomImage::from_bytes(&bytes)?;
@@ -53,22 +70,67 @@
 //! # Ok::<(), Box<dyn std::error::Error>>(())
 //! ```
 
-use std::convert::TryInto;
 use std::convert::TryFrom;
 use std::time::SystemTime;
 use std::error::Error as StdError;
 use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom, Write};
 use sha2::{Sha256, Digest};
 use serde::{Serialize, Deserialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Range;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use image::imageops::{self, FilterType};
+use image::{ImageBuffer, Luma, LumaA, Pixel, Rgb};
 
 /// Metadata associated with an image.
+///
+/// Custom fields are kept sorted by key, so the same metadata always
+/// serializes to the same bytes (and the file to the same checksum):
+///
+/// ```
+/// use nor_image::format::ImageMetadata;
+///
+/// let keys = ["zeta", "alpha", "mid", "beta"];
+/// let mut first = ImageMetadata { creation_date: 0, ..ImageMetadata::default() };
+/// let mut second = first.clone();
+/// for key in keys {
+///     first.custom_fields.insert(key.to_string(), key.to_uppercase());
+/// }
+/// for key in keys.iter().rev() {
+///     second.custom_fields.insert(key.to_string(), key.to_uppercase());
+/// }
+/// assert_eq!(serde_json::to_vec(&first).unwrap(), serde_json::to_vec(&second).unwrap());
+/// ```
+///
+/// The title, description and copyright are left out of the JSON when unset,
+/// so metadata without them serializes exactly as it did before they existed:
+///
+/// ```
+/// use nor_image::format::ImageMetadata;
+///
+/// let metadata = ImageMetadata { creation_date: 0, ..ImageMetadata::default() };
+/// assert!(!serde_json::to_string(&metadata).unwrap().contains("title"));
+/// let old: ImageMetadata = serde_json::from_str(r#"{"creation_date":0,"author":null,"custom_fields":{}}"#).unwrap();
+/// assert_eq!(old.title, None);
+/// ```
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct ImageMetadata {
     /// Creation date of the image (Unix timestamp)
     pub creation_date: u64,
     /// Author/creator of the image
     pub author: Option<String>,
+    /// Title of the image
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Description or caption of the image
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Copyright notice
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub copyright: Option<String>,
     /// Camera model used
     pub camera_model: Option<String>,
     /// Exposure time in seconds
@@ -80,7 +142,7 @@ pub struct ImageMetadata {
     /// Focal length in mm
     pub focal_length: Option<f32>,
     /// Additional custom metadata as key-value pairs
-    pub custom_fields: HashMap<String, String>,
+    pub custom_fields: BTreeMap<String, String>,
 }
 
 impl Default for ImageMetadata {
@@ -91,12 +153,123 @@ impl Default for ImageMetadata {
                 .unwrap_or_default()
                 .as_secs(),
             author: None,
+            title: None,
+            description: None,
+            copyright: None,
+            camera_model: None,
+            exposure_time: None,
+            iso: None,
+            f_number: None,
+            focal_length: None,
+            custom_fields: BTreeMap::new(),
+        }
+    }
+}
+
+impl ImageMetadata {
+    /// Returns metadata with every optional field cleared, for sharing an
+    /// image without identifying information.
+    ///
+    /// The creation date is kept when `keep_date` is true and zeroed otherwise.
+    pub fn stripped(&self, keep_date: bool) -> Self {
+        ImageMetadata {
+            creation_date: if keep_date { self.creation_date } else { 0 },
+            author: None,
+            title: None,
+            description: None,
+            copyright: None,
             camera_model: None,
             exposure_time: None,
             iso: None,
             f_number: None,
             focal_length: None,
-            custom_fields: HashMap::new(),
+            custom_fields: BTreeMap::new(),
+        }
+    }
+
+    /// Compares this metadata with `other` field by field, returning what
+    /// `other` adds, removes or changes.
+    ///
+    /// Fields are named as they are serialized; custom fields appear as
+    /// `custom_fields.KEY`, in key order after the built-in fields.
+    ///
+    /// ```rust
+    /// use nor_image::format::{ImageMetadata, MetadataChange};
+    ///
+    /// let mut old = ImageMetadata { creation_date: 0, ..ImageMetadata::default() };
+    /// old.author = Some("Ada".to_string());
+    /// old.custom_fields.insert("location".to_string(), "Paris".to_string());
+    /// let mut new = old.clone();
+    /// new.author = None;
+    /// new.iso = Some(200);
+    /// new.custom_fields.insert("location".to_string(), "Rome".to_string());
+    ///
+    /// let changes = old.diff(&new);
+    /// assert_eq!(changes.len(), 3);
+    /// assert_eq!(changes[0], MetadataChange::Removed { field: "author".to_string(), value: "Ada".to_string() });
+    /// assert_eq!(changes[1].to_string(), "+ iso: 200");
+    /// assert_eq!(changes[2].to_string(), "~ custom_fields.location: Paris -> Rome");
+    /// assert!(old.diff(&old).is_empty());
+    /// ```
+    pub fn diff(&self, other: &ImageMetadata) -> Vec<MetadataChange> {
+        fn text<T: ToString>(value: &Option<T>) -> Option<String> {
+            value.as_ref().map(ToString::to_string)
+        }
+
+        let mut changes = Vec::new();
+        let mut compare = |field: String, old: Option<String>, new: Option<String>| {
+            let change = match (old, new) {
+                (None, Some(value)) => MetadataChange::Added { field, value },
+                (Some(value), None) => MetadataChange::Removed { field, value },
+                (Some(old), Some(new)) if old != new => MetadataChange::Changed { field, old, new },
+                _ => return,
+            };
+            changes.push(change);
+        };
+        compare(
+            "creation_date".to_string(),
+            Some(self.creation_date.to_string()),
+            Some(other.creation_date.to_string()),
+        );
+        compare("author".to_string(), text(&self.author), text(&other.author));
+        compare("title".to_string(), text(&self.title), text(&other.title));
+        compare("description".to_string(), text(&self.description), text(&other.description));
+        compare("copyright".to_string(), text(&self.copyright), text(&other.copyright));
+        compare("camera_model".to_string(), text(&self.camera_model), text(&other.camera_model));
+        compare("exposure_time".to_string(), text(&self.exposure_time), text(&other.exposure_time));
+        compare("iso".to_string(), text(&self.iso), text(&other.iso));
+        compare("f_number".to_string(), text(&self.f_number), text(&other.f_number));
+        compare("focal_length".to_string(), text(&self.focal_length), text(&other.focal_length));
+        let keys: BTreeSet<&String> = self.custom_fields.keys().chain(other.custom_fields.keys()).collect();
+        for key in keys {
+            compare(
+                format!("custom_fields.{}", key),
+                self.custom_fields.get(key).cloned(),
+                other.custom_fields.get(key).cloned(),
+            );
+        }
+        changes
+    }
+}
+
+/// One difference between two `ImageMetadata`s, as found by
+/// `ImageMetadata::diff`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MetadataChange {
+    /// The field is only set in the second metadata.
+    Added { field: String, value: String },
+    /// The field is only set in the first metadata.
+    Removed { field: String, value: String },
+    /// The field is set in both, to different values.
+    Changed { field: String, old: String, new: String },
+}
+
+impl fmt::Display for MetadataChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MetadataChange::Added { field, value } => write!(f, "+ {}: {}", field, value),
+            MetadataChange::Removed { field, value } => write!(f, "- {}: {}", field, value),
+            MetadataChange::Changed { field, old, new } => write!(f, "~ {}: {} -> {}", field, old, new),
         }
     }
 }
@@ -122,6 +295,20 @@ pub enum FormatError {
     CompressionError(String),
     /// Error serializing/deserializing metadata.
     MetadataError(String),
+    /// I/O error while reading or writing serialized data.
+    IoError(io::Error),
+    /// A requested region is empty or extends past the image bounds.
+    InvalidRegion(Region),
+    /// The tile index does not describe the image's pixel data.
+    InvalidTileIndex(String),
+    /// The chunk index does not describe the image's pixel data.
+    InvalidChunkIndex(String),
+    /// The pyramid index is malformed, or a requested level does not exist.
+    InvalidPyramid(String),
+    /// The footer flag is set but the file does not end with `FOOTER_MAGIC`.
+    MissingFooter,
+    /// The decoded pixel data would exceed `max_pixel_bytes()`.
+    TooLarge { bytes: usize, limit: usize },
 }
 impl fmt::Display for FormatError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -139,23 +326,51 @@ impl fmt::Display for FormatError {
             FormatError::ChecksumMismatch => write!(f, "Checksum verification failed"),
             FormatError::CompressionError(msg) => write!(f, "Compression error: {}", msg),
             FormatError::MetadataError(msg) => write!(f, "Metadata error: {}", msg),
+            FormatError::IoError(e) => write!(f, "I/O error: {}", e),
+            FormatError::InvalidRegion(r) => write!(
+                f,
+                "Invalid region {}x{} at ({}, {})",
+                r.width, r.height, r.x, r.y
+            ),
+            FormatError::InvalidTileIndex(msg) => write!(f, "Invalid tile index: {}", msg),
+            FormatError::InvalidChunkIndex(msg) => write!(f, "Invalid chunk index: {}", msg),
+            FormatError::InvalidPyramid(msg) => write!(f, "Invalid pyramid: {}", msg),
+            FormatError::MissingFooter => write!(f, "Footer magic is missing or corrupt"),
+            FormatError::TooLarge { bytes, limit } => write!(
+                f,
+                "Image needs {} bytes of pixel data, over the limit of {} bytes",
+                bytes, limit
+            ),
         }
     }
 }
 
 impl StdError for FormatError {
     fn source(&self) -> Option<&(dyn StdError + 'static)> {
-        None
+        match self {
+            FormatError::IoError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for FormatError {
+    fn from(err: io::Error) -> Self {
+        FormatError::IoError(err)
     }
 }
 
 /// Supported color types for image data.
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ColorType {
     /// Single channel grayscale.
     Gray = 0,
     /// Three channel RGB.
     Rgb = 1,
+    /// Two channel grayscale with alpha, e.g. for masks and icons.
+    #[serde(rename = "gray-alpha")]
+    GrayAlpha = 2,
 }
 
 impl ColorType {
@@ -164,6 +379,50 @@ impl ColorType {
         match self {
             ColorType::Gray => 1,
             ColorType::Rgb => 3,
+            ColorType::GrayAlpha => 2,
+        }
+    }
+
+    /// Returns every supported color type, in format byte order.
+    ///
+    /// Every byte the decoder accepts maps to an entry here:
+    ///
+    /// ```rust
+    /// use nor_image::format::ColorType;
+    ///
+    /// for byte in 0..=u8::MAX {
+    ///     if let Ok(color_type) = ColorType::try_from(byte) {
+    ///         assert!(ColorType::all().contains(&color_type));
+    ///     }
+    /// }
+    /// assert_eq!(ColorType::all().len(), 3);
+    /// ```
+    pub fn all() -> &'static [ColorType] {
+        &[ColorType::Gray, ColorType::Rgb, ColorType::GrayAlpha]
+    }
+
+    /// Returns the color type's stable identifier, as used in metadata and
+    /// on the command line.
+    ///
+    /// ```rust
+    /// use nor_image::format::ColorType;
+    ///
+    /// assert_eq!(ColorType::GrayAlpha.name(), "gray-alpha");
+    /// ```
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColorType::Gray => "gray",
+            ColorType::Rgb => "rgb",
+            ColorType::GrayAlpha => "gray-alpha",
+        }
+    }
+
+    /// Returns a one-line human readable description of the color type.
+    pub fn description(&self) -> &'static str {
+        match self {
+            ColorType::Gray => "Single channel grayscale",
+            ColorType::Rgb => "Three channel RGB",
+            ColorType::GrayAlpha => "Grayscale with an alpha channel",
         }
     }
 }
@@ -175,13 +434,16 @@ impl TryFrom<u8> for ColorType {
         match value {
             0 => Ok(ColorType::Gray),
             1 => Ok(ColorType::Rgb),
+            2 => Ok(ColorType::GrayAlpha),
             other => Err(FormatError::UnsupportedColorType(other)),
         }
     }
 }
 
 /// Supported compression methods for image data.
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[allow(clippy::upper_case_acronyms)]
 pub enum CompressionType {
     /// No compression.
     None = 0,
@@ -191,6 +453,10 @@ pub enum CompressionType {
     Delta = 2,
     /// Lossy compression.
     Lossy = 3,
+    /// Run-length encoding with LEB128 varint run counts, so long runs
+    /// of a single byte take one pair instead of one pair per 255 bytes.
+    #[serde(rename = "rle-varint")]
+    RleVarint = 4,
 }
 
 impl TryFrom<u8> for CompressionType {
@@ -202,11 +468,67 @@ impl TryFrom<u8> for CompressionType {
             1 => Ok(CompressionType::RLE),
             2 => Ok(CompressionType::Delta),
             3 => Ok(CompressionType::Lossy),
+            4 => Ok(CompressionType::RleVarint),
             other => Err(FormatError::UnsupportedVersion(other)),
         }
     }
 }
 
+impl CompressionType {
+    /// Returns every supported compression method, in format byte order.
+    ///
+    /// Every byte the decoder accepts maps to an entry here:
+    ///
+    /// ```rust
+    /// use nor_image::format::CompressionType;
+    ///
+    /// for byte in 0..=u8::MAX {
+    ///     if let Ok(compression) = CompressionType::try_from(byte) {
+    ///         assert!(CompressionType::all().contains(&compression));
+    ///     }
+    /// }
+    /// assert_eq!(CompressionType::all().len(), 5);
+    /// ```
+    pub fn all() -> &'static [CompressionType] {
+        &[
+            CompressionType::None,
+            CompressionType::RLE,
+            CompressionType::Delta,
+            CompressionType::Lossy,
+            CompressionType::RleVarint,
+        ]
+    }
+
+    /// Returns the method's stable identifier, as used in metadata and on
+    /// the command line.
+    ///
+    /// ```rust
+    /// use nor_image::format::CompressionType;
+    ///
+    /// assert_eq!(CompressionType::RleVarint.name(), "rle-varint");
+    /// ```
+    pub fn name(&self) -> &'static str {
+        match self {
+            CompressionType::None => "none",
+            CompressionType::RLE => "rle",
+            CompressionType::Delta => "delta",
+            CompressionType::Lossy => "lossy",
+            CompressionType::RleVarint => "rle-varint",
+        }
+    }
+
+    /// Returns a one-line human readable description of the method.
+    pub fn description(&self) -> &'static str {
+        match self {
+            CompressionType::None => "Uncompressed pixel data",
+            CompressionType::RLE => "Run-length encoding, lossless",
+            CompressionType::Delta => "Delta encoding, lossless; suits smooth gradients",
+            CompressionType::Lossy => "Block averaging, lossy; smallest output",
+            CompressionType::RleVarint => "Run-length encoding with varint run counts, lossless",
+        }
+    }
+}
+
 /// Represents an image in the Custom Image Format (CIF).
 #[derive(Clone, PartialEq, Debug)]
 pub struct CustomImage {
@@ -222,12 +544,259 @@ pub struct CustomImage {
     pub metadata: ImageMetadata,
     /// Type of compression used.
     pub compression: CompressionType,
+    /// Tile layout when the pixel data is stored as independently compressed
+    /// tiles, or `None` for a single contiguous stream.
+    pub tiles: Option<TileIndex>,
+    /// Chunk layout when run-length encoded data was compressed in
+    /// independent chunks, allowing them to be decompressed in parallel.
+    pub chunks: Option<ChunkIndex>,
+    /// Whether a SHA256 checksum is written after the pixel data and
+    /// verified on read. Files without one set a header flag instead.
+    pub checksummed: bool,
+    /// Whether `FOOTER_MAGIC` is written at the very end of the file, so
+    /// tools can find where one image ends when several are packed together.
+    pub footer: bool,
+    /// Size of the metadata block reserved on write, so the metadata can
+    /// later be rewritten in place with `rewrite_metadata`. `None` stores
+    /// the metadata without padding.
+    pub metadata_capacity: Option<u32>,
+    /// Reduced-resolution copies of the image (1/2, 1/4, ...), largest
+    /// first. Empty for single-level images.
+    pub levels: Vec<PyramidLevel>,
+    /// Embedded ICC color profile, or `None` for sRGB. Stored after the
+    /// metadata only when present, so files without one are unchanged.
+    pub icc_profile: Option<Vec<u8>>,
+    /// Quality (1-100) that lossy data is encoded and decoded at; see
+    /// `lossy_block_size`. Stored in the header only when it differs from
+    /// `DEFAULT_LOSSY_QUALITY`, so older files decode as before.
+    pub lossy_quality: u8,
+}
+
+/// A reduced-resolution copy of an image, stored after its pixel data.
+///
+/// Levels are compressed with the image's compression type (never tiled or
+/// chunked) and share its color type and metadata.
+#[derive(Clone, PartialEq, Debug)]
+pub struct PyramidLevel {
+    /// Width of the level, in pixels.
+    pub width: u32,
+    /// Height of the level, in pixels.
+    pub height: u32,
+    /// Pixel data of the level.
+    pub data: Vec<u8>,
+}
+
+/// Index of the independently compressed tiles of a tiled image.
+///
+/// Tiles are square, laid out in row-major order, and clipped at the right
+/// and bottom edges of the image. Each tile is compressed on its own with the
+/// image's compression type, and the compressed tiles are stored back to back
+/// in the pixel data.
+#[derive(Clone, PartialEq, Debug)]
+pub struct TileIndex {
+    /// Side length of a full tile, in pixels.
+    pub tile_size: u32,
+    /// Compressed length of each tile, in bytes.
+    pub lengths: Vec<u32>,
+}
+
+/// Index of the independently run-length encoded chunks of an image.
+///
+/// The uncompressed pixel data is split into chunks of `chunk_size` bytes
+/// (the last may be shorter), and each is encoded on its own, so chunks can be
+/// decoded in parallel. Decoding the concatenated chunks serially gives the
+/// same result.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ChunkIndex {
+    /// Uncompressed size of a full chunk, in bytes.
+    pub chunk_size: u32,
+    /// Compressed length of each chunk, in bytes.
+    pub lengths: Vec<u32>,
+}
+
+impl ChunkIndex {
+    /// Returns the byte range of each compressed chunk within the pixel data.
+    pub fn ranges(&self) -> Vec<Range<usize>> {
+        length_ranges(&self.lengths)
+    }
+}
+
+/// A rectangle of pixels within an image.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Region {
+    /// Left edge, in pixels.
+    pub x: u32,
+    /// Top edge, in pixels.
+    pub y: u32,
+    /// Width, in pixels.
+    pub width: u32,
+    /// Height, in pixels.
+    pub height: u32,
+}
+
+impl Region {
+    /// Creates a new region.
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Region { x, y, width, height }
+    }
+
+    /// Returns the overlap of two regions, if they overlap at all.
+    fn intersect(&self, other: &Region) -> Option<Region> {
+        let x0 = self.x.max(other.x);
+        let y0 = self.y.max(other.y);
+        let x1 = (self.x + self.width).min(other.x + other.width);
+        let y1 = (self.y + self.height).min(other.y + other.height);
+        (x0 < x1 && y0 < y1).then(|| Region::new(x0, y0, x1 - x0, y1 - y0))
+    }
 }
 
 /// Constants for the Custom Image Format.
 const MAGIC_NUMBER: &[u8] = b"CIMG";
-const VERSION: u8 = 2;
-const MAX_DIMENSION: u32 = 32_768;
+const VERSION: u8 = 4;
+/// Oldest file version that can still be read.
+const MIN_VERSION: u8 = 2;
+/// Header flag marking pixel data stored as independently compressed tiles.
+const FLAG_TILED: u8 = 0x01;
+/// Header flag marking run-length encoded data with a chunk index.
+const FLAG_CHUNKED: u8 = 0x02;
+/// Header flag marking a file written without a trailing checksum.
+const FLAG_NO_CHECKSUM: u8 = 0x04;
+/// Header flag marking a file with reduced-resolution pyramid levels.
+const FLAG_PYRAMID: u8 = 0x08;
+/// Header flag marking a file that ends with `FOOTER_MAGIC`.
+const FLAG_FOOTER: u8 = 0x10;
+/// Trailing magic written after the checksum when `CustomImage::footer` is set.
+pub const FOOTER_MAGIC: &[u8] = b"GMIC";
+/// Header flag marking a metadata block padded to a reserved capacity.
+const FLAG_RESERVED_METADATA: u8 = 0x20;
+/// Header flag marking an ICC profile stored after the metadata.
+const FLAG_ICC_PROFILE: u8 = 0x40;
+/// Header flag marking a lossy quality byte stored after the flags.
+const FLAG_LOSSY_QUALITY: u8 = 0x80;
+/// Lossy quality of images that do not store one, and of files written
+/// before the quality was stored.
+pub const DEFAULT_LOSSY_QUALITY: u8 = 50;
+/// Maximum size of an embedded ICC profile, in bytes.
+pub const MAX_ICC_PROFILE_LEN: usize = 4 * 1024 * 1024;
+/// Maximum number of reduced levels; halving the default `MAX_DIMENSION` this
+/// many times reaches a single pixel.
+pub const MAX_PYRAMID_LEVELS: u32 = 15;
+/// Offset of the flags byte in version 3 and later files.
+const FLAGS_OFFSET: usize = 15;
+/// Default tile size for tiled storage, in pixels.
+pub const DEFAULT_TILE_SIZE: u32 = 256;
+/// Default maximum width or height of an image, in pixels.
+///
+/// Embedders that need larger images can raise the limit with
+/// `set_max_dimension`.
+pub const MAX_DIMENSION: u32 = 32_768;
+/// Most channels any color type has; used to bound the pixel buffer size.
+const MAX_CHANNELS: usize = 3;
+/// The width and height limit currently in force.
+static MAX_DIMENSION_LIMIT: AtomicU32 = AtomicU32::new(MAX_DIMENSION);
+/// Default maximum size of an image's decoded pixel data (1 GiB), about
+/// 18500x18500 RGB pixels.
+///
+/// `MAX_DIMENSION` alone still admits 32768x32768 RGB images, about 3 GiB,
+/// which a crafted header could make a decoder allocate.
+pub const DEFAULT_MAX_PIXEL_BYTES: usize = 1 << 30;
+/// The pixel data size limit currently in force.
+static MAX_PIXEL_BYTES_LIMIT: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_PIXEL_BYTES);
+
+/// Returns the maximum size of decoded pixel data accepted when creating or
+/// decoding an image. This is `DEFAULT_MAX_PIXEL_BYTES` unless changed with
+/// `set_max_pixel_bytes`.
+pub fn max_pixel_bytes() -> usize {
+    MAX_PIXEL_BYTES_LIMIT.load(Ordering::Relaxed)
+}
+
+/// Sets the maximum size of decoded pixel data (`width * height *
+/// channels`) accepted when creating or decoding an image, for every thread,
+/// and returns the previous limit.
+///
+/// The check runs on the header alone, before pixel data is read or
+/// allocated, so services accepting untrusted files can bound their memory
+/// use. Images over the limit fail with `TooLarge`.
+///
+/// ```rust
+/// use nor_image::format::{self, CustomImage, ColorType, CompressionType, FormatError, DEFAULT_MAX_PIXEL_BYTES};
+///
+/// let image = CustomImage::new(64, 64, ColorType::Rgb, vec![0; 64 * 64 * 3], None, CompressionType::None)?;
+/// let bytes = image.to_bytes()?;
+///
+/// let previous = format::set_max_pixel_bytes(10_000);
+/// assert_eq!(previous, DEFAULT_MAX_PIXEL_BYTES);
+/// assert!(matches!(
+///     CustomImage::from_bytes(&bytes),
+///     Err(FormatError::TooLarge { bytes: 12_288, limit: 10_000 })
+/// ));
+/// assert!(CustomImage::new(64, 64, ColorType::Gray, vec![0; 64 * 64], None, CompressionType::None).is_ok());
+///
+/// format::set_max_pixel_bytes(previous);
+/// assert!(CustomImage::from_bytes(&bytes).is_ok());
+/// # Ok::<(), FormatError>(())
+/// ```
+pub fn set_max_pixel_bytes(limit: usize) -> usize {
+    MAX_PIXEL_BYTES_LIMIT.swap(limit, Ordering::Relaxed)
+}
+
+/// Returns the maximum width or height accepted when creating or decoding
+/// an image. This is `MAX_DIMENSION` unless changed with `set_max_dimension`.
+pub fn max_dimension() -> u32 {
+    MAX_DIMENSION_LIMIT.load(Ordering::Relaxed)
+}
+
+/// Sets the maximum width or height accepted when creating or decoding an
+/// image, for every thread, and returns the previous limit.
+///
+/// Buffer sizes are computed as `width * height * channels` in `usize`. The
+/// limit is only accepted if that product cannot overflow for a square image
+/// at the limit with the widest color type, so every image that passes
+/// validation has a representable size. On 64-bit targets this allows limits
+/// up to about 2.4 billion pixels per side; on 32-bit targets the default is
+/// close to the ceiling.
+///
+/// A raised limit lets crafted headers request correspondingly large
+/// allocations, so only raise it as far as the imagery requires.
+///
+/// # Errors
+///
+/// Returns `InvalidDimensions` if the limit is zero or would allow the
+/// buffer size to overflow; the current limit is left unchanged.
+///
+/// ```rust
+/// use nor_image::format::{self, CustomImage, ColorType, CompressionType, FormatError, MAX_DIMENSION};
+///
+/// let panorama = || CustomImage::new(40_000, 1, ColorType::Gray, vec![0; 40_000], None, CompressionType::None);
+/// assert!(matches!(panorama(), Err(FormatError::InvalidDimensions { .. })));
+///
+/// let previous = format::set_max_dimension(65_536)?;
+/// assert_eq!(previous, MAX_DIMENSION);
+/// assert!(panorama().is_ok());
+///
+/// assert!(format::set_max_dimension(0).is_err());
+/// format::set_max_dimension(previous)?;
+/// # Ok::<(), FormatError>(())
+/// ```
+#[allow(dead_code)]
+pub fn set_max_dimension(limit: u32) -> Result<u32, FormatError> {
+    let side = limit as usize;
+    let fits = side
+        .checked_mul(side)
+        .and_then(|pixels| pixels.checked_mul(MAX_CHANNELS))
+        .is_some();
+    if limit == 0 || !fits {
+        return Err(FormatError::InvalidDimensions { width: limit, height: limit });
+    }
+    Ok(MAX_DIMENSION_LIMIT.swap(limit, Ordering::Relaxed))
+}
+
+/// Maximum size of the serialized metadata block (1 MiB).
+///
+/// Enforced on both write and read so that a crafted length field cannot force
+/// a huge allocation while decoding. A file claiming a larger metadata block
+/// is rejected with `MetadataError` before anything is allocated for it.
+pub const MAX_METADATA_LEN: usize = 1024 * 1024;
 
 impl CustomImage {
     /// Returns the total number of pixels in the image.
@@ -241,8 +810,64 @@ impl CustomImage {
         self.width.checked_mul(self.height)
     }
 
+    /// Returns the size of the pixel data once decompressed, in bytes.
+    ///
+    /// Computed from the header alone, so it is cheap even for compressed
+    /// images.
+    pub fn uncompressed_len(&self) -> usize {
+        self.width as usize * self.height as usize * self.color_type.channels() as usize
+    }
+
+    /// Returns the embedded ICC color profile, if any.
+    ///
+    /// The profile survives serialization, and an image without one
+    /// serializes exactly as before profiles were supported:
+    ///
+    /// ```rust
+    /// use nor_image::format::{CustomImage, ColorType, CompressionType};
+    ///
+    /// let mut image = CustomImage::new(2, 2, ColorType::Gray, vec![1, 2, 3, 4], None, CompressionType::None)?;
+    /// let plain = image.to_bytes()?;
+    /// assert_eq!(CustomImage::from_bytes(&plain)?.icc_profile(), None);
+    ///
+    /// image.icc_profile = Some(vec![7; 200]);
+    /// let profiled = image.to_bytes()?;
+    /// assert_eq!(profiled.len(), plain.len() + 4 + 200);
+    /// assert_eq!(CustomImage::from_bytes(&profiled)?.icc_profile(), Some(&[7u8; 200][..]));
+    /// assert_eq!(CustomImage::from_reader(&profiled[..])?, image);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn icc_profile(&self) -> Option<&[u8]> {
+        self.icc_profile.as_deref()
+    }
+
+    /// Returns how many times smaller the stored pixel data is than the
+    /// uncompressed pixels (1.0 for uncompressed images).
+    ///
+    /// For lossy compression this reflects the stored size only; the decoded
+    /// pixels are an approximation of the original.
+    ///
+    /// ```rust
+    /// use nor_image::format::{CustomImage, ColorType, CompressionType};
+    ///
+    /// let image = CustomImage::new(4, 4, ColorType::Gray, vec![7; 16], None, CompressionType::None)?;
+    /// let compressed = CustomImage { data: CustomImage::compress_rle(&image.data), compression: CompressionType::RLE, ..image };
+    /// assert_eq!(compressed.data.len(), 2);
+    /// assert_eq!(compressed.compression_ratio(), 8.0);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn compression_ratio(&self) -> f64 {
+        if self.data.is_empty() {
+            return 1.0;
+        }
+        self.uncompressed_len() as f64 / self.data.len() as f64
+    }
+
     /// Validates image dimensions to ensure they are within allowed limits.
     ///
+    /// Dimensions that pass always have a `uncompressed_len` that fits in
+    /// `usize`, since `set_max_dimension` rejects limits where it would not.
+    ///
     /// # Returns
     ///
     /// Returns `Ok(())` if dimensions are valid, otherwise returns an error.
@@ -250,12 +875,25 @@ impl CustomImage {
         if width == 0 || height == 0 {
             return Err(FormatError::InvalidDimensions { width, height });
         }
-        if width > MAX_DIMENSION || height > MAX_DIMENSION {
+        let limit = max_dimension();
+        if width > limit || height > limit {
             return Err(FormatError::InvalidDimensions { width, height });
         }
         Ok(())
     }
 
+    /// Validates dimensions like `validate_dimensions`, and checks that the
+    /// decoded pixel data fits within `max_pixel_bytes()`.
+    pub(crate) fn validate_size(width: u32, height: u32, color_type: ColorType) -> Result<(), FormatError> {
+        Self::validate_dimensions(width, height)?;
+        let bytes = width as usize * height as usize * color_type.channels() as usize;
+        let limit = max_pixel_bytes();
+        if bytes > limit {
+            return Err(FormatError::TooLarge { bytes, limit });
+        }
+        Ok(())
+    }
+
     /// Creates a new `CustomImage` instance.
     ///
     /// # Arguments
@@ -271,6 +909,7 @@ impl CustomImage {
     ///
     /// Returns an error if:
     /// - Dimensions are invalid (zero or too large).
+    /// - The pixel data would be larger than `max_pixel_bytes()` (`TooLarge`).
     /// - Data length doesn't match the expected size for uncompressed images.
     pub fn new(
         width: u32,
@@ -280,11 +919,11 @@ impl CustomImage {
         metadata: Option<ImageMetadata>,
         compression: CompressionType,
     ) -> Result<Self, FormatError> {
-        Self::validate_dimensions(width, height)?;
-        let expected_len = width
-            .checked_mul(height)
-            .and_then(|pixels| pixels.checked_mul(color_type.channels()))
-            .ok_or(FormatError::InvalidDimensions { width, height })? as usize;
+        Self::validate_size(width, height, color_type)?;
+        let expected_len = (width as usize)
+            .checked_mul(height as usize)
+            .and_then(|pixels| pixels.checked_mul(color_type.channels() as usize))
+            .ok_or(FormatError::InvalidDimensions { width, height })?;
         
         if compression == CompressionType::None && data.len() != expected_len {
             return Err(FormatError::DataLengthMismatch {
@@ -300,9 +939,376 @@ impl CustomImage {
             data,
             metadata: metadata.unwrap_or_default(),
             compression,
+            tiles: None,
+            chunks: None,
+            checksummed: true,
+            footer: false,
+            metadata_capacity: None,
+            levels: Vec::new(),
+            icc_profile: None,
+            lossy_quality: DEFAULT_LOSSY_QUALITY,
+        })
+    }
+
+    /// Returns the region covering the whole image.
+    pub fn full_region(&self) -> Region {
+        Region::new(0, 0, self.width, self.height)
+    }
+
+    /// Builds up to `count` successively halved copies of an uncompressed
+    /// image, stopping early once a level is a single pixel. Each level
+    /// averages 2x2 blocks of the one above it.
+    ///
+    /// The result is stored with `set_pyramid`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError` if the image is compressed or tiled.
+    pub fn build_pyramid(&self, count: u32) -> Result<Vec<CustomImage>, FormatError> {
+        if self.compression != CompressionType::None || self.tiles.is_some() {
+            return Err(FormatError::CompressionError(
+                "Pyramid levels are built from uncompressed images".to_string(),
+            ));
+        }
+        let mut levels: Vec<CustomImage> = Vec::new();
+        for _ in 0..count.min(MAX_PYRAMID_LEVELS) {
+            let previous = levels.last().unwrap_or(self);
+            if previous.width == 1 && previous.height == 1 {
+                break;
+            }
+            levels.push(previous.halved()?);
+        }
+        Ok(levels)
+    }
+
+    /// Stores `levels` as this image's pyramid, compressing each with the
+    /// image's compression type.
+    ///
+    /// ```rust
+    /// use nor_image::format::{CustomImage, ColorType, CompressionType};
+    ///
+    /// let data: Vec<u8> = (0..64 * 48).map(|i| (i % 7) as u8).collect();
+    /// let image = CustomImage::new(64, 48, ColorType::Gray, data.clone(), None, CompressionType::None)?;
+    /// let levels = image.build_pyramid(3)?;
+    ///
+    /// let mut stored = CustomImage { data: CustomImage::compress_rle_varint(&data), compression: CompressionType::RleVarint, ..image };
+    /// stored.set_pyramid(&levels)?;
+    /// let decoded = CustomImage::from_bytes(&stored.to_bytes()?)?;
+    ///
+    /// assert_eq!(decoded.level_count(), 4);
+    /// assert_eq!(decoded.level(0)?.data, data);
+    /// let quarter = decoded.level(2)?;
+    /// assert_eq!((quarter.width, quarter.height), (16, 12));
+    /// assert_eq!(quarter.data, levels[1].data);
+    /// assert_eq!(decoded.smallest_level_for(20, 12), 1);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn set_pyramid(&mut self, levels: &[CustomImage]) -> Result<(), FormatError> {
+        self.levels = levels
+            .iter()
+            .map(|level| {
+                if level.compression != CompressionType::None || level.color_type != self.color_type {
+                    return Err(FormatError::InvalidPyramid(
+                        "levels must be uncompressed and match the image's color type".to_string(),
+                    ));
+                }
+                let data = match self.compression {
+                    CompressionType::Lossy => level.compress_lossy(self.lossy_quality)?,
+                    compression => level.compress(compression)?,
+                };
+                Ok(PyramidLevel { width: level.width, height: level.height, data })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(())
+    }
+
+    /// Returns the number of resolution levels, including the full image.
+    pub fn level_count(&self) -> usize {
+        1 + self.levels.len()
+    }
+
+    /// Decodes resolution level `n` into a new uncompressed image; level 0 is
+    /// the full-resolution image.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidPyramid` if there is no level `n`, or a compression
+    /// error if it fails to decompress.
+    pub fn level(&self, n: usize) -> Result<CustomImage, FormatError> {
+        if n == 0 {
+            let mut full = self.decode_region(self.full_region())?;
+            full.levels = Vec::new();
+            return Ok(full);
+        }
+        let level = self.levels.get(n - 1).ok_or_else(|| {
+            FormatError::InvalidPyramid(format!("level {} requested but the image has {}", n, self.level_count()))
+        })?;
+        let data = self.decompress_pixels(&level.data, level.width, level.height, self.compression)?;
+        let mut image = CustomImage::new(level.width, level.height, self.color_type, data, None, CompressionType::None)?;
+        image.metadata = self.metadata.clone();
+        image.icc_profile = self.icc_profile.clone();
+        image.checksummed = self.checksummed;
+        image.footer = self.footer;
+        image.metadata_capacity = self.metadata_capacity;
+        Ok(image)
+    }
+
+    /// Returns the smallest level that is at least `width` x `height`, or 0
+    /// (full resolution) if no reduced level is large enough.
+    pub fn smallest_level_for(&self, width: u32, height: u32) -> usize {
+        self.levels
+            .iter()
+            .rposition(|level| level.width >= width && level.height >= height)
+            .map_or(0, |i| i + 1)
+    }
+
+    /// Decodes the image scaled to `width` x `height`, returning a new
+    /// uncompressed image with default metadata.
+    ///
+    /// When the image has pyramid levels, the smallest level at least as
+    /// large as the target is scaled instead of the full image.
+    ///
+    /// ```rust
+    /// use image::imageops::FilterType;
+    /// use nor_image::format::{CustomImage, ColorType, CompressionType};
+    ///
+    /// let data: Vec<u8> = (0..48).collect();
+    /// let image = CustomImage::new(4, 4, ColorType::Rgb, data, None, CompressionType::None)?;
+    /// let image = CustomImage::from_bytes(&image.to_bytes()?)?;
+    /// let mut compressed = image.clone();
+    /// compressed.data = compressed.compress(CompressionType::Delta)?;
+    /// compressed.compression = CompressionType::Delta;
+    ///
+    /// let thumbnail = compressed.resized(2, 1, FilterType::Triangle)?;
+    /// assert_eq!((thumbnail.width, thumbnail.height), (2, 1));
+    /// assert_eq!(thumbnail.compression, CompressionType::None);
+    /// assert_eq!(thumbnail.data.len(), 2 * 3);
+    /// assert_eq!(thumbnail.data, image.resized(2, 1, FilterType::Triangle)?.data);
+    /// assert!(image.resized(0, 1, FilterType::Triangle).is_err());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidDimensions` if the target is empty or larger than
+    /// `max_dimension()`, `TooLarge` if its pixels would exceed
+    /// `max_pixel_bytes()`, or a compression error if the source fails to
+    /// decompress.
+    pub fn resized(&self, width: u32, height: u32, filter: FilterType) -> Result<CustomImage, FormatError> {
+        Self::validate_size(width, height, self.color_type)?;
+        let source = self.level(self.smallest_level_for(width, height))?;
+        let data = match self.color_type {
+            ColorType::Gray => resize_pixels::<Luma<u8>>(source, width, height, filter)?,
+            ColorType::Rgb => resize_pixels::<Rgb<u8>>(source, width, height, filter)?,
+            ColorType::GrayAlpha => resize_pixels::<LumaA<u8>>(source, width, height, filter)?,
+        };
+        CustomImage::new(width, height, self.color_type, data, None, CompressionType::None)
+    }
+
+    /// Re-encodes the pyramid levels from compression `from` to `to`, keeping
+    /// them consistent when the image's own data is (de)compressed.
+    pub fn recompress_levels(&mut self, from: CompressionType, to: CompressionType) -> Result<(), FormatError> {
+        for level in &mut self.levels {
+            let data = Self::decompress(&level.data, level.width, level.height, self.color_type, from, self.lossy_quality)?;
+            let mut image = CustomImage::new(level.width, level.height, self.color_type, data, None, CompressionType::None)?;
+            image.lossy_quality = self.lossy_quality;
+            level.data = image.compress(to)?;
+        }
+        Ok(())
+    }
+
+    /// Returns a copy of an uncompressed image at half the size (rounded up),
+    /// averaging each 2x2 block.
+    fn halved(&self) -> Result<CustomImage, FormatError> {
+        let channels = self.color_type.channels() as usize;
+        let (width, height) = (self.width as usize, self.height as usize);
+        let (half_width, half_height) = (width.div_ceil(2), height.div_ceil(2));
+        let mut data = Vec::with_capacity(half_width * half_height * channels);
+        for y in 0..half_height {
+            for x in 0..half_width {
+                let rows = (2 * y)..(2 * y + 2).min(height);
+                let cols = (2 * x)..(2 * x + 2).min(width);
+                let count = (rows.len() * cols.len()) as u32;
+                for c in 0..channels {
+                    let sum: u32 = rows
+                        .clone()
+                        .flat_map(|sy| cols.clone().map(move |sx| (sy * width + sx) * channels + c))
+                        .map(|i| self.data[i] as u32)
+                        .sum();
+                    data.push(((sum + count / 2) / count) as u8);
+                }
+            }
+        }
+        CustomImage::new(half_width as u32, half_height as u32, self.color_type, data, None, CompressionType::None)
+    }
+
+    /// Splits an uncompressed image into square tiles of `tile_size` pixels
+    /// and compresses each tile independently with `compression`.
+    ///
+    /// The result can be partially decoded with `decode_region`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError` if the image is already compressed or tiled,
+    /// if `compression` is `None`, or if `tile_size` is zero.
+    ///
+    /// ```rust
+    /// use nor_image::format::{CustomImage, ColorType, CompressionType, Region};
+    ///
+    /// let data: Vec<u8> = (0..64 * 48).map(|i| (i % 251) as u8).collect();
+    /// let image = CustomImage::new(64, 48, ColorType::Gray, data.clone(), None, CompressionType::None)?;
+    /// let tiled = image.tiled(16, CompressionType::RleVarint)?;
+    /// let decoded = CustomImage::from_bytes(&tiled.to_bytes()?)?;
+    ///
+    /// let region = decoded.decode_region(Region::new(10, 20, 30, 5))?;
+    /// assert_eq!((region.width, region.height), (30, 5));
+    /// assert_eq!(&region.data[..30], &data[20 * 64 + 10..20 * 64 + 40]);
+    /// assert_eq!(decoded.decode_region(decoded.full_region())?.data, data);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn tiled(&self, tile_size: u32, compression: CompressionType) -> Result<CustomImage, FormatError> {
+        if self.compression != CompressionType::None || self.tiles.is_some() {
+            return Err(FormatError::CompressionError(
+                "Only uncompressed images can be tiled".to_string(),
+            ));
+        }
+        if compression == CompressionType::None {
+            return Err(FormatError::CompressionError(
+                "Tiled storage requires a compression type".to_string(),
+            ));
+        }
+        if tile_size == 0 {
+            return Err(FormatError::CompressionError("Tile size must be positive".to_string()));
+        }
+
+        let tile_size = tile_size.min(max_dimension());
+        let regions = tile_regions(self.width, self.height, tile_size);
+        #[cfg(feature = "parallel")]
+        let regions = regions.into_par_iter();
+        #[cfg(not(feature = "parallel"))]
+        let regions = regions.into_iter();
+        let tiles = regions
+            .map(|region| {
+                let tile = self.crop(region)?;
+                tile.compress(compression)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let lengths = tiles.iter().map(|tile| tile.len() as u32).collect();
+        Ok(CustomImage {
+            width: self.width,
+            height: self.height,
+            color_type: self.color_type,
+            data: tiles.concat(),
+            metadata: self.metadata.clone(),
+            compression,
+            tiles: Some(TileIndex { tile_size, lengths }),
+            chunks: None,
+            checksummed: self.checksummed,
+            footer: self.footer,
+            metadata_capacity: self.metadata_capacity,
+            levels: Vec::new(),
+            icc_profile: self.icc_profile.clone(),
+            lossy_quality: self.lossy_quality,
+        })
+    }
+
+    /// Decodes the pixels inside `region` into a new uncompressed image.
+    ///
+    /// For tiled images only the tiles overlapping the region are
+    /// decompressed; other images are decompressed in full and cropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidRegion` if the region is empty or extends past the image
+    /// bounds, or a compression error if a tile fails to decompress.
+    pub fn decode_region(&self, region: Region) -> Result<CustomImage, FormatError> {
+        let in_bounds = region.x.checked_add(region.width).is_some_and(|x1| x1 <= self.width)
+            && region.y.checked_add(region.height).is_some_and(|y1| y1 <= self.height);
+        if region.width == 0 || region.height == 0 || !in_bounds {
+            return Err(FormatError::InvalidRegion(region));
+        }
+
+        let Some(index) = &self.tiles else {
+            let mut full = self.clone();
+            full.data = self.decompress_pixels(&self.data, self.width, self.height, self.compression)?;
+            full.compression = CompressionType::None;
+            full.chunks = None;
+            return full.crop(region);
+        };
+
+        let channels = self.color_type.channels() as usize;
+        let mut out = vec![0u8; region.width as usize * region.height as usize * channels];
+        let tiles: Vec<(Region, Range<usize>)> = tile_regions(self.width, self.height, index.tile_size)
+            .into_iter()
+            .zip(length_ranges(&index.lengths))
+            .filter(|(tile, _)| tile.intersect(&region).is_some())
+            .collect();
+        #[cfg(feature = "parallel")]
+        let pending = tiles.par_iter();
+        #[cfg(not(feature = "parallel"))]
+        let pending = tiles.iter();
+        let decoded = pending
+            .map(|(tile, range)| {
+                self.decompress_pixels(&self.data[range.clone()], tile.width, tile.height, self.compression)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for ((tile, _), pixels) in tiles.iter().zip(decoded) {
+            let expected = tile.width as usize * tile.height as usize * channels;
+            if pixels.len() != expected {
+                return Err(FormatError::DataLengthMismatch { expected, actual: pixels.len() });
+            }
+            let overlap = tile.intersect(&region).ok_or(FormatError::InvalidRegion(region))?;
+            let row_len = overlap.width as usize * channels;
+            for y in overlap.y..overlap.y + overlap.height {
+                let src = ((y - tile.y) as usize * tile.width as usize + (overlap.x - tile.x) as usize) * channels;
+                let dst = ((y - region.y) as usize * region.width as usize + (overlap.x - region.x) as usize) * channels;
+                out[dst..dst + row_len].copy_from_slice(&pixels[src..src + row_len]);
+            }
+        }
+
+        Ok(CustomImage {
+            width: region.width,
+            height: region.height,
+            color_type: self.color_type,
+            data: out,
+            metadata: self.metadata.clone(),
+            compression: CompressionType::None,
+            tiles: None,
+            chunks: None,
+            checksummed: self.checksummed,
+            footer: self.footer,
+            metadata_capacity: self.metadata_capacity,
+            levels: Vec::new(),
+            icc_profile: self.icc_profile.clone(),
+            lossy_quality: self.lossy_quality,
         })
     }
 
+    /// Copies `region` out of an uncompressed, untiled image.
+    fn crop(&self, region: Region) -> Result<CustomImage, FormatError> {
+        let channels = self.color_type.channels() as usize;
+        let row_len = region.width as usize * channels;
+        let mut data = Vec::with_capacity(row_len * region.height as usize);
+        for y in region.y..region.y + region.height {
+            let start = (y as usize * self.width as usize + region.x as usize) * channels;
+            data.extend_from_slice(read_slice(&self.data, start, row_len)?);
+        }
+        let mut cropped = CustomImage::new(
+            region.width,
+            region.height,
+            self.color_type,
+            data,
+            None,
+            CompressionType::None,
+        )?;
+        cropped.metadata = self.metadata.clone();
+        cropped.icc_profile = self.icc_profile.clone();
+        cropped.lossy_quality = self.lossy_quality;
+        Ok(cropped)
+    }
+
     /// Compresses data using RLE encoding.
     ///
     /// Run-length encoding compresses sequences of repeated bytes by storing
@@ -330,6 +1336,13 @@ impl CustomImage {
 
     /// Decompresses RLE encoded data.
     pub fn decompress_rle(data: &[u8]) -> Result<Vec<u8>, FormatError> {
+        Self::decompress_rle_capped(data, usize::MAX)
+    }
+
+    /// Decompresses RLE encoded data, failing as soon as the output would
+    /// grow past `max_len` bytes, so hostile input cannot allocate more
+    /// than the caller expects.
+    pub fn decompress_rle_capped(data: &[u8], max_len: usize) -> Result<Vec<u8>, FormatError> {
         let mut decompressed = Vec::new();
         let mut i = 0;
         
@@ -340,13 +1353,86 @@ impl CustomImage {
             
             let count = data[i] as usize;
             let value = data[i + 1];
-            decompressed.extend(std::iter::repeat(value).take(count));
+            if count > max_len - decompressed.len() {
+                return Err(FormatError::DataLengthMismatch {
+                    expected: max_len,
+                    actual: decompressed.len() + count,
+                });
+            }
+            decompressed.extend(std::iter::repeat_n(value, count));
             i += 2;
         }
         
         Ok(decompressed)
     }
 
+    /// Compresses data using RLE with varint run counts.
+    ///
+    /// Each run is stored as its length in unsigned LEB128 (7 bits per byte,
+    /// high bit set on all but the last byte) followed by the byte value.
+    ///
+    /// Long runs shrink to a few bytes instead of one pair per 255 bytes,
+    /// while data without runs costs the same as 8-bit RLE.
+    pub fn compress_rle_varint(data: &[u8]) -> Vec<u8> {
+        let mut compressed = Vec::new();
+        let mut i = 0;
+
+        while i < data.len() {
+            let current = data[i];
+            let mut count = 1;
+            while i + count < data.len() && data[i + count] == current {
+                count += 1;
+            }
+
+            let mut remaining = count;
+            while remaining >= 0x80 {
+                compressed.push((remaining as u8 & 0x7F) | 0x80);
+                remaining >>= 7;
+            }
+            compressed.push(remaining as u8);
+            compressed.push(current);
+            i += count;
+        }
+
+        compressed
+    }
+
+    /// Decompresses RLE data with varint run counts.
+    ///
+    /// Fails if the data is truncated, a count is zero or overlong, or the
+    /// output would exceed `expected_len` bytes.
+    pub fn decompress_rle_varint(data: &[u8], expected_len: usize) -> Result<Vec<u8>, FormatError> {
+        let invalid = || FormatError::CompressionError("Invalid varint RLE data".to_string());
+        let mut decompressed = Vec::with_capacity(expected_len);
+        let mut i = 0;
+
+        while i < data.len() {
+            let mut count = 0usize;
+            let mut shift = 0;
+            loop {
+                let byte = *data.get(i).ok_or_else(invalid)?;
+                i += 1;
+                if shift >= usize::BITS {
+                    return Err(invalid());
+                }
+                count |= ((byte & 0x7F) as usize) << shift;
+                shift += 7;
+                if byte & 0x80 == 0 {
+                    break;
+                }
+            }
+            let value = *data.get(i).ok_or_else(invalid)?;
+            i += 1;
+
+            if count == 0 || count > expected_len - decompressed.len() {
+                return Err(invalid());
+            }
+            decompressed.extend(std::iter::repeat_n(value, count));
+        }
+
+        Ok(decompressed)
+    }
+
     /// Compresses data using delta encoding.
     pub fn compress_delta(data: &[u8]) -> Vec<u8> {
         let mut compressed = Vec::with_capacity(data.len());
@@ -380,10 +1466,9 @@ impl CustomImage {
     /// Compresses data using lossy compression.
     ///
     /// The lossy method uses block-based quantization. The quality parameter (1-100)
-    /// controls the block size.
+    /// controls the block size; see `lossy_block_size`.
     pub fn compress_lossy(&self, quality: u8) -> Result<Vec<u8>, FormatError> {
-        let quality = quality.clamp(1, 100) as f32 / 100.0;
-        let block_size = if quality < 0.5 { 4 } else { 2 };
+        let block_size = lossy_block_size(quality);
         
         let mut compressed = Vec::new();
         match self.color_type {
@@ -402,32 +1487,29 @@ impl CustomImage {
                     }
                 }
             }
-            ColorType::Rgb => {
-                // For RGB, apply chroma subsampling and block quantization.
+            ColorType::Rgb | ColorType::GrayAlpha => {
+                // For multi-channel data, apply block quantization per channel.
+                let channels = self.color_type.channels() as usize;
                 for y in (0..self.height as usize).step_by(block_size) {
                     for x in (0..self.width as usize).step_by(block_size) {
-                        let mut r_sum = 0u32;
-                        let mut g_sum = 0u32;
-                        let mut b_sum = 0u32;
-                        let mut count = 0;
+                        let mut sums = vec![0u32; channels];
+                        let mut count = 0u32;
 
-                        // Average RGB values for the block.
+                        // Average each channel over the block.
                         for dy in 0..block_size {
                             for dx in 0..block_size {
                                 if y + dy < self.height as usize && x + dx < self.width as usize {
-                                    let idx = ((y + dy) * self.width as usize + (x + dx)) * 3;
-                                    r_sum += self.data[idx] as u32;
-                                    g_sum += self.data[idx + 1] as u32;
-                                    b_sum += self.data[idx + 2] as u32;
+                                    let idx = ((y + dy) * self.width as usize + (x + dx)) * channels;
+                                    for (sum, &value) in sums.iter_mut().zip(&self.data[idx..idx + channels]) {
+                                        *sum += value as u32;
+                                    }
                                     count += 1;
                                 }
                             }
                         }
 
                         if count > 0 {
-                            compressed.push((r_sum / count) as u8);
-                            compressed.push((g_sum / count) as u8);
-                            compressed.push((b_sum / count) as u8);
+                            compressed.extend(sums.iter().map(|&sum| (sum / count) as u8));
                         }
                     }
                 }
@@ -438,6 +1520,48 @@ impl CustomImage {
     }
 
     /// Decompresses data that was compressed using lossy compression.
+    ///
+    /// Multi-channel images are stored as a grid of block averages, one
+    /// block per `block_size` pixels in each direction; the grid size follows
+    /// from the image dimensions. Decoding interpolates bilinearly between
+    /// the centers of neighboring blocks rather than repeating each block
+    /// average, which removes the visible block edges and lowers the error
+    /// on smooth content at the same stored size:
+    ///
+    /// ```rust
+    /// use nor_image::format::{CustomImage, ColorType, CompressionType};
+    ///
+    /// // A smooth RGB image with some curvature.
+    /// let (width, height) = (64u32, 48u32);
+    /// let data: Vec<u8> = (0..width * height)
+    ///     .flat_map(|i| {
+    ///         let (x, y) = ((i % width) as f32, (i / width) as f32);
+    ///         let v = 128.0 + 100.0 * (x / 9.0).sin() * (y / 7.0).cos();
+    ///         [v as u8, (x * 3.0) as u8, (255.0 - y * 4.0) as u8]
+    ///     })
+    ///     .collect();
+    /// let image = CustomImage::new(width, height, ColorType::Rgb, data.clone(), None, CompressionType::None)?;
+    /// let compressed = image.compress(CompressionType::Lossy)?;
+    /// let decoded = CustomImage::decompress(&compressed, width, height, ColorType::Rgb, CompressionType::Lossy, image.lossy_quality)?;
+    /// assert_eq!(decoded.len(), data.len());
+    ///
+    /// // The previous decoder repeated each 2x2 block average.
+    /// let replicated: Vec<u8> = (0..(width * height) as usize)
+    ///     .flat_map(|i| {
+    ///         let (x, y) = (i % width as usize, i / width as usize);
+    ///         let block = ((y / 2) * (width as usize / 2) + x / 2) * 3;
+    ///         compressed[block..block + 3].to_vec()
+    ///     })
+    ///     .collect();
+    ///
+    /// let psnr = |decoded: &[u8]| {
+    ///     let mse = data.iter().zip(decoded).map(|(&a, &b)| (a as f64 - b as f64).powi(2)).sum::<f64>()
+    ///         / data.len() as f64;
+    ///     10.0 * (255.0 * 255.0 / mse).log10()
+    /// };
+    /// assert!(psnr(&decoded) > psnr(&replicated) + 1.0);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
     pub fn decompress_lossy(
         compressed: &[u8],
         width: u32,
@@ -445,39 +1569,40 @@ impl CustomImage {
         color_type: ColorType,
         quality: u8,
     ) -> Result<Vec<u8>, FormatError> {
-        let quality = quality.clamp(1, 100) as f32 / 100.0;
-        let block_size = if quality < 0.5 { 4 } else { 2 };
+        let block_size = lossy_block_size(quality);
         
         let mut decompressed = Vec::new();
         match color_type {
             ColorType::Gray => {
-                for y in 0..height as usize {
-                    for x in 0..width as usize {
-                        let block_x = (x / block_size) * block_size;
-                        let block_y = (y / block_size) * block_size;
-                        let block_idx = (block_y * width as usize + block_x) / (block_size * block_size);
-                        
-                        if block_idx < compressed.len() {
-                            decompressed.push(compressed[block_idx]);
-                        } else {
-                            decompressed.push(0);
-                        }
-                    }
+                // The encoder averages consecutive runs of `block_size²`
+                // samples and stores any incomplete trailing run verbatim.
+                let run = block_size * block_size;
+                let len = width as usize * height as usize;
+                let full_runs = len / run;
+                for i in 0..len {
+                    let idx = if i / run < full_runs { i / run } else { full_runs + i - full_runs * run };
+                    decompressed.push(compressed.get(idx).copied().unwrap_or(0));
                 }
             }
-            ColorType::Rgb => {
-                for y in 0..height as usize {
-                    for x in 0..width as usize {
-                        let block_x = (x / block_size) * block_size;
-                        let block_y = (y / block_size) * block_size;
-                        let block_idx = ((block_y * width as usize + block_x) / (block_size * block_size)) * 3;
-                        
-                        if block_idx + 2 < compressed.len() {
-                            decompressed.push(compressed[block_idx]);     // R
-                            decompressed.push(compressed[block_idx + 1]); // G
-                            decompressed.push(compressed[block_idx + 2]); // B
-                        } else {
-                            decompressed.extend_from_slice(&[0, 0, 0]);
+            ColorType::Rgb | ColorType::GrayAlpha => {
+                // Blocks are stored row by row, one averaged pixel each,
+                // including the partial blocks along the right and bottom edges.
+                let channels = color_type.channels() as usize;
+                let blocks_per_row = (width as usize).div_ceil(block_size);
+                let columns = lossy_block_weights(width as usize, block_size);
+                let rows = lossy_block_weights(height as usize, block_size);
+                // Missing blocks in truncated data decode as zero.
+                let sample = |bx: usize, by: usize, channel: usize| {
+                    let idx = (by * blocks_per_row + bx) * channels + channel;
+                    compressed.get(idx).copied().unwrap_or(0) as f32
+                };
+                decompressed.reserve(width as usize * height as usize * channels);
+                for &(top, bottom, ty) in &rows {
+                    for &(left, right, tx) in &columns {
+                        for channel in 0..channels {
+                            let upper = sample(left, top, channel) * (1.0 - tx) + sample(right, top, channel) * tx;
+                            let lower = sample(left, bottom, channel) * (1.0 - tx) + sample(right, bottom, channel) * tx;
+                            decompressed.push((upper * (1.0 - ty) + lower * ty).round() as u8);
                         }
                     }
                 }
@@ -488,85 +1613,406 @@ impl CustomImage {
     }
 
     /// Compresses the image data based on the provided compression type.
+    /// Lossy data is encoded at `lossy_quality`.
     #[allow(dead_code)]
     pub fn compress(&self, compression_type: CompressionType) -> Result<Vec<u8>, FormatError> {
         match compression_type {
             CompressionType::None => Ok(self.data.clone()),
             CompressionType::RLE => Ok(Self::compress_rle(&self.data)),
             CompressionType::Delta => Ok(Self::compress_delta(&self.data)),
-            CompressionType::Lossy => self.compress_lossy(50),
+            CompressionType::Lossy => self.compress_lossy(self.lossy_quality),
+            CompressionType::RleVarint => Ok(Self::compress_rle_varint(&self.data)),
         }
     }
 
-    /// Decompresses data based on the provided compression type.
-    #[allow(dead_code)]
+    /// Decompresses data based on the provided compression type, decoding
+    /// lossy data at `lossy_quality`. Other codecs ignore the quality.
     pub fn decompress(
         compressed: &[u8],
         width: u32,
         height: u32,
         color_type: ColorType,
         compression_type: CompressionType,
+        lossy_quality: u8,
     ) -> Result<Vec<u8>, FormatError> {
         match compression_type {
             CompressionType::None => Ok(compressed.to_vec()),
             CompressionType::RLE => Self::decompress_rle(compressed),
             CompressionType::Delta => Ok(Self::decompress_delta(compressed)),
-            CompressionType::Lossy => Self::decompress_lossy(compressed, width, height, color_type, 50),
+            CompressionType::Lossy => Self::decompress_lossy(compressed, width, height, color_type, lossy_quality),
+            CompressionType::RleVarint => {
+                let expected_len = width as usize * height as usize * color_type.channels() as usize;
+                Self::decompress_rle_varint(compressed, expected_len)
+            }
         }
     }
 
-    /// Serializes the `CustomImage` into a byte vector.
+    /// Decompresses `compressed`, the pixel data of this image or of one of
+    /// its tiles or pyramid levels, decoding lossy data at `lossy_quality`.
     ///
-    /// The format is:
-    /// - MAGIC_NUMBER (4 bytes)
-    /// - VERSION (1 byte)
-    /// - COLOR_TYPE (1 byte)
+    /// The quality is stored in the header, so lossy files decode at the
+    /// quality they were written with, and lossy data of the wrong length is
+    /// rejected when read:
+    ///
+    /// ```rust
+    /// use nor_image::format::{CustomImage, ColorType, CompressionType, FormatError};
+    ///
+    /// let data: Vec<u8> = (0..16 * 16 * 3).map(|i| (i % 97) as u8).collect();
+    /// let mut image = CustomImage::new(16, 16, ColorType::Rgb, data, None, CompressionType::None)?;
+    /// image.lossy_quality = 20; // 5x5 blocks
+    /// let stored = CustomImage { data: image.compress(CompressionType::Lossy)?, compression: CompressionType::Lossy, ..image };
+    /// assert_eq!(stored.data.len(), 4 * 4 * 3);
+    ///
+    /// let decoded = CustomImage::from_bytes(&stored.to_bytes()?)?;
+    /// assert_eq!(decoded.lossy_quality, 20);
+    /// let pixels = decoded.decompress_pixels(&decoded.data, 16, 16, CompressionType::Lossy)?;
+    /// assert_eq!(pixels, CustomImage::decompress_lossy(&stored.data, 16, 16, ColorType::Rgb, 20)?);
+    ///
+    /// let truncated = CustomImage { data: stored.data[..47].to_vec(), ..stored };
+    /// assert!(matches!(
+    ///     CustomImage::from_bytes(&truncated.to_bytes()?),
+    ///     Err(FormatError::DataLengthMismatch { expected: 48, actual: 47 })
+    /// ));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn decompress_pixels(
+        &self,
+        compressed: &[u8],
+        width: u32,
+        height: u32,
+        compression_type: CompressionType,
+    ) -> Result<Vec<u8>, FormatError> {
+        Self::decompress(compressed, width, height, self.color_type, compression_type, self.lossy_quality)
+    }
+
+    /// Returns whether the header stores the pixel data length, because no
+    /// other field implies it.
+    fn stores_data_len(&self) -> bool {
+        stores_data_len(self.compression, self.tiles.is_some() || self.chunks.is_some())
+    }
+
+    /// Serializes the `CustomImage` into a byte vector.
+    ///
+    /// The format is:
+    /// - MAGIC_NUMBER (4 bytes)
+    /// - VERSION (1 byte)
+    /// - COLOR_TYPE (1 byte)
     /// - Width (4 bytes, little-endian)
     /// - Height (4 bytes, little-endian)
     /// - Compression type (1 byte)
+    /// - Flags (1 byte)
+    /// - Lossy quality (1 byte), if it is not `DEFAULT_LOSSY_QUALITY`
+    /// - Pixel data length (8 bytes, little-endian), for RLE and varint RLE
+    ///   data that is neither tiled nor chunked; other layouts imply it
+    /// - Tile index, if tiled: tile size and tile count (4 bytes each,
+    ///   little-endian), then each tile's compressed length (4 bytes each)
+    /// - Chunk index, if chunked: chunk size and chunk count, then each
+    ///   chunk's compressed length, laid out like the tile index
+    /// - Pyramid index, if there are levels: level count (4 bytes), then each
+    ///   level's width, height and compressed length (4 bytes each)
     /// - Metadata length (4 bytes, little-endian)
-    /// - Metadata (JSON)
+    /// - Metadata (JSON), padded with spaces to `metadata_capacity` if set
+    /// - ICC profile, if present: its length (4 bytes, little-endian), then
+    ///   the profile
     /// - Image data
-    /// - SHA256 checksum (32 bytes)
+    /// - Pyramid level data, in index order
+    /// - SHA256 checksum (32 bytes), unless `checksummed` is false
+    /// - `FOOTER_MAGIC` (4 bytes), if `footer` is set
+    ///
+    /// # Errors
+    ///
+    /// Returns `MetadataError` if the serialized metadata or the reserved
+    /// capacity is larger than `MAX_METADATA_LEN`, or `CompressionError` if
+    /// `lossy_quality` is outside 1-100.
     pub fn to_bytes(&self) -> Result<Vec<u8>, FormatError> {
-        let metadata_json = serde_json::to_string(&self.metadata)
-            .unwrap_or_else(|_| "{}".to_string());
-        let metadata_bytes = metadata_json.as_bytes();
-        
-        if metadata_bytes.len() > u32::MAX as usize {
-            return Err(FormatError::MetadataError("Metadata size exceeds limit".to_string()));
+        let metadata_bytes = self.metadata_bytes()?;
+        let tile_index_len = self.tiles.as_ref().map_or(0, |index| 8 + 4 * index.lengths.len());
+        let chunk_index_len = self.chunks.as_ref().map_or(0, |index| 8 + 4 * index.lengths.len());
+        let pyramid_index_len = if self.levels.is_empty() { 0 } else { 4 + 12 * self.levels.len() };
+        let quality_len = usize::from(self.lossy_quality != DEFAULT_LOSSY_QUALITY);
+        let data_len_len = if self.stores_data_len() { 8 } else { 0 };
+        let header_len = MAGIC_NUMBER.len() + 1 + 1 + 4 + 4 + 1 + 1 + quality_len + data_len_len
+            + tile_index_len + chunk_index_len + pyramid_index_len + 4 + metadata_bytes.len()
+            + self.icc_profile.as_ref().map_or(0, |profile| 4 + profile.len());
+        let levels_len: usize = self.levels.iter().map(|level| level.data.len()).sum();
+        let checksum_len = if self.checksummed { 32 } else { 0 }; // SHA256 hash
+        let footer_len = if self.footer { FOOTER_MAGIC.len() } else { 0 };
+        let total_size = header_len + self.data.len() + levels_len + checksum_len + footer_len;
+        let mut bytes = Vec::with_capacity(total_size);
+        self.write_to(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Serializes the `CustomImage` directly into a writer.
+    ///
+    /// Produces exactly the same bytes as `to_bytes`, but streams the header,
+    /// metadata, and pixel data to `w` while hashing them incrementally, so the
+    /// whole file never has to be held in memory a second time.
+    ///
+    /// Images with `checksummed` set to false are written with the
+    /// no-checksum flag and without the trailing hash, skipping the hashing
+    /// entirely:
+    ///
+    /// ```rust
+    /// use nor_image::format::{CustomImage, ColorType, CompressionType};
+    ///
+    /// let mut image = CustomImage::new(2, 2, ColorType::Gray, vec![1, 2, 3, 4], None, CompressionType::None)?;
+    /// let checksummed = image.to_bytes()?;
+    /// image.checksummed = false;
+    /// let unchecked = image.to_bytes()?;
+    /// assert_eq!(unchecked.len(), checksummed.len() - 32);
+    ///
+    /// let decoded = CustomImage::from_bytes(&unchecked)?;
+    /// assert!(!decoded.checksummed);
+    /// assert_eq!(decoded.data, image.data);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `MetadataError` if the metadata is too large, `CompressionError`
+    /// if `lossy_quality` is outside 1-100, or `IoError` if writing to `w`
+    /// fails.
+    ///
+    /// ```rust
+    /// use nor_image::format::{CustomImage, ColorType, CompressionType};
+    ///
+    /// let image = CustomImage::new(2, 2, ColorType::Gray, vec![1, 2, 3, 4], None, CompressionType::None)?;
+    /// let mut streamed = Vec::new();
+    /// image.write_to(&mut streamed)?;
+    /// assert_eq!(streamed, image.to_bytes()?);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn write_to<W: Write>(&self, w: &mut W) -> Result<(), FormatError> {
+        let metadata_bytes = self.metadata_bytes()?;
+        if let Some(profile) = &self.icc_profile {
+            check_icc_profile_len(profile.len())?;
         }
+        check_lossy_quality(self.lossy_quality)?;
+        let mut writer = HashingWriter { inner: w, hasher: self.checksummed.then(Sha256::new) };
 
-        let header_len = MAGIC_NUMBER.len() + 1 + 1 + 4 + 4 + 1 + 4 + metadata_bytes.len();
-        let total_size = header_len + self.data.len() + 32; // 32 bytes for SHA256 hash
-        let mut bytes = Vec::with_capacity(total_size);
-        
         // Write header.
-        bytes.extend_from_slice(MAGIC_NUMBER);
-        bytes.push(VERSION);
-        bytes.push(self.color_type as u8);
-        bytes.extend_from_slice(&self.width.to_le_bytes());
-        bytes.extend_from_slice(&self.height.to_le_bytes());
-        bytes.push(self.compression as u8);
-        
+        writer.write_all(MAGIC_NUMBER)?;
+        writer.write_all(&[VERSION, self.color_type as u8])?;
+        writer.write_all(&self.width.to_le_bytes())?;
+        writer.write_all(&self.height.to_le_bytes())?;
+        writer.write_all(&[self.compression as u8])?;
+
+        // Write flags, then the lossy quality, data length and the tile and
+        // chunk indexes that are present.
+        let mut flags = 0;
+        if self.tiles.is_some() {
+            flags |= FLAG_TILED;
+        }
+        if self.chunks.is_some() {
+            flags |= FLAG_CHUNKED;
+        }
+        if !self.checksummed {
+            flags |= FLAG_NO_CHECKSUM;
+        }
+        if !self.levels.is_empty() {
+            flags |= FLAG_PYRAMID;
+        }
+        if self.footer {
+            flags |= FLAG_FOOTER;
+        }
+        if self.metadata_capacity.is_some() {
+            flags |= FLAG_RESERVED_METADATA;
+        }
+        if self.icc_profile.is_some() {
+            flags |= FLAG_ICC_PROFILE;
+        }
+        if self.lossy_quality != DEFAULT_LOSSY_QUALITY {
+            flags |= FLAG_LOSSY_QUALITY;
+        }
+        writer.write_all(&[flags])?;
+        if flags & FLAG_LOSSY_QUALITY != 0 {
+            writer.write_all(&[self.lossy_quality])?;
+        }
+        if self.stores_data_len() {
+            writer.write_all(&(self.data.len() as u64).to_le_bytes())?;
+        }
+        if let Some(index) = &self.tiles {
+            writer.write_all(&index.tile_size.to_le_bytes())?;
+            writer.write_all(&(index.lengths.len() as u32).to_le_bytes())?;
+            for length in &index.lengths {
+                writer.write_all(&length.to_le_bytes())?;
+            }
+        }
+        if let Some(index) = &self.chunks {
+            writer.write_all(&index.chunk_size.to_le_bytes())?;
+            writer.write_all(&(index.lengths.len() as u32).to_le_bytes())?;
+            for length in &index.lengths {
+                writer.write_all(&length.to_le_bytes())?;
+            }
+        }
+        if !self.levels.is_empty() {
+            writer.write_all(&(self.levels.len() as u32).to_le_bytes())?;
+            for level in &self.levels {
+                writer.write_all(&level.width.to_le_bytes())?;
+                writer.write_all(&level.height.to_le_bytes())?;
+                writer.write_all(&(level.data.len() as u32).to_le_bytes())?;
+            }
+        }
+
         // Write metadata.
-        bytes.extend_from_slice(&(metadata_bytes.len() as u32).to_le_bytes());
-        bytes.extend_from_slice(metadata_bytes);
-        
-        // Write image data.
-        bytes.extend_from_slice(&self.data);
-        
-        // Calculate and append checksum.
-        let mut hasher = Sha256::new();
-        hasher.update(&bytes);
-        let checksum: sha2::digest::generic_array::GenericArray<u8, sha2::digest::typenum::UInt<sha2::digest::typenum::UInt<sha2::digest::typenum::UInt<sha2::digest::typenum::UInt<sha2::digest::typenum::UInt<sha2::digest::typenum::UInt<sha2::digest::typenum::UTerm, sha2::digest::consts::B1>, sha2::digest::consts::B0>, sha2::digest::consts::B0>, sha2::digest::consts::B0>, sha2::digest::consts::B0>, sha2::digest::consts::B0>> = hasher.finalize();
-        bytes.extend_from_slice(&checksum);
-        
-        Ok(bytes)
+        writer.write_all(&(metadata_bytes.len() as u32).to_le_bytes())?;
+        writer.write_all(&metadata_bytes)?;
+        if let Some(profile) = &self.icc_profile {
+            writer.write_all(&(profile.len() as u32).to_le_bytes())?;
+            writer.write_all(profile)?;
+        }
+
+        // Write image data, then any pyramid levels.
+        writer.write_all(&self.data)?;
+        for level in &self.levels {
+            writer.write_all(&level.data)?;
+        }
+
+        // Append checksum of everything written so far, then the footer.
+        let HashingWriter { inner, hasher } = writer;
+        if let Some(hasher) = hasher {
+            inner.write_all(&hasher.finalize())?;
+        }
+        if self.footer {
+            inner.write_all(FOOTER_MAGIC)?;
+        }
+        Ok(())
+    }
+
+    /// Serializes the metadata to JSON, enforcing `MAX_METADATA_LEN`.
+    fn metadata_bytes(&self) -> Result<Vec<u8>, FormatError> {
+        serialize_metadata(&self.metadata, self.metadata_capacity)
+    }
+
+    /// Applies `edit` to the metadata of a serialized image and writes it
+    /// back in place, leaving the pixel data untouched.
+    ///
+    /// Only files written with a `metadata_capacity` can be edited this way,
+    /// and the edited metadata must fit in the reserved space; otherwise
+    /// `false` is returned and the file is left as it was. The checksum, if
+    /// present, is recomputed over the whole file, so the pixel data is still
+    /// read once; files without a checksum only have the metadata block
+    /// written.
+    ///
+    /// ```rust
+    /// use std::io::Cursor;
+    /// use nor_image::format::{CustomImage, ColorType, CompressionType, ImageMetadata};
+    ///
+    /// let mut image = CustomImage::new(2, 2, ColorType::Gray, vec![1, 2, 3, 4], None, CompressionType::None)?;
+    /// image.metadata_capacity = Some(256);
+    /// let mut file = Cursor::new(image.to_bytes()?);
+    /// let len = file.get_ref().len();
+    ///
+    /// assert!(CustomImage::rewrite_metadata(&mut file, |metadata| {
+    ///     metadata.author = Some("Grish".to_string());
+    /// })?);
+    /// assert_eq!(file.get_ref().len(), len);
+    ///
+    /// let decoded = CustomImage::from_bytes(file.get_ref())?;
+    /// assert_eq!(decoded.metadata.author.as_deref(), Some("Grish"));
+    /// assert_eq!(decoded.metadata_capacity, Some(256));
+    ///
+    /// // Metadata that outgrows the reservation is not written.
+    /// assert!(!CustomImage::rewrite_metadata(&mut file, |metadata| {
+    ///     metadata.custom_fields.insert("notes".to_string(), "x".repeat(512));
+    /// })?);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns a header error if `file` is not a valid image, or `IoError`
+    /// if reading or writing fails.
+    pub fn rewrite_metadata<F, E>(file: &mut F, edit: E) -> Result<bool, FormatError>
+    where
+        F: Read + Write + Seek,
+        E: FnOnce(&mut ImageMetadata),
+    {
+        file.seek(SeekFrom::Start(0))?;
+        let mut counter = CountingReader { inner: &mut *file, count: 0 };
+        let mut header = Header::read(&mut counter)?;
+        let icc_len = header.icc_profile.as_ref().map_or(0, |profile| 4 + profile.len() as u64);
+        let metadata_end = counter.count - icc_len;
+        let Some(capacity) = header.metadata_capacity else {
+            return Ok(false);
+        };
+
+        edit(&mut header.metadata);
+        let metadata_bytes = serialize_metadata(&header.metadata, Some(capacity))?;
+        if metadata_bytes.len() > capacity as usize {
+            return Ok(false);
+        }
+        file.seek(SeekFrom::Start(metadata_end - capacity as u64))?;
+        file.write_all(&metadata_bytes)?;
+
+        if header.checksummed {
+            let footer_len = if header.footer { FOOTER_MAGIC.len() as u64 } else { 0 };
+            let checksum_offset = file
+                .seek(SeekFrom::End(0))?
+                .checked_sub(32 + footer_len)
+                .filter(|&offset| offset >= metadata_end)
+                .ok_or(FormatError::DataTooShort)?;
+            file.seek(SeekFrom::Start(0))?;
+            let mut hasher = Sha256::new();
+            io::copy(&mut (&mut *file).take(checksum_offset), &mut hasher)?;
+            file.seek(SeekFrom::Start(checksum_offset))?;
+            file.write_all(&hasher.finalize())?;
+        }
+        file.flush()?;
+        Ok(true)
     }
 
     /// Deserializes a `CustomImage` from a byte slice.
     ///
+    /// The checksum is verified before anything else is parsed, so any
+    /// corruption is reported as `ChecksumMismatch`. Files written with the
+    /// no-checksum flag are parsed without verification. Files with the
+    /// footer flag must end with `FOOTER_MAGIC`; files without it are read
+    /// as before.
+    ///
+    /// ```rust
+    /// use nor_image::format::{CustomImage, ColorType, CompressionType, FOOTER_MAGIC};
+    ///
+    /// let mut image = CustomImage::new(2, 2, ColorType::Gray, vec![1, 2, 3, 4], None, CompressionType::None)?;
+    /// image.footer = true;
+    /// let bytes = image.to_bytes()?;
+    /// assert!(bytes.ends_with(FOOTER_MAGIC));
+    /// assert!(CustomImage::from_bytes(&bytes)?.footer);
+    /// assert!(CustomImage::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// The layout does not depend on the platform. A hand-built file with a
+    /// width of `[2, 1, 0, 0]` is 258 pixels wide everywhere, and every
+    /// truncation of its header is rejected as too short:
+    ///
+    /// ```rust
+    /// use nor_image::format::{CustomImage, ColorType, FormatError};
+    ///
+    /// let metadata = br#"{"creation_date":7,"custom_fields":{}}"#;
+    /// let mut bytes = b"CIMG".to_vec();
+    /// bytes.extend([4, ColorType::Gray as u8]);
+    /// bytes.extend([2, 1, 0, 0]); // width 0x0102
+    /// bytes.extend([1, 0, 0, 0]); // height 1
+    /// bytes.extend([0, 0x04]); // uncompressed, no checksum
+    /// bytes.extend((metadata.len() as u32).to_le_bytes());
+    /// bytes.extend(metadata);
+    /// let header_len = bytes.len();
+    /// bytes.extend([42u8; 258]);
+    ///
+    /// let image = CustomImage::from_bytes(&bytes)?;
+    /// assert_eq!((image.width, image.height), (258, 1));
+    /// assert_eq!(image.metadata.creation_date, 7);
+    /// assert_eq!(image.to_bytes()?[..16], bytes[..16]);
+    ///
+    /// for len in 0..header_len {
+    ///     assert!(matches!(CustomImage::from_bytes(&bytes[..len]), Err(FormatError::DataTooShort)));
+    ///     assert!(matches!(CustomImage::from_reader(&bytes[..len]), Err(FormatError::DataTooShort)));
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    ///
     /// # Errors
     ///
     /// Returns an error if:
@@ -574,70 +2020,1111 @@ impl CustomImage {
     /// - The magic number is invalid.
     /// - The version is unsupported.
     /// - The color type is unsupported.
-    /// - The pixel data length does not match the expected size.
+    /// - The dimensions are zero or exceed `max_dimension()`.
+    /// - The decoded pixel data would exceed `max_pixel_bytes()`.
+    /// - The metadata block is larger than `MAX_METADATA_LEN`.
+    /// - The stored lossy quality is outside 1-100.
+    /// - The pixel data, once decoded, does not hold exactly `width * height`
+    ///   pixels, or lossy data is not the length its quality produces. This
+    ///   is checked without decoding.
+    /// - The tile or chunk index does not match the image size or pixel data.
+    /// - The footer flag is set but the footer magic is missing.
+    ///
+    /// Version 2 files, which predate the flags byte, and version 3 files,
+    /// which store no RLE data length, are still accepted.
+    ///
+    /// ```rust
+    /// use nor_image::format::{CustomImage, ColorType, CompressionType, FormatError};
+    ///
+    /// let data: Vec<u8> = (0..48).map(|i| i / 5).collect();
+    /// for compression in [CompressionType::None, CompressionType::RLE, CompressionType::Delta, CompressionType::RleVarint] {
+    ///     let image = CustomImage::new(4, 4, ColorType::Rgb, data.clone(), None, CompressionType::None)?;
+    ///     let mut stored = image.compress(compression)?;
+    ///     stored.truncate(stored.len() - 2);
+    ///     let truncated = CustomImage { data: stored, compression, ..image };
+    ///     assert!(matches!(
+    ///         CustomImage::from_bytes(&truncated.to_bytes()?),
+    ///         Err(FormatError::DataLengthMismatch { expected: 48, .. })
+    ///     ));
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// Malformed input is rejected with an error, never a panic:
+    ///
+    /// ```rust
+    /// assert!(nor_image::format::CustomImage::from_bytes(b"CIMG\x04 not an image").is_err());
+    /// ```
     pub fn from_bytes(bytes: &[u8]) -> Result<Self, FormatError> {
-        let min_len = MAGIC_NUMBER.len() + 1 + 1 + 4 + 4 + 1 + 4 + 32;
-        if bytes.len() < min_len {
+        let checksummed = !has_flag(bytes, FLAG_NO_CHECKSUM);
+        let footer_len = if has_flag(bytes, FLAG_FOOTER) { FOOTER_MAGIC.len() } else { 0 };
+        if bytes.len() < min_file_len(bytes) {
             return Err(FormatError::DataTooShort);
         }
+        let (bytes, footer) = bytes.split_at(bytes.len() - footer_len);
+        if footer != &FOOTER_MAGIC[..footer_len] {
+            return Err(FormatError::MissingFooter);
+        }
         
         // Verify checksum.
-        let data_bytes = &bytes[..bytes.len() - 32];
-        let file_hash = &bytes[bytes.len() - 32..];
-        let mut hasher = Sha256::new();
-        hasher.update(data_bytes);
-        if &hasher.finalize()[..] != file_hash {
-            return Err(FormatError::ChecksumMismatch);
-        }
+        let body = if checksummed {
+            let (body, file_hash) = bytes.split_at(bytes.len() - 32);
+            let mut hasher = Sha256::new();
+            hasher.update(body);
+            if &hasher.finalize()[..] != file_hash {
+                return Err(FormatError::ChecksumMismatch);
+            }
+            body
+        } else {
+            bytes
+        };
         
-        // Read header.
-        if &bytes[0..MAGIC_NUMBER.len()] != MAGIC_NUMBER {
+        // Everything after the header is pixel data. Its length is bounded by
+        // the input itself.
+        let mut reader = body;
+        let header = Header::read(&mut reader)?;
+        header.into_image(reader.to_vec())
+    }
+
+    /// Returns the format version of `bytes` if they look like a `.nor` file.
+    ///
+    /// This is a cheap check for file type detection: the magic number and
+    /// version must match, and there must be room for the fixed header
+    /// fields and the checksum. The metadata, pixel data and checksum are
+    /// not parsed or verified, so decoding can still fail.
+    ///
+    /// ```rust
+    /// use nor_image::format::{CustomImage, ColorType, CompressionType};
+    ///
+    /// let image = CustomImage::new(2, 2, ColorType::Gray, vec![1, 2, 3, 4], None, CompressionType::None)?;
+    /// let bytes = image.to_bytes()?;
+    /// assert_eq!(CustomImage::nor_version(&bytes), Some(4));
+    /// assert!(CustomImage::is_nor(&bytes));
+    ///
+    /// assert!(!CustomImage::is_nor(b"\x89PNG\r\n\x1a\n"));
+    /// assert!(!CustomImage::is_nor(&bytes[..20]));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn nor_version(bytes: &[u8]) -> Option<u8> {
+        let version = *bytes.get(MAGIC_NUMBER.len())?;
+        let plausible = bytes.starts_with(MAGIC_NUMBER)
+            && (MIN_VERSION..=VERSION).contains(&version)
+            && bytes.len() >= min_file_len(bytes);
+        plausible.then_some(version)
+    }
+
+    /// Returns whether `bytes` look like a `.nor` file; see `nor_version`.
+    pub fn is_nor(bytes: &[u8]) -> bool {
+        Self::nor_version(bytes).is_some()
+    }
+
+    /// Like `is_nor`, but reads only the first few dozen bytes of `reader`.
+    ///
+    /// ```rust
+    /// use nor_image::format::{CustomImage, ColorType, CompressionType};
+    ///
+    /// let image = CustomImage::new(64, 64, ColorType::Rgb, vec![0; 64 * 64 * 3], None, CompressionType::None)?;
+    /// let bytes = image.to_bytes()?;
+    /// let mut reader = &bytes[..];
+    /// assert!(CustomImage::is_nor_reader(&mut reader)?);
+    /// assert!(reader.len() > 64 * 64 * 3);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an I/O error if reading fails.
+    pub fn is_nor_reader<R: Read>(reader: R) -> io::Result<bool> {
+        let mut prefix = Vec::with_capacity(NOR_SNIFF_LEN);
+        reader.take(NOR_SNIFF_LEN as u64).read_to_end(&mut prefix)?;
+        Ok(Self::is_nor(&prefix))
+    }
+
+    /// Deserializes a `CustomImage` from a reader, such as a file or socket.
+    ///
+    /// The header is read and validated first, then the metadata and pixel
+    /// data, hashing everything as it arrives; the trailing checksum is
+    /// compared last. Exactly one image is consumed and the reader is left
+    /// just past its checksum, so several images can follow each other on
+    /// one stream. Only RLE images from files older than version 4 store no
+    /// data length; they are read until end of stream, and fail if that runs
+    /// past the longest data RLE can produce for the image. Files written with
+    /// the no-checksum flag are read without verification, and a footer is
+    /// consumed after the checksum when the footer flag is set.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as `from_bytes`, `DataTooShort` if the stream
+    /// ends early, or `IoError` if reading fails.
+    ///
+    /// ```rust
+    /// use nor_image::format::{CustomImage, ColorType, CompressionType};
+    ///
+    /// let image = CustomImage::new(2, 2, ColorType::Gray, vec![1, 2, 3, 4], None, CompressionType::None)?;
+    /// let mut stream = image.to_bytes()?;
+    /// stream.extend_from_slice(b"next message");
+    ///
+    /// let mut reader = &stream[..];
+    /// assert_eq!(CustomImage::from_reader(&mut reader)?, image);
+    /// assert_eq!(reader, b"next message");
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self, FormatError> {
+        let mut reader = HashingReader { inner: reader, hasher: Sha256::new() };
+        let header = Header::read(&mut reader)?;
+
+        let HashingReader { mut inner, mut hasher } = reader;
+        let checksum_len = if header.checksummed { 32 } else { 0 };
+        let footer_len = if header.footer { FOOTER_MAGIC.len() } else { 0 };
+        let (data, file_hash, footer) = match header.data_len() {
+            Some(len) => {
+                let data = read_vec(&mut inner, len)?;
+                let file_hash = read_vec(&mut inner, checksum_len)?;
+                (data, file_hash, read_vec(&mut inner, footer_len)?)
+            }
+            None => {
+                // Older RLE files store no data length, so they run to the
+                // end of the stream, bounded by the longest data RLE can
+                // produce for the image.
+                let limit = max_stored_len(header.width, header.height, header.color_type)
+                    + header.levels_len() + checksum_len + footer_len;
+                let mut rest = Vec::new();
+                (&mut inner).take(limit as u64 + 1).read_to_end(&mut rest)?;
+                if rest.len() > limit {
+                    return Err(FormatError::CompressionError(format!(
+                        "Pixel data runs past the {} bytes RLE can take",
+                        limit
+                    )));
+                }
+                if rest.len() < checksum_len + footer_len {
+                    return Err(FormatError::DataTooShort);
+                }
+                let footer = rest.split_off(rest.len() - footer_len);
+                let file_hash = rest.split_off(rest.len() - checksum_len);
+                (rest, file_hash, footer)
+            }
+        };
+        if footer[..] != FOOTER_MAGIC[..footer_len] {
+            return Err(FormatError::MissingFooter);
+        }
+        if header.checksummed {
+            hasher.update(&data);
+            if hasher.finalize()[..] != file_hash[..] {
+                return Err(FormatError::ChecksumMismatch);
+            }
+        }
+        header.into_image(data)
+    }
+}
+
+/// Header fields of a serialized image, everything before the pixel data.
+struct Header {
+    color_type: ColorType,
+    width: u32,
+    height: u32,
+    compression: CompressionType,
+    tiles: Option<TileIndex>,
+    chunks: Option<ChunkIndex>,
+    /// Width, height and stored length of each pyramid level.
+    levels: Vec<(u32, u32, u32)>,
+    checksummed: bool,
+    footer: bool,
+    /// Length of the metadata block, if it was written with reserved space.
+    metadata_capacity: Option<u32>,
+    metadata: ImageMetadata,
+    icc_profile: Option<Vec<u8>>,
+    lossy_quality: u8,
+    /// Pixel data length stored in the header, for layouts that imply none.
+    stored_data_len: Option<usize>,
+}
+
+impl Header {
+    /// Reads and validates a header, leaving `r` at the start of the pixel data.
+    fn read<R: Read>(r: &mut R) -> Result<Self, FormatError> {
+        if read_array::<4, _>(r)? != MAGIC_NUMBER {
             return Err(FormatError::InvalidHeader);
         }
         
-        let mut pos = MAGIC_NUMBER.len();
-        let file_version = bytes[pos];
-        if file_version != VERSION {
+        let file_version = read_u8(r)?;
+        if !(MIN_VERSION..=VERSION).contains(&file_version) {
             return Err(FormatError::UnsupportedVersion(file_version));
         }
         
-        pos += 1;
-        let color_type = ColorType::try_from(bytes[pos])?;
-        
-        pos += 1;
-        let width = u32::from_le_bytes(bytes[pos..pos + 4].try_into().unwrap());
-        pos += 4;
-        let height = u32::from_le_bytes(bytes[pos..pos + 4].try_into().unwrap());
-        pos += 4;
+        let color_type = ColorType::try_from(read_u8(r)?)?;
+        let width = read_u32_le(r)?;
+        let height = read_u32_le(r)?;
+        CustomImage::validate_size(width, height, color_type)?;
         
-        let compression = CompressionType::try_from(bytes[pos])?;
-        pos += 1;
+        let compression = CompressionType::try_from(read_u8(r)?)?;
+        let flags = if file_version >= 3 { read_u8(r)? } else { 0 };
+        let lossy_quality = if flags & FLAG_LOSSY_QUALITY != 0 { read_u8(r)? } else { DEFAULT_LOSSY_QUALITY };
+        check_lossy_quality(lossy_quality)?;
+        let indexed = flags & (FLAG_TILED | FLAG_CHUNKED) != 0;
+        let stored_data_len = if file_version >= 4 && stores_data_len(compression, indexed) {
+            let len = read_u64_le(r)?;
+            let limit = max_stored_len(width, height, color_type);
+            if len > limit as u64 {
+                return Err(FormatError::CompressionError(format!(
+                    "Pixel data length {} exceeds the {} bytes RLE can take",
+                    len, limit
+                )));
+            }
+            Some(len as usize)
+        } else {
+            None
+        };
+        let tiles = if flags & FLAG_TILED != 0 {
+            Some(read_tile_index(r, width, height, compression)?)
+        } else {
+            None
+        };
+        let chunks = if flags & FLAG_CHUNKED != 0 {
+            if tiles.is_some() {
+                return Err(FormatError::InvalidChunkIndex("tiled images cannot be chunked".to_string()));
+            }
+            Some(read_chunk_index(r, width, height, color_type, compression)?)
+        } else {
+            None
+        };
+        let levels = if flags & FLAG_PYRAMID != 0 {
+            read_pyramid_index(r, width, height)?
+        } else {
+            Vec::new()
+        };
         
         // Read metadata.
-        if pos + 4 > bytes.len() - 32 {
-            return Err(FormatError::DataTooShort);
-        }
-        let metadata_len = u32::from_le_bytes(bytes[pos..pos + 4].try_into().unwrap()) as usize;
-        pos += 4;
-        if pos + metadata_len > bytes.len() - 32 {
-            return Err(FormatError::DataTooShort);
+        let metadata_len = read_u32_le(r)? as usize;
+        if metadata_len > MAX_METADATA_LEN {
+            return Err(FormatError::MetadataError(format!(
+                "Metadata size {} exceeds limit of {} bytes",
+                metadata_len, MAX_METADATA_LEN
+            )));
         }
-        let metadata_json = std::str::from_utf8(&bytes[pos..pos + metadata_len])
+        let metadata_bytes = read_vec(r, metadata_len)?;
+        let metadata_json = std::str::from_utf8(&metadata_bytes)
             .map_err(|e| FormatError::MetadataError(e.to_string()))?;
         let metadata: ImageMetadata = serde_json::from_str(metadata_json)
             .map_err(|e| FormatError::MetadataError(e.to_string()))?;
-        pos += metadata_len;
-        
-        // Read image data.
-        let data = bytes[pos..bytes.len() - 32].to_vec();
-        
-        Ok(CustomImage {
+        let icc_profile = if flags & FLAG_ICC_PROFILE != 0 {
+            let len = read_u32_le(r)? as usize;
+            check_icc_profile_len(len)?;
+            Some(read_vec(r, len)?)
+        } else {
+            None
+        };
+
+        let checksummed = flags & FLAG_NO_CHECKSUM == 0;
+        let footer = flags & FLAG_FOOTER != 0;
+        let metadata_capacity = (flags & FLAG_RESERVED_METADATA != 0).then_some(metadata_len as u32);
+        Ok(Header {
+            color_type,
             width,
             height,
-            color_type,
-            data,
-            metadata,
             compression,
+            tiles,
+            chunks,
+            levels,
+            checksummed,
+            footer,
+            metadata_capacity,
+            metadata,
+            icc_profile,
+            lossy_quality,
+            stored_data_len,
+        })
+    }
+
+    /// Returns the length of the pixel data plus any pyramid levels given by
+    /// the header, if any.
+    ///
+    /// Tiled and chunked images sum their indexes, uncompressed, delta and
+    /// lossy data follow from the dimensions, and RLE data stores its length
+    /// from version 4 on. Only RLE data in older files has no known length.
+    fn data_len(&self) -> Option<usize> {
+        let lengths = self.tiles.as_ref().map(|index| &index.lengths)
+            .or(self.chunks.as_ref().map(|index| &index.lengths));
+        let pixel_len = self.width as usize * self.height as usize * self.color_type.channels() as usize;
+        let image_len = match (lengths, self.compression) {
+            (Some(lengths), _) => lengths.iter().map(|&l| l as usize).sum(),
+            (None, CompressionType::None | CompressionType::Delta) => pixel_len,
+            (None, CompressionType::Lossy) => lossy_len(self.width, self.height, self.color_type, self.lossy_quality),
+            (None, CompressionType::RLE | CompressionType::RleVarint) => self.stored_data_len?,
+        };
+        Some(image_len + self.levels_len())
+    }
+
+    /// Returns the total stored length of the pyramid levels.
+    fn levels_len(&self) -> usize {
+        self.levels.iter().map(|&(_, _, len)| len as usize).sum()
+    }
+
+    /// Combines the header with its pixel data (followed by any pyramid
+    /// levels), checking tiled and chunked data lengths.
+    fn into_image(self, mut data: Vec<u8>) -> Result<CustomImage, FormatError> {
+        let levels_start = data.len().checked_sub(self.levels_len()).ok_or_else(|| {
+            FormatError::InvalidPyramid(format!(
+                "levels need {} bytes but only {} remain",
+                self.levels_len(),
+                data.len()
+            ))
+        })?;
+        let mut level_data = data.split_off(levels_start);
+        if let Some(expected) = self.stored_data_len {
+            if data.len() != expected {
+                return Err(FormatError::DataLengthMismatch { expected, actual: data.len() });
+            }
+        }
+        let mut levels = Vec::with_capacity(self.levels.len());
+        for &(width, height, len) in &self.levels {
+            let rest = level_data.split_off(len as usize);
+            levels.push(PyramidLevel { width, height, data: level_data });
+            level_data = rest;
+        }
+
+        if let Some(index) = &self.tiles {
+            let tiles_len: u64 = index.lengths.iter().map(|&l| l as u64).sum();
+            if tiles_len != data.len() as u64 {
+                return Err(FormatError::InvalidTileIndex(format!(
+                    "tiles cover {} bytes but pixel data is {} bytes",
+                    tiles_len,
+                    data.len()
+                )));
+            }
+        }
+        if let Some(index) = &self.chunks {
+            let chunks_len: u64 = index.lengths.iter().map(|&l| l as u64).sum();
+            if chunks_len != data.len() as u64 {
+                return Err(FormatError::InvalidChunkIndex(format!(
+                    "chunks cover {} bytes but pixel data is {} bytes",
+                    chunks_len,
+                    data.len()
+                )));
+            }
+        }
+        match &self.tiles {
+            // Lossy tiles are sized by their own dimensions, so each tile is
+            // checked on its own.
+            Some(index) => {
+                let tiles = tile_regions(self.width, self.height, index.tile_size);
+                for (tile, range) in tiles.into_iter().zip(length_ranges(&index.lengths)) {
+                    let (width, height) = (tile.width, tile.height);
+                    check_decoded_len(&data[range], width, height, self.color_type, self.compression, self.lossy_quality)?;
+                }
+            }
+            None => {
+                check_decoded_len(&data, self.width, self.height, self.color_type, self.compression, self.lossy_quality)?
+            }
+        }
+        for level in &levels {
+            let (width, height) = (level.width, level.height);
+            check_decoded_len(&level.data, width, height, self.color_type, self.compression, self.lossy_quality)?;
+        }
+        
+        Ok(CustomImage {
+            width: self.width,
+            height: self.height,
+            color_type: self.color_type,
+            data,
+            metadata: self.metadata,
+            compression: self.compression,
+            tiles: self.tiles,
+            chunks: self.chunks,
+            checksummed: self.checksummed,
+            footer: self.footer,
+            metadata_capacity: self.metadata_capacity,
+            levels,
+            icc_profile: self.icc_profile,
+            lossy_quality: self.lossy_quality,
+        })
+    }
+}
+
+/// Returns how many bytes `data` decodes to under a lossless codec, without
+/// decoding it, or `None` for lossy data, whose decoded size does not depend
+/// on its length.
+///
+/// Tile and chunk streams are concatenated, so their total decodes to the
+/// sum of their parts.
+fn decoded_len(data: &[u8], compression: CompressionType) -> Result<Option<usize>, FormatError> {
+    match compression {
+        CompressionType::None | CompressionType::Delta => Ok(Some(data.len())),
+        CompressionType::RLE => {
+            let pairs = data.chunks_exact(2);
+            if !pairs.remainder().is_empty() {
+                return Err(FormatError::CompressionError("Invalid RLE data".to_string()));
+            }
+            Ok(Some(pairs.map(|pair| pair[0] as usize).sum()))
+        }
+        CompressionType::RleVarint => {
+            let invalid = || FormatError::CompressionError("Invalid varint RLE data".to_string());
+            let mut total = 0usize;
+            let mut bytes = data.iter();
+            while let Some(&first) = bytes.next() {
+                let (mut count, mut shift, mut byte) = (0usize, 0, first);
+                loop {
+                    if shift >= usize::BITS {
+                        return Err(invalid());
+                    }
+                    count |= ((byte & 0x7F) as usize) << shift;
+                    shift += 7;
+                    if byte & 0x80 == 0 {
+                        break;
+                    }
+                    byte = *bytes.next().ok_or_else(invalid)?;
+                }
+                bytes.next().ok_or_else(invalid)?;
+                total = total.checked_add(count).ok_or_else(invalid)?;
+            }
+            Ok(Some(total))
+        }
+        CompressionType::Lossy => Ok(None),
+    }
+}
+
+/// Rejects pixel data that does not decode to exactly `width * height`
+/// pixels of `color_type`, so truncated files fail to open instead of handing
+/// a short buffer to the viewer or converter.
+///
+/// Lossy data is checked against the length the encoder produces at
+/// `lossy_quality`, so the mismatch reports stored rather than decoded sizes.
+fn check_decoded_len(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    color_type: ColorType,
+    compression: CompressionType,
+    lossy_quality: u8,
+) -> Result<(), FormatError> {
+    let (expected, actual) = match decoded_len(data, compression)? {
+        Some(actual) => (width as usize * height as usize * color_type.channels() as usize, actual),
+        None => (lossy_len(width, height, color_type, lossy_quality), data.len()),
+    };
+    if actual != expected {
+        return Err(FormatError::DataLengthMismatch { expected, actual });
+    }
+    Ok(())
+}
+
+/// Returns the side length of the pixel blocks averaged by lossy
+/// compression at `quality` (clamped to 1-100): 100 divided by the quality,
+/// rounded up. Quality 100 keeps every pixel, the default of 50 averages 2x2
+/// blocks, and lower qualities average ever larger blocks.
+///
+/// Grayscale images average runs of `block_size²` consecutive samples
+/// instead of square blocks.
+///
+/// ```rust
+/// use nor_image::format::{lossy_block_size, DEFAULT_LOSSY_QUALITY};
+///
+/// assert_eq!(lossy_block_size(100), 1);
+/// assert_eq!(lossy_block_size(DEFAULT_LOSSY_QUALITY), 2);
+/// assert_eq!(lossy_block_size(25), 4);
+/// assert_eq!(lossy_block_size(0), 100);
+/// ```
+pub fn lossy_block_size(quality: u8) -> usize {
+    100usize.div_ceil(quality.clamp(1, 100) as usize)
+}
+
+/// Returns how many bytes lossy compression at `quality` stores for a
+/// `width`x`height` image of `color_type`.
+fn lossy_len(width: u32, height: u32, color_type: ColorType, quality: u8) -> usize {
+    let block_size = lossy_block_size(quality);
+    match color_type {
+        ColorType::Gray => {
+            let (len, run) = (width as usize * height as usize, block_size * block_size);
+            len / run + len % run
+        }
+        ColorType::Rgb | ColorType::GrayAlpha => {
+            let blocks = (width as usize).div_ceil(block_size) * (height as usize).div_ceil(block_size);
+            blocks * color_type.channels() as usize
+        }
+    }
+}
+
+/// Returns whether an image stores its pixel data length in the header:
+/// only RLE data that is neither tiled nor chunked, whose length no other
+/// field implies.
+fn stores_data_len(compression: CompressionType, indexed: bool) -> bool {
+    !indexed && matches!(compression, CompressionType::RLE | CompressionType::RleVarint)
+}
+
+/// Returns the longest RLE data the encoders produce for a `width`x`height`
+/// image of `color_type`: two bytes per decoded byte, when no byte repeats.
+fn max_stored_len(width: u32, height: u32, color_type: ColorType) -> usize {
+    2 * width as usize * height as usize * color_type.channels() as usize
+}
+
+/// Rejects lossy qualities outside 1-100, which the encoder never produces.
+fn check_lossy_quality(quality: u8) -> Result<(), FormatError> {
+    if !(1..=100).contains(&quality) {
+        return Err(FormatError::CompressionError(format!(
+            "Lossy quality {} is outside 1-100",
+            quality
+        )));
+    }
+    Ok(())
+}
+
+/// Rejects ICC profiles larger than `MAX_ICC_PROFILE_LEN`.
+fn check_icc_profile_len(len: usize) -> Result<(), FormatError> {
+    if len > MAX_ICC_PROFILE_LEN {
+        return Err(FormatError::MetadataError(format!(
+            "ICC profile size {} exceeds limit of {} bytes",
+            len, MAX_ICC_PROFILE_LEN
+        )));
+    }
+    Ok(())
+}
+
+/// Serializes metadata to JSON, padded with spaces up to `capacity` when
+/// one is reserved. Metadata larger than the capacity is returned unpadded.
+fn serialize_metadata(metadata: &ImageMetadata, capacity: Option<u32>) -> Result<Vec<u8>, FormatError> {
+    let mut metadata_json = serde_json::to_string(metadata)
+        .unwrap_or_else(|_| "{}".to_string())
+        .into_bytes();
+
+    let limit = metadata_json.len().max(capacity.unwrap_or(0) as usize);
+    if limit > MAX_METADATA_LEN {
+        return Err(FormatError::MetadataError(format!(
+            "Metadata size {} exceeds limit of {} bytes",
+            limit,
+            MAX_METADATA_LEN
+        )));
+    }
+    metadata_json.resize(limit, b' ');
+    Ok(metadata_json)
+}
+
+/// For each of `len` pixels along one axis of a lossy image, returns the two
+/// neighboring blocks to interpolate between and the weight of the second.
+///
+/// Each block's value sits at the center of the pixels it covers, so the
+/// partial block at the end of the axis is centered on what it covers. Pixels
+/// before the first center or past the last one take that block unchanged.
+fn lossy_block_weights(len: usize, block_size: usize) -> Vec<(usize, usize, f32)> {
+    let blocks = len.div_ceil(block_size);
+    let center = |block: usize| {
+        let start = block * block_size;
+        start as f32 + ((len - start).min(block_size) - 1) as f32 / 2.0
+    };
+    (0..len)
+        .map(|p| {
+            let block = p / block_size;
+            let first = if (p as f32) < center(block) { block.saturating_sub(1) } else { block };
+            if first + 1 >= blocks || (p as f32) < center(first) {
+                return (first, first, 0.0);
+            }
+            let t = (p as f32 - center(first)) / (center(first + 1) - center(first));
+            (first, first + 1, t)
         })
+        .collect()
+}
+
+/// Scales the pixels of an uncompressed image with `imageops::resize`.
+fn resize_pixels<P: Pixel<Subpixel = u8> + 'static>(
+    image: CustomImage,
+    width: u32,
+    height: u32,
+    filter: FilterType,
+) -> Result<Vec<u8>, FormatError> {
+    let expected = image.uncompressed_len();
+    let actual = image.data.len();
+    let buffer = ImageBuffer::<P, _>::from_raw(image.width, image.height, image.data)
+        .ok_or(FormatError::DataLengthMismatch { expected, actual })?;
+    Ok(imageops::resize(&buffer, width, height, filter).into_raw())
+}
+
+/// Length of the header fields every version has, up to and including the
+/// metadata length.
+const FIXED_HEADER_LEN: usize = MAGIC_NUMBER.len() + 1 + 1 + 4 + 4 + 1 + 4;
+
+/// Bytes `is_nor_reader` reads: the longest `min_file_len`, with checksum
+/// and footer.
+pub const NOR_SNIFF_LEN: usize = FIXED_HEADER_LEN + 32 + FOOTER_MAGIC.len();
+
+/// Returns the shortest length a serialized image with the flags in `bytes`
+/// can have: the fixed header fields, checksum and footer.
+fn min_file_len(bytes: &[u8]) -> usize {
+    let checksum_len = if has_flag(bytes, FLAG_NO_CHECKSUM) { 0 } else { 32 };
+    let footer_len = if has_flag(bytes, FLAG_FOOTER) { FOOTER_MAGIC.len() } else { 0 };
+    FIXED_HEADER_LEN + checksum_len + footer_len
+}
+
+/// Returns whether a serialized image has the given header flag set.
+///
+/// Version 2 files have no flags byte, so no flag is ever set.
+fn has_flag(bytes: &[u8], flag: u8) -> bool {
+    bytes.get(MAGIC_NUMBER.len()).is_some_and(|&version| version >= 3)
+        && bytes.get(FLAGS_OFFSET).is_some_and(|&flags| flags & flag != 0)
+}
+
+/// Returns `len` bytes of `bytes` starting at `pos`, or `DataTooShort`.
+fn read_slice(bytes: &[u8], pos: usize, len: usize) -> Result<&[u8], FormatError> {
+    pos.checked_add(len)
+        .and_then(|end| bytes.get(pos..end))
+        .ok_or(FormatError::DataTooShort)
+}
+
+/// Maps a short read to `DataTooShort` and any other failure to `IoError`.
+fn read_error(e: io::Error) -> FormatError {
+    if e.kind() == io::ErrorKind::UnexpectedEof {
+        FormatError::DataTooShort
+    } else {
+        FormatError::IoError(e)
+    }
+}
+
+/// Reads exactly `N` bytes.
+fn read_array<const N: usize, R: Read>(r: &mut R) -> Result<[u8; N], FormatError> {
+    let mut buf = [0u8; N];
+    r.read_exact(&mut buf).map_err(read_error)?;
+    Ok(buf)
+}
+
+/// Reads a single byte.
+fn read_u8<R: Read>(r: &mut R) -> Result<u8, FormatError> {
+    Ok(read_array::<1, _>(r)?[0])
+}
+
+/// Reads a little-endian `u32`.
+fn read_u32_le<R: Read>(r: &mut R) -> Result<u32, FormatError> {
+    Ok(u32::from_le_bytes(read_array(r)?))
+}
+
+/// Reads a little-endian `u64`.
+fn read_u64_le<R: Read>(r: &mut R) -> Result<u64, FormatError> {
+    Ok(u64::from_le_bytes(read_array(r)?))
+}
+
+/// Reads exactly `len` bytes.
+///
+/// The buffer grows as data arrives, so a crafted length cannot force a huge
+/// allocation up front.
+fn read_vec<R: Read>(r: &mut R, len: usize) -> Result<Vec<u8>, FormatError> {
+    let mut buf = Vec::new();
+    r.take(len as u64).read_to_end(&mut buf)?;
+    if buf.len() != len {
+        return Err(FormatError::DataTooShort);
+    }
+    Ok(buf)
+}
+
+/// Reads and validates the tile index of a tiled image.
+fn read_tile_index<R: Read>(
+    r: &mut R,
+    width: u32,
+    height: u32,
+    compression: CompressionType,
+) -> Result<TileIndex, FormatError> {
+    if compression == CompressionType::None {
+        return Err(FormatError::InvalidTileIndex("tiled image is not compressed".to_string()));
+    }
+    let tile_size = read_u32_le(r)?;
+    if tile_size == 0 || tile_size > max_dimension() {
+        return Err(FormatError::InvalidTileIndex(format!("tile size {} is out of range", tile_size)));
+    }
+    let count = read_u32_le(r)? as usize;
+    let expected = (width.div_ceil(tile_size) as usize) * (height.div_ceil(tile_size) as usize);
+    if count != expected {
+        return Err(FormatError::InvalidTileIndex(format!(
+            "expected {} tiles, found {}",
+            expected, count
+        )));
+    }
+    let lengths = read_vec(r, count * 4)?
+        .chunks_exact(4)
+        .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+        .collect();
+    Ok(TileIndex { tile_size, lengths })
+}
+
+/// Reads and validates the chunk index of a chunked, run-length encoded image.
+fn read_chunk_index<R: Read>(
+    r: &mut R,
+    width: u32,
+    height: u32,
+    color_type: ColorType,
+    compression: CompressionType,
+) -> Result<ChunkIndex, FormatError> {
+    if !matches!(compression, CompressionType::RLE | CompressionType::RleVarint) {
+        return Err(FormatError::InvalidChunkIndex(format!(
+            "{:?} data cannot be chunked",
+            compression
+        )));
+    }
+    let chunk_size = read_u32_le(r)?;
+    if chunk_size == 0 {
+        return Err(FormatError::InvalidChunkIndex("chunk size must be positive".to_string()));
+    }
+    let count = read_u32_le(r)? as usize;
+    let total = width as u64 * height as u64 * color_type.channels() as u64;
+    let expected = total.div_ceil(chunk_size as u64) as usize;
+    if count != expected {
+        return Err(FormatError::InvalidChunkIndex(format!(
+            "expected {} chunks, found {}",
+            expected, count
+        )));
+    }
+    let lengths = read_vec(r, count * 4)?
+        .chunks_exact(4)
+        .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+        .collect();
+    Ok(ChunkIndex { chunk_size, lengths })
+}
+
+/// Reads a pyramid index, checking that each level is smaller than the one
+/// above it.
+fn read_pyramid_index<R: Read>(r: &mut R, width: u32, height: u32) -> Result<Vec<(u32, u32, u32)>, FormatError> {
+    let count = read_u32_le(r)?;
+    if count == 0 || count > MAX_PYRAMID_LEVELS {
+        return Err(FormatError::InvalidPyramid(format!(
+            "level count {} is not between 1 and {}",
+            count, MAX_PYRAMID_LEVELS
+        )));
+    }
+    let mut levels = Vec::with_capacity(count as usize);
+    let (mut above_width, mut above_height) = (width, height);
+    for _ in 0..count {
+        let level = (read_u32_le(r)?, read_u32_le(r)?, read_u32_le(r)?);
+        let (level_width, level_height, _) = level;
+        if level_width == 0 || level_height == 0 || level_width > above_width || level_height > above_height
+            || (level_width, level_height) == (above_width, above_height)
+        {
+            return Err(FormatError::InvalidPyramid(format!(
+                "level of {}x{} does not reduce {}x{}",
+                level_width, level_height, above_width, above_height
+            )));
+        }
+        (above_width, above_height) = (level_width, level_height);
+        levels.push(level);
+    }
+    Ok(levels)
+}
+
+/// Returns the region of every tile of an image, in row-major order.
+fn tile_regions(width: u32, height: u32, tile_size: u32) -> Vec<Region> {
+    (0..height)
+        .step_by(tile_size as usize)
+        .flat_map(|y| {
+            (0..width).step_by(tile_size as usize).map(move |x| {
+                Region::new(x, y, tile_size.min(width - x), tile_size.min(height - y))
+            })
+        })
+        .collect()
+}
+
+/// Converts per-tile or per-chunk compressed lengths into byte ranges of
+/// the pixel data.
+fn length_ranges(lengths: &[u32]) -> Vec<Range<usize>> {
+    let mut offset = 0;
+    lengths
+        .iter()
+        .map(|&len| {
+            let range = offset..offset + len as usize;
+            offset = range.end;
+            range
+        })
+        .collect()
+}
+
+/// Writer adapter that feeds every byte written through a SHA256 hasher, if any.
+struct HashingWriter<'a, W: Write> {
+    inner: &'a mut W,
+    hasher: Option<Sha256>,
+}
+
+impl<W: Write> HashingWriter<'_, W> {
+    fn write_all(&mut self, bytes: &[u8]) -> Result<(), FormatError> {
+        if let Some(hasher) = &mut self.hasher {
+            hasher.update(bytes);
+        }
+        self.inner.write_all(bytes)?;
+        Ok(())
+    }
+}
+
+/// Reader adapter that counts the bytes read through it.
+struct CountingReader<R: Read> {
+    inner: R,
+    count: u64,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count += n as u64;
+        Ok(n)
+    }
+}
+
+/// Reader adapter that feeds every byte read through a SHA256 hasher.
+struct HashingReader<R: Read> {
+    inner: R,
+    hasher: Sha256,
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processing::ParallelImageProcessor;
+
+    /// Xorshift generator with a fixed seed, so fuzz failures reproduce.
+    fn xorshift() -> impl FnMut() -> u64 {
+        let mut state = 0x2545_F491_4F6C_DD1Du64;
+        move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        }
+    }
+
+    /// Appends the SHA256 checksum of `body`.
+    fn with_checksum(body: &[u8]) -> Vec<u8> {
+        [body, &Sha256::digest(body)[..]].concat()
+    }
+
+    /// Valid files covering RLE chunks, tiles, lossy quality and metadata.
+    fn fuzz_files() -> Vec<Vec<u8>> {
+        let data: Vec<u8> = (0..8 * 8 * 3).map(|i| (i / 10) as u8).collect();
+        let metadata = ImageMetadata { title: Some("fuzz".to_string()), ..Default::default() };
+        let image = CustomImage::new(8, 8, ColorType::Rgb, data, Some(metadata), CompressionType::None).unwrap();
+        let mut rle = image.clone();
+        ParallelImageProcessor::compress(&mut rle, CompressionType::RLE).unwrap();
+        vec![
+            rle.to_bytes().unwrap(),
+            image.tiled(4, CompressionType::RleVarint).unwrap().to_bytes().unwrap(),
+            lossy(&image, 20).to_bytes().unwrap(),
+        ]
+    }
+
+    /// A 16x16 RGB image with varied pixels.
+    fn sample_rgb() -> CustomImage {
+        let data = (0..16 * 16 * 3).map(|i| (i % 97) as u8).collect();
+        CustomImage::new(16, 16, ColorType::Rgb, data, None, CompressionType::None).unwrap()
+    }
+
+    /// Returns `image` lossy-encoded at `quality`.
+    fn lossy(image: &CustomImage, quality: u8) -> CustomImage {
+        let mut image = CustomImage { lossy_quality: quality, ..image.clone() };
+        image.data = image.compress(CompressionType::Lossy).unwrap();
+        image.compression = CompressionType::Lossy;
+        image
+    }
+
+    #[test]
+    fn decompress_uses_the_given_lossy_quality() {
+        let image = sample_rgb();
+        let stored = lossy(&image, 20);
+        let decoded = CustomImage::decompress(&stored.data, 16, 16, ColorType::Rgb, CompressionType::Lossy, 20).unwrap();
+        assert_eq!(decoded.len(), image.data.len());
+        assert_eq!(decoded, CustomImage::decompress_lossy(&stored.data, 16, 16, ColorType::Rgb, 20).unwrap());
+    }
+
+    #[test]
+    fn lossy_quality_round_trips() {
+        let stored = lossy(&sample_rgb(), 20);
+        let decoded = CustomImage::from_bytes(&stored.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded.lossy_quality, 20);
+        assert_eq!(decoded.data, stored.data);
+    }
+
+    #[test]
+    fn out_of_range_lossy_quality_is_not_written() {
+        for quality in [0, 101, 255] {
+            let image = CustomImage { lossy_quality: quality, ..sample_rgb() };
+            assert!(matches!(image.to_bytes(), Err(FormatError::CompressionError(_))));
+        }
+    }
+
+    #[test]
+    fn out_of_range_lossy_quality_is_rejected_on_read() {
+        let stored = lossy(&sample_rgb(), 20);
+        let bytes = stored.to_bytes().unwrap();
+        // The quality byte follows the flags byte.
+        assert_eq!(bytes[FLAGS_OFFSET + 1], 20);
+        for quality in [0, 101, 255] {
+            let mut body = bytes[..bytes.len() - 32].to_vec();
+            body[FLAGS_OFFSET + 1] = quality;
+            let file = [&body[..], &Sha256::digest(&body)[..]].concat();
+            assert!(matches!(CustomImage::from_bytes(&file), Err(FormatError::CompressionError(_))));
+            assert!(matches!(CustomImage::from_reader(&file[..]), Err(FormatError::CompressionError(_))));
+        }
+    }
+
+    #[test]
+    fn random_bytes_are_rejected() {
+        let mut next = xorshift();
+        for _ in 0..5000 {
+            let len = (next() % 128) as usize;
+            let mut body: Vec<u8> = (0..len).map(|_| next() as u8).collect();
+            if next().is_multiple_of(2) {
+                body.splice(0..0, *b"CIMG\x04");
+            }
+            assert!(CustomImage::from_bytes(&body).is_err());
+            assert!(CustomImage::from_bytes(&with_checksum(&body)).is_err());
+            assert!(CustomImage::from_reader(&body[..]).is_err());
+        }
+    }
+
+    #[test]
+    fn truncated_files_are_rejected() {
+        for file in fuzz_files() {
+            let body = &file[..file.len() - 32];
+            for len in 0..file.len() {
+                assert!(CustomImage::from_bytes(&file[..len]).is_err());
+            }
+            for len in 0..body.len() {
+                assert!(CustomImage::from_bytes(&with_checksum(&body[..len])).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn mutated_files_do_not_panic() {
+        let mut next = xorshift();
+        for file in fuzz_files() {
+            let body = &file[..file.len() - 32];
+            for pos in 0..body.len() {
+                let mut mutated = body.to_vec();
+                mutated[pos] ^= (next() % 255 + 1) as u8;
+                // Some mutations (e.g. of a pixel) still describe a valid
+                // image; those must decode all the way without panicking.
+                if let Ok(mut decoded) = CustomImage::from_bytes(&with_checksum(&mutated)) {
+                    let _ = ParallelImageProcessor::decompress(&mut decoded);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn oversized_metadata_length_is_rejected_on_read() {
+        // Without a checksum, the length field can be patched directly. It
+        // follows the magic, version, color type, width, height, compression
+        // and flags.
+        let image = CustomImage::new(1, 1, ColorType::Gray, vec![0], None, CompressionType::None).unwrap();
+        let mut bytes = CustomImage { checksummed: false, ..image }.to_bytes().unwrap();
+        let offset = FLAGS_OFFSET + 1;
+        let claimed = (MAX_METADATA_LEN + 1) as u32;
+        bytes[offset..offset + 4].copy_from_slice(&claimed.to_le_bytes());
+        assert!(matches!(CustomImage::from_bytes(&bytes), Err(FormatError::MetadataError(_))));
+        assert!(matches!(CustomImage::from_reader(&bytes[..]), Err(FormatError::MetadataError(_))));
+
+        // The limit itself still passes the length check.
+        bytes[offset..offset + 4].copy_from_slice(&(MAX_METADATA_LEN as u32).to_le_bytes());
+        assert!(!matches!(
+            CustomImage::from_bytes(&bytes),
+            Err(FormatError::MetadataError(ref m)) if m.contains("exceeds")
+        ));
+    }
+
+    #[test]
+    fn oversized_metadata_is_not_written() {
+        let mut metadata = ImageMetadata::default();
+        metadata.custom_fields.insert("blob".to_string(), "x".repeat(MAX_METADATA_LEN));
+        let image = CustomImage::new(1, 1, ColorType::Gray, vec![0], Some(metadata), CompressionType::None).unwrap();
+        assert!(matches!(image.to_bytes(), Err(FormatError::MetadataError(_))));
+    }
+
+    #[test]
+    fn varint_rle_beats_8_bit_rle_on_long_runs() {
+        // A solid 1000x1000 grayscale image is a single run.
+        let solid = vec![200u8; 1000 * 1000];
+        assert_eq!(CustomImage::compress_rle(&solid).len(), 1_000_000usize.div_ceil(255) * 2);
+        assert_eq!(CustomImage::compress_rle_varint(&solid).len(), 3 + 1);
+
+        // A vertical gradient has one 1000-byte run per row.
+        let vertical: Vec<u8> = (0..1000 * 1000).map(|i| (i / 1000 % 256) as u8).collect();
+        assert_eq!(CustomImage::compress_rle(&vertical).len(), 1000 * 4 * 2);
+        assert_eq!(CustomImage::compress_rle_varint(&vertical).len(), 1000 * (2 + 1));
+    }
+
+    #[test]
+    fn varint_rle_matches_8_bit_rle_without_runs() {
+        // A horizontal gradient changes every pixel, so both store 2 bytes each.
+        let horizontal: Vec<u8> = (0..1000 * 1000).map(|i| (i % 1000 % 256) as u8).collect();
+        assert_eq!(CustomImage::compress_rle(&horizontal).len(), horizontal.len() * 2);
+        assert_eq!(CustomImage::compress_rle_varint(&horizontal).len(), horizontal.len() * 2);
+    }
+
+    /// An 8x8 RGB image stored as plain, unchunked RLE.
+    fn sample_rle() -> CustomImage {
+        let data: Vec<u8> = (0..8 * 8 * 3).map(|i| (i / 10) as u8).collect();
+        let image = CustomImage::new(8, 8, ColorType::Rgb, data, None, CompressionType::None).unwrap();
+        CustomImage { data: CustomImage::compress_rle(&image.data), compression: CompressionType::RLE, ..image }
+    }
+
+    /// Rewrites a version 4 RLE file as version 3, without the data length.
+    fn as_version_3(file: &[u8]) -> Vec<u8> {
+        let mut body = file[..file.len() - 32].to_vec();
+        body[MAGIC_NUMBER.len()] = 3;
+        body.drain(FLAGS_OFFSET + 1..FLAGS_OFFSET + 9);
+        with_checksum(&body)
+    }
+
+    #[test]
+    fn from_reader_stops_after_an_rle_image() {
+        let image = sample_rle();
+        let mut stream = image.to_bytes().unwrap();
+        stream.extend(image.to_bytes().unwrap());
+        stream.extend_from_slice(b"next message");
+
+        let mut reader = &stream[..];
+        assert_eq!(CustomImage::from_reader(&mut reader).unwrap(), image);
+        assert_eq!(CustomImage::from_reader(&mut reader).unwrap(), image);
+        assert_eq!(reader, b"next message");
+    }
+
+    #[test]
+    fn stored_rle_length_must_match_the_data() {
+        let file = sample_rle().to_bytes().unwrap();
+        let mut body = file[..file.len() - 32].to_vec();
+        let stored = u64::from_le_bytes(body[FLAGS_OFFSET + 1..FLAGS_OFFSET + 9].try_into().unwrap());
+        body[FLAGS_OFFSET + 1..FLAGS_OFFSET + 9].copy_from_slice(&(stored - 2).to_le_bytes());
+        let file = with_checksum(&body);
+        assert!(matches!(
+            CustomImage::from_bytes(&file),
+            Err(FormatError::DataLengthMismatch { .. })
+        ));
+        assert!(CustomImage::from_reader(&file[..]).is_err());
+
+        body[FLAGS_OFFSET + 1..FLAGS_OFFSET + 9].copy_from_slice(&u64::MAX.to_le_bytes());
+        let file = with_checksum(&body);
+        assert!(matches!(CustomImage::from_bytes(&file), Err(FormatError::CompressionError(_))));
+        assert!(matches!(CustomImage::from_reader(&file[..]), Err(FormatError::CompressionError(_))));
+    }
+
+    #[test]
+    fn version_3_rle_is_read_to_the_end_of_the_stream() {
+        let image = sample_rle();
+        let file = as_version_3(&image.to_bytes().unwrap());
+        assert_eq!(CustomImage::from_bytes(&file).unwrap(), image);
+        assert_eq!(CustomImage::from_reader(&file[..]).unwrap(), image);
+    }
+
+    #[test]
+    fn version_3_rle_stream_is_bounded() {
+        let file = as_version_3(&sample_rle().to_bytes().unwrap());
+        // An endless stream after the header must not be read without limit.
+        let endless = file[..file.len() - 32].chain(io::repeat(0));
+        assert!(matches!(CustomImage::from_reader(endless), Err(FormatError::CompressionError(_))));
+    }
+
+    #[test]
+    fn capped_rle_stops_at_the_limit() {
+        let runs = [255, 7].repeat(1000);
+        assert_eq!(CustomImage::decompress_rle_capped(&runs[..4], 510).unwrap(), vec![7; 510]);
+        assert!(matches!(
+            CustomImage::decompress_rle_capped(&runs, 510),
+            Err(FormatError::DataLengthMismatch { expected: 510, actual: 765 })
+        ));
     }
 }
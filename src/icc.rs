@@ -0,0 +1,367 @@
+// Copyright 2025 Grish
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! ICC profile conversion to sRGB.
+//!
+//! Converts pixels tagged with an embedded ICC profile to sRGB, so images
+//! from mixed sources can be stored without a profile and still look alike.
+//!
+//! With the `lcms2` feature, Little CMS does the conversion (relative
+//! colorimetric intent) and every profile it accepts is supported. Without
+//! it, a built-in approximation handles matrix/TRC profiles, which covers
+//! typical camera and display profiles such as Adobe RGB, Display P3 and
+//! gray gamma profiles:
+//! - Each channel is linearized through the profile's tone curve (a gamma,
+//!   a sampled table or a parametric curve)
+//! - RGB is mapped through the profile's colorant matrix to the D50
+//!   connection space, then to linear sRGB with the Bradford-adapted sRGB
+//!   matrix; gray is taken as luminance
+//! - The result is sRGB encoded, clipping colors outside the sRGB gamut
+//!
+//! LUT-based profiles (`A2B0` and friends) are rejected by the approximation.
+//! Alpha is left unchanged.
+
+use std::error::Error as StdError;
+use std::fmt;
+
+use image::DynamicImage;
+
+/// Errors converting pixels with an ICC profile.
+#[derive(Debug)]
+pub enum IccError {
+    /// The profile is truncated or a tag it needs is malformed.
+    Malformed(&'static str),
+    /// The profile is valid but cannot be converted.
+    Unsupported(String),
+}
+
+impl fmt::Display for IccError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IccError::Malformed(msg) => write!(f, "Malformed ICC profile: {}", msg),
+            IccError::Unsupported(msg) => write!(f, "Unsupported ICC profile: {}", msg),
+        }
+    }
+}
+
+impl StdError for IccError {}
+
+/// Converts `img` from the color space described by `profile` to sRGB.
+///
+/// The result has 8 bits per channel; an RGB profile needs a
+/// color image and a gray profile a grayscale one.
+///
+/// ```rust
+/// use image::{DynamicImage, GrayImage, Luma};
+/// use nor_image::icc::to_srgb;
+///
+/// // A gray profile with a linear tone curve (gamma 1.0).
+/// let mut profile = vec![0u8; 128];
+/// profile[8] = 2; // version 2
+/// profile[12..16].copy_from_slice(b"mntr");
+/// profile[16..20].copy_from_slice(b"GRAY");
+/// profile[20..24].copy_from_slice(b"XYZ ");
+/// profile[36..40].copy_from_slice(b"acsp");
+/// profile.extend_from_slice(&1u32.to_be_bytes());
+/// profile.extend_from_slice(b"kTRC");
+/// profile.extend_from_slice(&144u32.to_be_bytes());
+/// profile.extend_from_slice(&14u32.to_be_bytes());
+/// profile.extend_from_slice(b"curv\0\0\0\0");
+/// profile.extend_from_slice(&1u32.to_be_bytes());
+/// profile.extend_from_slice(&0x0100u16.to_be_bytes());
+/// profile.extend_from_slice(&[0, 0]);
+/// let len = profile.len() as u32;
+/// profile[..4].copy_from_slice(&len.to_be_bytes());
+///
+/// // Linear mid gray is lighter once sRGB encoded; black and white stay put.
+/// let img = DynamicImage::ImageLuma8(GrayImage::from_fn(3, 1, |x, _| Luma([[0, 128, 255][x as usize]])));
+/// let srgb = to_srgb(&img, &profile)?.into_luma8();
+/// assert_eq!(srgb.as_raw()[0], 0);
+/// assert!((186..=190).contains(&srgb.as_raw()[1]));
+/// assert_eq!(srgb.as_raw()[2], 255);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// # Errors
+///
+/// Returns `Malformed` if the profile cannot be parsed, or `Unsupported` if
+/// it does not match the image or cannot be converted.
+pub fn to_srgb(img: &DynamicImage, profile: &[u8]) -> Result<DynamicImage, IccError> {
+    let space = profile.get(16..20).ok_or(IccError::Malformed("truncated header"))?;
+    let gray_image = !img.color().has_color();
+    let gray_profile = match space {
+        b"GRAY" => true,
+        b"RGB " => false,
+        _ => {
+            return Err(IccError::Unsupported(format!(
+                "color space {:?}",
+                String::from_utf8_lossy(space).trim_end()
+            )))
+        }
+    };
+    if gray_profile != gray_image {
+        return Err(IccError::Unsupported(format!(
+            "a {} profile on a {} image",
+            if gray_profile { "gray" } else { "RGB" },
+            if gray_image { "grayscale" } else { "color" }
+        )));
+    }
+    convert(img, profile)
+}
+
+/// Converts with Little CMS.
+#[cfg(feature = "lcms2")]
+fn convert(img: &DynamicImage, profile: &[u8]) -> Result<DynamicImage, IccError> {
+    use lcms2::{CIExyY, PixelFormat, Profile, ToneCurve};
+
+    let source = Profile::new_icc(profile).map_err(unsupported)?;
+    let srgb_gray = || {
+        // sRGB's tone curve as an ICC type 4 parametric curve, D65 white.
+        let curve = ToneCurve::new_parametric(4, &[2.4, 1.0 / 1.055, 0.055 / 1.055, 1.0 / 12.92, 0.04045])?;
+        Profile::new_gray(&CIExyY { x: 0.3127, y: 0.3290, Y: 1.0 }, &curve)
+    };
+    Ok(match img {
+        DynamicImage::ImageLuma8(_) | DynamicImage::ImageLuma16(_) => {
+            let mut gray = img.to_luma8();
+            transform::<1>(&source, &srgb_gray().map_err(unsupported)?, PixelFormat::GRAY_8, &mut gray)?;
+            DynamicImage::ImageLuma8(gray)
+        }
+        DynamicImage::ImageLumaA8(_) | DynamicImage::ImageLumaA16(_) => {
+            let mut gray = img.to_luma_alpha8();
+            transform::<2>(&source, &srgb_gray().map_err(unsupported)?, PixelFormat::GRAYA_8, &mut gray)?;
+            DynamicImage::ImageLumaA8(gray)
+        }
+        img if img.color().has_alpha() => {
+            let mut rgba = img.to_rgba8();
+            transform::<4>(&source, &Profile::new_srgb(), PixelFormat::RGBA_8, &mut rgba)?;
+            DynamicImage::ImageRgba8(rgba)
+        }
+        img => {
+            let mut rgb = img.to_rgb8();
+            transform::<3>(&source, &Profile::new_srgb(), PixelFormat::RGB_8, &mut rgb)?;
+            DynamicImage::ImageRgb8(rgb)
+        }
+    })
+}
+
+#[cfg(feature = "lcms2")]
+fn unsupported(e: lcms2::Error) -> IccError {
+    IccError::Unsupported(e.to_string())
+}
+
+/// Transforms interleaved `N`-channel 8-bit samples in place, relative
+/// colorimetric.
+#[cfg(feature = "lcms2")]
+fn transform<const N: usize>(
+    source: &lcms2::Profile,
+    destination: &lcms2::Profile,
+    format: lcms2::PixelFormat,
+    samples: &mut [u8],
+) -> Result<(), IccError> {
+    let transform = lcms2::Transform::<[u8; N], [u8; N]>::new(
+        source,
+        format,
+        destination,
+        format,
+        lcms2::Intent::RelativeColorimetric,
+    )
+    .map_err(unsupported)?;
+    let mut pixels: Vec<[u8; N]> = samples.chunks_exact(N).map(|p| std::array::from_fn(|i| p[i])).collect();
+    transform.transform_in_place(&mut pixels);
+    samples.copy_from_slice(pixels.as_flattened());
+    Ok(())
+}
+
+/// Converts with the built-in matrix/TRC approximation.
+#[cfg(not(feature = "lcms2"))]
+fn convert(img: &DynamicImage, profile: &[u8]) -> Result<DynamicImage, IccError> {
+    let tags = Tags::parse(profile)?;
+    let encode = srgb_encode_lut();
+    let encode = |linear: f32| encode[(linear.clamp(0.0, 1.0) * (ENCODE_STEPS - 1) as f32).round() as usize];
+
+    if !img.color().has_color() {
+        let curve = tags.curve(b"kTRC")?;
+        let map = |v: &mut u8| *v = encode(curve[*v as usize]);
+        return Ok(if img.color().has_alpha() {
+            let mut gray = img.to_luma_alpha8();
+            gray.chunks_exact_mut(2).for_each(|p| map(&mut p[0]));
+            DynamicImage::ImageLumaA8(gray)
+        } else {
+            let mut gray = img.to_luma8();
+            gray.iter_mut().for_each(map);
+            DynamicImage::ImageLuma8(gray)
+        });
+    }
+
+    let curves = [tags.curve(b"rTRC")?, tags.curve(b"gTRC")?, tags.curve(b"bTRC")?];
+    let colorants = [tags.xyz(b"rXYZ")?, tags.xyz(b"gXYZ")?, tags.xyz(b"bXYZ")?];
+    // Linear profile RGB -> D50 XYZ -> linear sRGB, as one matrix.
+    let matrix: [[f32; 3]; 3] = std::array::from_fn(|row| {
+        std::array::from_fn(|col| (0..3).map(|k| SRGB_FROM_D50_XYZ[row][k] * colorants[col][k]).sum())
+    });
+    let map = |p: &mut [u8]| {
+        let linear = [0, 1, 2].map(|c| curves[c][p[c] as usize]);
+        for (out, row) in p.iter_mut().zip(&matrix) {
+            *out = encode(row[0] * linear[0] + row[1] * linear[1] + row[2] * linear[2]);
+        }
+    };
+    Ok(if img.color().has_alpha() {
+        let mut rgba = img.to_rgba8();
+        rgba.chunks_exact_mut(4).for_each(map);
+        DynamicImage::ImageRgba8(rgba)
+    } else {
+        let mut rgb = img.to_rgb8();
+        rgb.chunks_exact_mut(3).for_each(map);
+        DynamicImage::ImageRgb8(rgb)
+    })
+}
+
+/// D50 XYZ to linear sRGB, Bradford-adapted from sRGB's D65 white.
+#[cfg(not(feature = "lcms2"))]
+const SRGB_FROM_D50_XYZ: [[f32; 3]; 3] = [
+    [3.133_856, -1.616_867, -0.490_615],
+    [-0.978_768, 1.916_142, 0.033_454],
+    [0.071_945, -0.228_991, 1.405_243],
+];
+
+/// Number of entries in the linear to sRGB encoding table.
+#[cfg(not(feature = "lcms2"))]
+const ENCODE_STEPS: usize = 4096;
+
+/// Builds a table of sRGB encoded values for evenly spaced linear values.
+#[cfg(not(feature = "lcms2"))]
+fn srgb_encode_lut() -> Vec<u8> {
+    (0..ENCODE_STEPS)
+        .map(|i| {
+            let linear = i as f32 / (ENCODE_STEPS - 1) as f32;
+            let encoded = if linear <= 0.003_130_8 { linear * 12.92 } else { 1.055 * linear.powf(1.0 / 2.4) - 0.055 };
+            (encoded * 255.0).round() as u8
+        })
+        .collect()
+}
+
+/// The tag table of an ICC profile.
+#[cfg(not(feature = "lcms2"))]
+struct Tags<'a> {
+    profile: &'a [u8],
+    /// Signature, offset and size of each tag.
+    entries: Vec<([u8; 4], usize, usize)>,
+}
+
+#[cfg(not(feature = "lcms2"))]
+impl<'a> Tags<'a> {
+    fn parse(profile: &'a [u8]) -> Result<Self, IccError> {
+        let count = read_u32(profile, 128).ok_or(IccError::Malformed("truncated tag table"))? as usize;
+        let entries = (0..count)
+            .map(|i| {
+                let at = 132 + i * 12;
+                let signature = profile.get(at..at + 4).ok_or(IccError::Malformed("truncated tag table"))?;
+                let offset = read_u32(profile, at + 4).ok_or(IccError::Malformed("truncated tag table"))?;
+                let size = read_u32(profile, at + 8).ok_or(IccError::Malformed("truncated tag table"))?;
+                Ok((signature.try_into().unwrap_or_default(), offset as usize, size as usize))
+            })
+            .collect::<Result<_, IccError>>()?;
+        let tags = Tags { profile, entries };
+        if tags.get(b"A2B0").is_some() {
+            return Err(IccError::Unsupported("LUT-based profiles need the lcms2 feature".to_string()));
+        }
+        Ok(tags)
+    }
+
+    /// Returns the data of a tag, if present and within the profile.
+    fn get(&self, signature: &[u8; 4]) -> Option<&'a [u8]> {
+        let &(_, offset, size) = self.entries.iter().find(|(s, _, _)| s == signature)?;
+        self.profile.get(offset..offset.checked_add(size)?)
+    }
+
+    /// Reads an `XYZ ` tag.
+    fn xyz(&self, signature: &[u8; 4]) -> Result<[f32; 3], IccError> {
+        let data = self.get(signature).ok_or(IccError::Unsupported("missing colorant tag".to_string()))?;
+        if !data.starts_with(b"XYZ ") {
+            return Err(IccError::Malformed("colorant is not an XYZ tag"));
+        }
+        let value = |at| read_s15_fixed16(data, at).ok_or(IccError::Malformed("truncated XYZ tag"));
+        Ok([value(8)?, value(12)?, value(16)?])
+    }
+
+    /// Reads a `curv` or `para` tone curve as a table of the linear value of
+    /// each 8-bit sample.
+    fn curve(&self, signature: &[u8; 4]) -> Result<Vec<f32>, IccError> {
+        let data = self.get(signature).ok_or(IccError::Unsupported("missing tone curve tag".to_string()))?;
+        let truncated = IccError::Malformed("truncated tone curve");
+        let curve: Box<dyn Fn(f32) -> f32> = match data.get(..4) {
+            Some(b"curv") => {
+                let count = read_u32(data, 8).ok_or(truncated)? as usize;
+                let table = (0..count)
+                    .map(|i| read_u16(data, 12 + i * 2).map(|v| v as f32 / 65535.0))
+                    .collect::<Option<Vec<f32>>>()
+                    .ok_or(IccError::Malformed("truncated tone curve"))?;
+                match table.len() {
+                    0 => Box::new(|x| x),
+                    1 => {
+                        let gamma = table[0] * 65535.0 / 256.0;
+                        Box::new(move |x: f32| x.powf(gamma))
+                    }
+                    n => Box::new(move |x: f32| {
+                        let position = x * (n - 1) as f32;
+                        let i = (position as usize).min(n - 2);
+                        table[i] + (table[i + 1] - table[i]) * (position - i as f32)
+                    }),
+                }
+            }
+            Some(b"para") => {
+                let kind = read_u16(data, 8).ok_or(truncated)?;
+                let count = match kind {
+                    0 => 1,
+                    1 => 3,
+                    2 => 4,
+                    3 => 5,
+                    4 => 7,
+                    _ => return Err(IccError::Unsupported(format!("parametric curve type {}", kind))),
+                };
+                let mut p = [0.0f32; 7];
+                for (i, value) in p.iter_mut().take(count).enumerate() {
+                    *value = read_s15_fixed16(data, 12 + i * 4).ok_or(IccError::Malformed("truncated tone curve"))?;
+                }
+                let [g, a, b, c, d, e, f] = p;
+                let power = move |x: f32| (a * x + b).max(0.0).powf(g);
+                match kind {
+                    0 => Box::new(move |x: f32| x.powf(g)),
+                    1 => Box::new(move |x| if a * x + b >= 0.0 { power(x) } else { 0.0 }),
+                    2 => Box::new(move |x| if a * x + b >= 0.0 { power(x) + c } else { c }),
+                    3 => Box::new(move |x| if x >= d { power(x) } else { c * x }),
+                    _ => Box::new(move |x| if x >= d { power(x) + e } else { c * x + f }),
+                }
+            }
+            _ => return Err(IccError::Unsupported("tone curve is not a curv or para tag".to_string())),
+        };
+        Ok((0..=255).map(|v| curve(v as f32 / 255.0).clamp(0.0, 1.0)).collect())
+    }
+}
+
+#[cfg(not(feature = "lcms2"))]
+fn read_u16(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+#[cfg(not(feature = "lcms2"))]
+fn read_u32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+/// Reads an ICC `s15Fixed16Number`.
+#[cfg(not(feature = "lcms2"))]
+fn read_s15_fixed16(data: &[u8], at: usize) -> Option<f32> {
+    Some(i32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?) as f32 / 65536.0)
+}
@@ -1,4 +1,15 @@
+pub mod adjust;
+pub mod atomic;
+pub mod contact;
 pub mod converter;
+pub mod denoise;
 pub mod format;
+pub mod icc;
+pub mod metrics;
+pub mod preview;
+#[cfg(feature = "viewer")]
 pub mod viewer;
-pub mod processing; 
\ No newline at end of file
+pub mod processing;
+pub mod quantize;
+pub mod watermark;
+pub mod white_balance;
\ No newline at end of file
@@ -31,31 +31,82 @@
 //!
 //!   • `nor-image view image.nor`
 //!
+//!   • `nor-image compare-view a.nor b.nor`
+//!
 //!   • `nor-image info image.nor`
 //!
 //!   • `nor-image clear-cache`
 //!
-//! *Tip: Launching `nor-image` without any arguments will start interactive mode.*
+//! *Tip: Launching `nor-image` without any arguments will start interactive mode
+//! (in builds with the `interactive` feature, the default).*
+//!
+//! **Exit Codes:**
+//!
+//! | Code | Meaning                                              |
+//! |------|------------------------------------------------------|
+//! | 0    | Success                                              |
+//! | 1    | Any other failure                                    |
+//! | 2    | Invalid input: arguments, file extension, or config  |
+//! | 3    | Checksum verification failed                         |
+//! | 4    | I/O error                                            |
+//! | 5    | Unsupported or malformed image format                |
+//! | 6    | --strict: a questionable outcome was rejected        |
 
-use clap::{Parser, Subcommand, ValueEnum};
+use clap::{ArgAction, Parser, Subcommand, ValueEnum};
+use std::collections::HashSet;
 use std::error::Error;
+use std::fmt;
 use std::fs;
-use std::io::Write;
-use std::path::Path;
+use std::io::{self, BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
 
-use crate::converter::{png_to_custom, custom_to_png, ConversionConfig};
-use crate::format::{CustomImage, CompressionType};
-use crate::viewer::view_custom_image;
+use crate::adjust::ContrastCurve;
+use crate::atomic::write_atomic;
+use crate::converter::{png_to_custom, custom_to_file, custom_to_png, custom_to_raw, custom_to_webp, raw_to_custom, ExportFormat, load_image, ConversionConfig, ConversionError, GrayscaleMethod, PngCompression, Preset, ProgressCallback, RawFormat, StageTimings};
+use crate::format::{ColorType, CustomImage, CompressionType, FormatError, ImageMetadata, MetadataChange, DEFAULT_TILE_SIZE, FOOTER_MAGIC, NOR_SNIFF_LEN};
+#[cfg(not(feature = "cache"))]
+use crate::converter::read_custom;
+#[cfg(any(feature = "interactive", feature = "cache"))]
+use crate::processing::CachedImageLoader;
+use crate::processing::{ParallelImageProcessor, ProcessingError};
+use crate::preview::{render_preview, PreviewStyle};
+use crate::contact::contact_sheet;
+#[cfg(feature = "viewer")]
+use crate::viewer::{view_comparison, view_custom_image, view_png_image};
+#[cfg(not(feature = "viewer"))]
+use crate::no_viewer::{view_comparison, view_custom_image, view_png_image};
+use crate::watermark::{Watermark, WatermarkContent, WatermarkPosition};
 
+mod adjust;
+mod atomic;
+mod contact;
 mod converter;
+mod denoise;
 mod format;
+mod icc;
+mod metrics;
+mod preview;
 mod processing;
+mod quantize;
+#[cfg(feature = "viewer")]
 mod viewer;
+mod watermark;
+mod white_balance;
 
+#[cfg(feature = "interactive")]
 use colored::*;
+#[cfg(not(feature = "interactive"))]
+use crate::no_colors::Colorize;
+#[cfg(feature = "interactive")]
 use dialoguer::{theme::ColorfulTheme, Confirm, Input, Select};
 use env_logger::Builder;
-use log::{Level, LevelFilter, Record};
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use log::{debug, error, warn, Level, LevelFilter, Log, Metadata, Record};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
 
 /// Supported compression types for the custom image format.
 #[derive(Copy, Clone, Debug, ValueEnum)]
@@ -68,22 +119,174 @@ enum CompressType {
     Delta,
     /// Lossy compression – Smallest file size, configurable quality.
     Lossy,
+    /// Run-length encoding with unbounded run lengths – Best for solid-color images.
+    RleVarint,
+    /// Try each lossless method and keep the smallest file.
+    Auto,
+}
+
+/// Channel layout of raw pixel data.
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum RawColor {
+    /// One byte per pixel.
+    Gray,
+    /// Three bytes per pixel, red, green, blue.
+    Rgb,
+    /// Two bytes per pixel, gray then alpha.
+    GrayAlpha,
+}
+
+impl From<RawColor> for ColorType {
+    fn from(color: RawColor) -> Self {
+        match color {
+            RawColor::Gray => ColorType::Gray,
+            RawColor::Rgb => ColorType::Rgb,
+            RawColor::GrayAlpha => ColorType::GrayAlpha,
+        }
+    }
+}
+
+/// PNG encoder effort levels.
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum PngLevel {
+    /// Fastest encoding, largest files.
+    Fast,
+    /// Balanced speed and size.
+    Default,
+    /// Smallest files, slowest encoding.
+    Best,
 }
 
-impl From<CompressType> for CompressionType {
-    fn from(ct: CompressType) -> Self {
-        match ct {
-            CompressType::None => CompressionType::None,
-            CompressType::Rle => CompressionType::RLE,
-            CompressType::Delta => CompressionType::Delta,
-            CompressType::Lossy => CompressionType::Lossy,
+impl From<PngLevel> for PngCompression {
+    fn from(level: PngLevel) -> Self {
+        match level {
+            PngLevel::Fast => PngCompression::Fast,
+            PngLevel::Default => PngCompression::Default,
+            PngLevel::Best => PngCompression::Best,
         }
     }
 }
 
+/// Shapes of the contrast adjustment.
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum Curve {
+    /// Linear stretch, clipped at black and white.
+    Linear,
+    /// Smooth S-curve that rolls off shadows and highlights.
+    Sigmoid,
+}
+
+impl From<Curve> for ContrastCurve {
+    fn from(curve: Curve) -> Self {
+        match curve {
+            Curve::Linear => ContrastCurve::Linear,
+            Curve::Sigmoid => ContrastCurve::Sigmoid,
+        }
+    }
+}
+
+/// Output formats for exporting a `.nor` image.
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum OutputFormat {
+    /// Lossless PNG.
+    Png,
+    /// Lossy JPEG (transparency is flattened).
+    Jpeg,
+    /// Uncompressed BMP.
+    Bmp,
+    /// Lossless WebP.
+    Webp,
+}
+
+impl From<OutputFormat> for ExportFormat {
+    fn from(format: OutputFormat) -> Self {
+        match format {
+            OutputFormat::Png => ExportFormat::Png,
+            OutputFormat::Jpeg => ExportFormat::Jpeg,
+            OutputFormat::Bmp => ExportFormat::Bmp,
+            OutputFormat::Webp => ExportFormat::WebP,
+        }
+    }
+}
+
+/// Looks that expand into a combination of adjustments.
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum LookPreset {
+    /// Punchy contrast with a slight lift.
+    Vivid,
+    /// Brighter, slightly more contrasty.
+    Bright,
+    /// Gentle, low-contrast look.
+    Soft,
+    /// Plain black and white.
+    Bw,
+    /// Black and white with strong contrast.
+    BwHighContrast,
+}
+
+impl From<LookPreset> for Preset {
+    fn from(preset: LookPreset) -> Self {
+        match preset {
+            LookPreset::Vivid => Preset::Vivid,
+            LookPreset::Bright => Preset::Bright,
+            LookPreset::Soft => Preset::Soft,
+            LookPreset::Bw => Preset::Bw,
+            LookPreset::BwHighContrast => Preset::BwHighContrast,
+        }
+    }
+}
+
+/// Watermark placements.
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum WatermarkCorner {
+    /// Top-left corner.
+    TopLeft,
+    /// Top-right corner.
+    TopRight,
+    /// Bottom-left corner.
+    BottomLeft,
+    /// Bottom-right corner.
+    BottomRight,
+    /// Centered on the image.
+    Center,
+}
+
+impl From<WatermarkCorner> for WatermarkPosition {
+    fn from(corner: WatermarkCorner) -> Self {
+        match corner {
+            WatermarkCorner::TopLeft => WatermarkPosition::TopLeft,
+            WatermarkCorner::TopRight => WatermarkPosition::TopRight,
+            WatermarkCorner::BottomLeft => WatermarkPosition::BottomLeft,
+            WatermarkCorner::BottomRight => WatermarkPosition::BottomRight,
+            WatermarkCorner::Center => WatermarkPosition::Center,
+        }
+    }
+}
+
+impl CompressType {
+    /// Returns the fixed compression method, or `None` for auto selection.
+    fn codec(self) -> Option<CompressionType> {
+        match self {
+            CompressType::None => Some(CompressionType::None),
+            CompressType::Rle => Some(CompressionType::RLE),
+            CompressType::Delta => Some(CompressionType::Delta),
+            CompressType::Lossy => Some(CompressionType::Lossy),
+            CompressType::RleVarint => Some(CompressionType::RleVarint),
+            CompressType::Auto => None,
+        }
+    }
+
+    /// Sets the compression method (or auto selection) on a conversion config.
+    fn apply_to(self, config: &mut ConversionConfig) {
+        config.auto_compression = matches!(self, CompressType::Auto);
+        config.compression = self.codec().unwrap_or(CompressionType::None);
+    }
+}
+
 /// Nor-Image: High-performance image processing and conversion tool.
 ///
-/// If no subcommand is provided, interactive mode will launch.
+/// If no subcommand is provided, interactive mode will launch (or, without
+/// the `interactive` feature, the usage is shown).
 #[derive(Parser)]
 #[command(
     name = "nor-image",
@@ -92,16 +295,175 @@ impl From<CompressType> for CompressionType {
     about = "A powerful tool for converting and manipulating images",
     long_about = "Nor-Image CLI\n\
                   \nA high-performance image processing and conversion tool.\n\
-                  \nIf no subcommand is provided, interactive mode is launched by default.\n\
+                  \nIf no subcommand is provided, interactive mode is launched by default\n\
+                  (builds without the interactive feature show this usage instead).\n\
                   \nUsage Examples:\n  • nor-image png-to-custom input.png output.nor\n  • nor-image custom-to-png input.nor output.png\n  • nor-image view image.nor\n  • nor-image info image.nor\n  • nor-image clear-cache"
 )]
 struct Cli {
+    /// Only log errors.
+    #[arg(short, long, global = true, conflicts_with = "verbose", help = "Only log errors")]
+    quiet: bool,
+    /// Increase log verbosity (-v for debug, -vv for trace).
+    #[arg(short, long, global = true, action = ArgAction::Count, help = "Increase log verbosity (-v debug, -vv trace)")]
+    verbose: u8,
+    /// Conversion defaults file (defaults to ./nor-image.toml when present).
+    #[arg(long, global = true, value_name = "PATH", help = "Read conversion defaults from a TOML file")]
+    config: Option<String>,
+    /// Turn questionable conversion outcomes into errors.
+    #[arg(long, global = true, help = "Fail on questionable outcomes (clamped values, expanding compression, dropped text fields) instead of warning")]
+    strict: bool,
+    /// Size of the worker pool used for parallel processing.
+    #[arg(long, global = true, value_name = "N", value_parser = clap::value_parser!(u16).range(1..), help = "Use N worker threads for parallel processing [default: one per core]")]
+    threads: Option<u16>,
+    /// Largest decoded pixel buffer to accept, in bytes.
+    #[arg(long, global = true, value_name = "SIZE", value_parser = parse_byte_size, help = "Refuse images whose decoded pixels exceed SIZE, e.g. 256MiB [default: 1GiB]")]
+    limit_memory: Option<usize>,
+    /// Draw a progress bar for conversions.
+    #[arg(long, global = true, help = "Show a progress bar while converting")]
+    progress: bool,
     #[command(subcommand)]
     command: Option<Commands>,
 }
 
+impl Cli {
+    /// Log level selected by `--quiet` / `-v`; defaults to info.
+    fn log_level(&self) -> LevelFilter {
+        if self.quiet {
+            return LevelFilter::Error;
+        }
+        match self.verbose {
+            0 => LevelFilter::Info,
+            1 => LevelFilter::Debug,
+            _ => LevelFilter::Trace,
+        }
+    }
+}
+
+/// Exit code for failures without a more specific category.
+const EXIT_FAILURE: i32 = 1;
+/// Exit code for invalid arguments, file extensions, or config files.
+const EXIT_INVALID_INPUT: i32 = 2;
+/// Exit code for checksum verification failures.
+const EXIT_CHECKSUM: i32 = 3;
+/// Exit code for I/O errors.
+const EXIT_IO: i32 = 4;
+/// Exit code for unsupported or malformed image data.
+const EXIT_UNSUPPORTED_FORMAT: i32 = 5;
+/// Exit code for questionable outcomes rejected by `--strict`.
+const EXIT_STRICT: i32 = 6;
+
+/// Errors returned by the command handlers, categorized for the exit code.
+#[derive(Debug)]
+enum CliError {
+    /// An argument or file extension was rejected.
+    InvalidInput(String),
+    /// A conversion failed.
+    Conversion(ConversionError),
+    /// A `.nor` file could not be parsed or written.
+    Format(FormatError),
+    /// Reading or writing a file failed.
+    Io(io::Error),
+    /// Any other error, e.g. from the viewer.
+    Other(Box<dyn Error>),
+}
+
+impl CliError {
+    /// Returns the process exit code for this error.
+    fn exit_code(&self) -> i32 {
+        match self {
+            CliError::InvalidInput(_) => EXIT_INVALID_INPUT,
+            CliError::Conversion(e) => conversion_exit_code(e),
+            CliError::Format(e) => format_exit_code(e),
+            CliError::Io(_) => EXIT_IO,
+            CliError::Other(e) => {
+                if let Some(e) = e.downcast_ref::<FormatError>() {
+                    format_exit_code(e)
+                } else if e.is::<io::Error>() {
+                    EXIT_IO
+                } else {
+                    EXIT_FAILURE
+                }
+            }
+        }
+    }
+}
+
+/// Maps a conversion error to its exit code.
+fn conversion_exit_code(e: &ConversionError) -> i32 {
+    match e {
+        ConversionError::ImageError(image::ImageError::IoError(_)) => EXIT_IO,
+        ConversionError::ImageError(_) => EXIT_UNSUPPORTED_FORMAT,
+        ConversionError::FormatError(e) => format_exit_code(e),
+        ConversionError::ProcessingError(ProcessingError::IoError(_)) => EXIT_IO,
+        ConversionError::ProcessingError(ProcessingError::FormatError(e)) => format_exit_code(e),
+        ConversionError::UnsupportedFormat(_) => EXIT_UNSUPPORTED_FORMAT,
+        ConversionError::IoError(_) => EXIT_IO,
+        ConversionError::ConfigError(_)
+        | ConversionError::DimensionMismatch { .. }
+        | ConversionError::InvalidOption(_)
+        | ConversionError::DimensionTooLarge { .. }
+        | ConversionError::OutputExists(_) => EXIT_INVALID_INPUT,
+        ConversionError::Strict(_) => EXIT_STRICT,
+    }
+}
+
+/// Maps a format error to its exit code.
+fn format_exit_code(e: &FormatError) -> i32 {
+    match e {
+        FormatError::ChecksumMismatch => EXIT_CHECKSUM,
+        FormatError::IoError(_) => EXIT_IO,
+        _ => EXIT_UNSUPPORTED_FORMAT,
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::InvalidInput(msg) => write!(f, "{}", msg),
+            CliError::Conversion(e) => write!(f, "{}", e),
+            CliError::Format(e) => write!(f, "{}", e),
+            CliError::Io(e) => write!(f, "{}", e),
+            CliError::Other(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<ConversionError> for CliError {
+    fn from(e: ConversionError) -> Self {
+        CliError::Conversion(e)
+    }
+}
+
+impl From<FormatError> for CliError {
+    fn from(e: FormatError) -> Self {
+        CliError::Format(e)
+    }
+}
+
+impl From<io::Error> for CliError {
+    fn from(e: io::Error) -> Self {
+        CliError::Io(e)
+    }
+}
+
+impl From<Box<dyn Error>> for CliError {
+    fn from(e: Box<dyn Error>) -> Self {
+        CliError::Other(e)
+    }
+}
+
+impl From<serde_json::Error> for CliError {
+    fn from(e: serde_json::Error) -> Self {
+        CliError::Other(Box::new(e))
+    }
+}
+
+/// Config file looked up in the working directory when `--config` is not given.
+const DEFAULT_CONFIG_FILE: &str = "nor-image.toml";
+
 /// Available commands.
 #[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)]
 enum Commands {
     /// Convert a PNG file to the custom `.nor` format.
     #[command(name = "png-to-custom", visible_alias = "p2n")]
@@ -115,75 +477,479 @@ enum Commands {
         /// Convert image to grayscale.
         #[arg(long, help = "Convert to grayscale (reduces file size)")]
         grayscale: bool,
+        /// Store images without color as grayscale.
+        #[arg(long, conflicts_with = "grayscale", help = "Store as grayscale if the image has no color")]
+        auto_grayscale: bool,
+        /// Channel weighting for the grayscale conversion.
+        #[arg(long, value_name = "METHOD", value_parser = parse_grayscale_method, help = "Grayscale weighting: luma601, luma709 (default), average or R,G,B weights")]
+        grayscale_method: Option<GrayscaleMethod>,
         /// Compression method.
-        #[arg(long, value_enum, default_value = "none", help = "Compression method")]
-        compression: CompressType,
+        #[arg(long, value_enum, help = "Compression method [default: none]")]
+        compression: Option<CompressType>,
+        /// Let `--compression auto` choose lossy compression.
+        #[arg(long, help = "Allow --compression auto to pick lossy compression")]
+        allow_lossy: bool,
+        /// Quality of lossy compression.
+        #[arg(long, value_name = "1-100", value_parser = clap::value_parser!(u8).range(1..=100), help = "Lossy compression quality; lower averages larger blocks [default: 50]")]
+        lossy_quality: Option<u8>,
+        /// Keep the output file at or under this size.
+        #[arg(long, value_name = "SIZE", value_parser = parse_byte_size, help = "Keep the output under SIZE bytes (e.g. 500K, 2M) by lowering the lossy quality; needs --compression lossy")]
+        max_bytes: Option<usize>,
+        /// Store pixel data as independently compressed tiles.
+        #[arg(long, help = "Store as independently compressed tiles for partial decoding")]
+        tiled: bool,
+        /// Tile size in pixels (implies --tiled).
+        #[arg(long, value_name = "PIXELS", help = "Tile side length in pixels (implies --tiled) [default: 256]")]
+        tile_size: Option<u32>,
+        /// Number of reduced-resolution levels to store.
+        #[arg(long, value_name = "LEVELS", help = "Also store LEVELS halved copies (1/2, 1/4, ...) for fast downscaled export")]
+        pyramid: Option<u32>,
         /// Target width for resizing.
         #[arg(long, value_name = "PIXELS", help = "Resize to specified width")]
         width: Option<u32>,
         /// Target height for resizing.
         #[arg(long, value_name = "PIXELS", help = "Resize to specified height")]
         height: Option<u32>,
+        /// Resize to a percentage of the source size.
+        #[arg(long, value_name = "PERCENT", allow_hyphen_values = true, conflicts_with_all = ["width", "height"], help = "Resize to a percentage of the source size")]
+        scale: Option<f32>,
+        /// Center-crop to an aspect ratio before resizing.
+        #[arg(long, value_name = "W:H", value_parser = parse_aspect, help = "Center-crop to this aspect ratio (e.g. 16:9) before resizing")]
+        aspect: Option<(u32, u32)>,
         /// Brightness adjustment (-255 to 255).
-        #[arg(long, default_value = "0", value_name = "VALUE", help = "Adjust brightness (-255 to 255)")]
-        brightness: i32,
+        #[arg(long, value_name = "VALUE", allow_hyphen_values = true, help = "Adjust brightness (-255 to 255) [default: 0]")]
+        brightness: Option<i32>,
         /// Contrast adjustment (-255 to 255).
-        #[arg(long, default_value = "0", value_name = "VALUE", help = "Adjust contrast (-255 to 255)")]
-        contrast: i32,
+        #[arg(long, value_name = "VALUE", allow_hyphen_values = true, help = "Adjust contrast (-255 to 255) [default: 0]")]
+        contrast: Option<i32>,
+        /// Red brightness, replacing `--brightness` for that channel.
+        #[arg(long, value_name = "VALUE", allow_hyphen_values = true, help = "Adjust red brightness (default: --brightness)")]
+        brightness_r: Option<i32>,
+        /// Green brightness, replacing `--brightness` for that channel.
+        #[arg(long, value_name = "VALUE", allow_hyphen_values = true, help = "Adjust green brightness (default: --brightness)")]
+        brightness_g: Option<i32>,
+        /// Blue brightness, replacing `--brightness` for that channel.
+        #[arg(long, value_name = "VALUE", allow_hyphen_values = true, help = "Adjust blue brightness (default: --brightness)")]
+        brightness_b: Option<i32>,
+        /// Red contrast, replacing `--contrast` for that channel.
+        #[arg(long, value_name = "VALUE", allow_hyphen_values = true, help = "Adjust red contrast (default: --contrast)")]
+        contrast_r: Option<i32>,
+        /// Green contrast, replacing `--contrast` for that channel.
+        #[arg(long, value_name = "VALUE", allow_hyphen_values = true, help = "Adjust green contrast (default: --contrast)")]
+        contrast_g: Option<i32>,
+        /// Blue contrast, replacing `--contrast` for that channel.
+        #[arg(long, value_name = "VALUE", allow_hyphen_values = true, help = "Adjust blue contrast (default: --contrast)")]
+        contrast_b: Option<i32>,
+        /// Shape of the contrast adjustment.
+        #[arg(long, value_enum, help = "Contrast curve [default: linear]")]
+        contrast_curve: Option<Curve>,
+        /// Preset look; explicit adjustment flags override its values.
+        #[arg(long, value_enum, help = "Apply a preset look (explicit adjustments take precedence)")]
+        preset: Option<LookPreset>,
+        /// Median-filter radius for denoising.
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(..=denoise::MAX_MEDIAN_RADIUS as i64), help = "Denoise with a median filter of radius N, at most 100 (slower for large N)")]
+        median: Option<u32>,
+        /// Reduce the image to at most this many colors.
+        #[arg(long, value_name = "N", help = "Reduce to at most N colors (still stored as RGB)")]
+        quantize: Option<u16>,
+        /// Seed for tie-breaking and randomized steps.
+        #[arg(long, value_name = "N", help = "Seed for --quantize tie-breaking [default: fixed, so output is reproducible]")]
+        seed: Option<u64>,
+        /// Remove color casts with gray-world white balance.
+        #[arg(long, help = "Auto white balance: neutralize color casts (e.g. indoor lighting)")]
+        awb: bool,
+        /// Text to stamp onto the image.
+        #[arg(long, value_name = "TEXT", conflicts_with = "watermark_image", help = "Stamp this text onto the image")]
+        watermark_text: Option<String>,
+        /// Image (e.g. a PNG logo) to stamp onto the image.
+        #[arg(long, value_name = "PATH", help = "Stamp this image onto the image")]
+        watermark_image: Option<PathBuf>,
+        /// Where to place the watermark.
+        #[arg(long, value_enum, value_name = "POSITION", help = "Watermark position [default: bottom-right]")]
+        watermark_position: Option<WatermarkCorner>,
+        /// Watermark opacity (0.0 to 1.0).
+        #[arg(long, value_name = "0.0-1.0", help = "Watermark opacity [default: 0.5]")]
+        watermark_opacity: Option<f32>,
+        /// Background color for flattening transparent pixels.
+        #[arg(long, value_name = "RRGGBB", value_parser = parse_hex_color, help = "Flatten transparency onto this color [default: FFFFFF]")]
+        background: Option<[u8; 3]>,
+        /// Keep PNG text chunks as custom metadata fields.
+        #[arg(long, help = "Store PNG text chunks (tEXt/zTXt/iTXt) in the metadata")]
+        preserve_text: bool,
+        /// Convert pixels with an embedded ICC profile to sRGB.
+        #[arg(long, help = "Convert images with an embedded ICC profile to sRGB and store no profile")]
+        to_srgb: bool,
+        /// Record the current time as the creation date.
+        #[arg(long, help = "Use the current time as the creation date instead of the source's EXIF date or modification time")]
+        now: bool,
         /// Disable image caching.
         #[arg(long, help = "Disable caching for faster processing")]
         no_cache: bool,
+        /// Write the file without a SHA256 checksum.
+        #[arg(long, help = "Skip the integrity checksum (for trusted pipelines only)")]
+        no_checksum: bool,
+        /// End the file with a footer magic marking its boundary.
+        #[arg(long, help = "Append a footer magic (GMIC) so packed .nor blobs can be split")]
+        footer: bool,
+        /// Pad the metadata block so it can be edited in place later.
+        #[arg(long, value_name = "BYTES", help = "Reserve metadata space so set-meta can edit it without rewriting pixels")]
+        reserve_metadata: Option<u32>,
         /// Disable streaming processing.
-        #[arg(long, help = "Disable streaming (uses more memory)")]
+        #[arg(long, help = "Read input in one piece instead of streaming chunks (faster; streaming peaks at twice the file size)")]
         no_streaming: bool,
         /// Chunk size for parallel processing (in MB).
-        #[arg(long, default_value = "1", value_name = "MB", help = "Chunk size for parallel processing (MB)")]
-        chunk_size: usize,
+        #[arg(long, value_name = "MB", value_parser = clap::value_parser!(u32).range(1..), help = "Chunk size for parallel processing (MB) [default: 1]")]
+        chunk_size: Option<u32>,
+        /// Run the full pipeline but only report the resulting size.
+        #[arg(long, help = "Report output size and compression ratio without writing the file")]
+        dry_run: bool,
+        /// Print how long each conversion stage took.
+        #[arg(long, help = "Print a per-stage timing summary after converting")]
+        timings: bool,
+        /// Replace the output file if it already exists.
+        #[arg(short = 'f', long, help = "Overwrite the output file if it exists")]
+        overwrite: bool,
+        /// Reload the written file and print its info.
+        #[arg(long, help = "Print the info of the written file, to confirm what was stored")]
+        show_info: bool,
     },
-    /// Convert a `.nor` file back to PNG format.
+    /// Convert a `.nor` file back to PNG (or JPEG, BMP or WebP, chosen by
+    /// the output extension).
     #[command(name = "custom-to-png", visible_alias = "n2p")]
     CustomToPng {
         /// Input .nor file path (must have .nor extension).
         #[arg(value_name = "input.nor")]
         input: String,
-        /// Output PNG file path (must have .png extension).
+        /// Output file path (.png, .jpg, .jpeg, .bmp or .webp).
         #[arg(value_name = "output.png")]
         output: String,
+        /// Output format, overriding the one implied by the extension.
+        #[arg(long, value_enum, help = "Output format [default: from the output extension]")]
+        format: Option<OutputFormat>,
         /// Target width for resizing.
         #[arg(long, value_name = "PIXELS", help = "Resize to specified width")]
         width: Option<u32>,
         /// Target height for resizing.
         #[arg(long, value_name = "PIXELS", help = "Resize to specified height")]
         height: Option<u32>,
+        /// Resize to a percentage of the source size.
+        #[arg(long, value_name = "PERCENT", allow_hyphen_values = true, conflicts_with_all = ["width", "height"], help = "Resize to a percentage of the source size")]
+        scale: Option<f32>,
         /// Brightness adjustment (-255 to 255).
-        #[arg(long, default_value = "0", value_name = "VALUE", help = "Adjust brightness (-255 to 255)")]
-        brightness: i32,
+        #[arg(long, value_name = "VALUE", allow_hyphen_values = true, help = "Adjust brightness (-255 to 255) [default: 0]")]
+        brightness: Option<i32>,
         /// Contrast adjustment (-255 to 255).
-        #[arg(long, default_value = "0", value_name = "VALUE", help = "Adjust contrast (-255 to 255)")]
-        contrast: i32,
+        #[arg(long, value_name = "VALUE", allow_hyphen_values = true, help = "Adjust contrast (-255 to 255) [default: 0]")]
+        contrast: Option<i32>,
+        /// Shape of the contrast adjustment.
+        #[arg(long, value_enum, help = "Contrast curve [default: linear]")]
+        contrast_curve: Option<Curve>,
+        /// PNG encoder effort.
+        #[arg(long, value_enum, help = "PNG compression effort [default: best]")]
+        png_compression: Option<PngLevel>,
+        /// Write custom metadata fields as PNG text chunks.
+        #[arg(long, help = "Write custom metadata fields as PNG text chunks")]
+        preserve_text: bool,
         /// Disable streaming processing.
-        #[arg(long, help = "Disable streaming (uses more memory)")]
+        #[arg(long, help = "Read input in one piece instead of streaming chunks (faster; streaming peaks at twice the file size)")]
         no_streaming: bool,
         /// Chunk size for parallel processing (in MB).
-        #[arg(long, default_value = "1", value_name = "MB", help = "Chunk size for parallel processing (MB)")]
-        chunk_size: usize,
+        #[arg(long, value_name = "MB", value_parser = clap::value_parser!(u32).range(1..), help = "Chunk size for parallel processing (MB) [default: 1]")]
+        chunk_size: Option<u32>,
+        /// Replace the output file if it already exists.
+        #[arg(short = 'f', long, help = "Overwrite the output file if it exists")]
+        overwrite: bool,
+    },
+    /// Convert a `.nor` file to WebP format.
+    #[command(name = "custom-to-webp", visible_alias = "n2w")]
+    CustomToWebp {
+        /// Input .nor file path (must have .nor extension).
+        #[arg(value_name = "input.nor")]
+        input: String,
+        /// Output WebP file path (must have .webp extension).
+        #[arg(value_name = "output.webp")]
+        output: String,
+        /// Target width for resizing.
+        #[arg(long, value_name = "PIXELS", help = "Resize to specified width")]
+        width: Option<u32>,
+        /// Target height for resizing.
+        #[arg(long, value_name = "PIXELS", help = "Resize to specified height")]
+        height: Option<u32>,
+        /// Resize to a percentage of the source size.
+        #[arg(long, value_name = "PERCENT", allow_hyphen_values = true, conflicts_with_all = ["width", "height"], help = "Resize to a percentage of the source size")]
+        scale: Option<f32>,
+        /// Brightness adjustment (-255 to 255).
+        #[arg(long, value_name = "VALUE", allow_hyphen_values = true, help = "Adjust brightness (-255 to 255) [default: 0]")]
+        brightness: Option<i32>,
+        /// Contrast adjustment (-255 to 255).
+        #[arg(long, value_name = "VALUE", allow_hyphen_values = true, help = "Adjust contrast (-255 to 255) [default: 0]")]
+        contrast: Option<i32>,
+        /// Shape of the contrast adjustment.
+        #[arg(long, value_enum, help = "Contrast curve [default: linear]")]
+        contrast_curve: Option<Curve>,
+        /// Lossy quality (0-100); lossless when omitted.
+        #[arg(long, value_name = "0-100", help = "Encode lossy WebP at this quality [default: lossless]")]
+        quality: Option<f32>,
+        /// Replace the output file if it already exists.
+        #[arg(short = 'f', long, help = "Overwrite the output file if it exists")]
+        overwrite: bool,
     },
-    /// View a `.nor` image.
+    /// View a `.nor` image, or a PNG to preview how each codec treats it.
     #[command(name = "view", visible_alias = "v")]
     View {
-        /// Input .nor file path.
-        #[arg(value_name = "IMAGE.nor", help = "Path to .nor image file")]
+        /// Input .nor or .png file path.
+        #[arg(value_name = "IMAGE", help = "Path to .nor or .png image file")]
         input: String,
         /// Use cached version if available.
         #[arg(long, help = "Use cached version for faster loading")]
         use_cache: bool,
     },
+    /// Render a `.nor` image in the terminal, for use without a display.
+    #[command(name = "preview")]
+    Preview {
+        /// Input .nor file path.
+        #[arg(value_name = "IMAGE.nor", help = "Path to .nor image file")]
+        input: String,
+        /// Maximum width in characters; defaults to the terminal width.
+        #[arg(short, long, value_name = "COLUMNS", help = "Preview width in characters (default: terminal width)")]
+        width: Option<u16>,
+    },
+    /// View two `.nor` images side by side with a draggable divider.
+    #[command(name = "compare-view", visible_alias = "cv")]
+    CompareView {
+        /// Image shown left of the divider.
+        #[arg(value_name = "A.nor", help = "Path to the left .nor image")]
+        left: String,
+        /// Image shown right of the divider.
+        #[arg(value_name = "B.nor", help = "Path to the right .nor image")]
+        right: String,
+    },
     /// Display metadata of a `.nor` image.
     #[command(name = "info", visible_alias = "i")]
     Info {
+        /// Input .nor file path, or `-` to read from stdin.
+        #[arg(value_name = "IMAGE.nor", help = "Path to .nor image file (- for stdin)")]
+        input: String,
+        /// Print the header and metadata as JSON instead of text.
+        #[arg(long, help = "Output machine-readable JSON")]
+        json: bool,
+    },
+    /// Report whether files are `.nor` images, without decoding them.
+    #[command(name = "identify")]
+    Identify {
+        /// Files to check.
+        #[arg(value_name = "PATH", required = true, help = "Files to identify")]
+        paths: Vec<String>,
+    },
+    /// Show how the metadata of two `.nor` images differs, and whether
+    /// their pixels do.
+    #[command(name = "meta-diff")]
+    MetaDiff {
+        /// The older .nor file.
+        #[arg(value_name = "A.nor")]
+        old: String,
+        /// The newer .nor file.
+        #[arg(value_name = "B.nor")]
+        new: String,
+    },
+    /// Compare two images and report quality metrics.
+    #[command(name = "compare", visible_alias = "cmp")]
+    Compare {
+        /// Reference image path (.nor or .png).
+        #[arg(value_name = "REFERENCE", help = "Reference image (.nor or .png)")]
+        reference: String,
+        /// Image to compare against the reference (.nor or .png).
+        #[arg(value_name = "TEST", help = "Image to compare (.nor or .png)")]
+        test: String,
+        /// Also compute structural similarity.
+        #[arg(long, help = "Also compute SSIM (slower)")]
+        ssim: bool,
+        /// Print the metrics as JSON instead of text.
+        #[arg(long, help = "Output machine-readable JSON")]
+        json: bool,
+    },
+    /// Convert every PNG in a directory to `.nor`.
+    #[command(name = "batch")]
+    Batch {
+        /// Directory containing the PNG files.
+        #[arg(value_name = "INPUT_DIR", required_unless_present = "from_stdin", conflicts_with = "from_stdin")]
+        input_dir: Option<String>,
+        /// Directory the `.nor` files are written to (created if missing).
+        #[arg(value_name = "OUTPUT_DIR", required_unless_present = "output_dir_flag")]
+        output_dir: Option<String>,
+        /// Output directory, as an alternative to the positional argument.
+        #[arg(short = 'o', long = "output-dir", value_name = "DIR", conflicts_with = "output_dir", help = "Directory the .nor files are written to")]
+        output_dir_flag: Option<String>,
+        /// Read newline-separated PNG paths from stdin instead of walking a
+        /// directory.
+        #[arg(long, help = "Read PNG paths from stdin, one per line (e.g. from find)")]
+        from_stdin: bool,
+        /// Also convert PNGs in subdirectories, mirroring the tree.
+        #[arg(short, long, conflicts_with = "from_stdin", help = "Walk subdirectories, mirroring them under the output directory")]
+        recursive: bool,
+        /// Compression method.
+        #[arg(short, long, value_enum, help = "Compression method [default: none]")]
+        compression: Option<CompressType>,
+        /// Convert to grayscale.
+        #[arg(short, long, help = "Convert images to grayscale")]
+        grayscale: bool,
+        /// Replace output files that already exist.
+        #[arg(short = 'f', long, help = "Overwrite output files that exist")]
+        overwrite: bool,
+    },
+    /// Benchmark every compression method on a PNG image.
+    #[command(name = "bench")]
+    Bench {
+        /// Input PNG file path.
+        #[arg(value_name = "INPUT.png")]
+        input: String,
+        /// Benchmark the grayscale conversion instead of RGB.
+        #[arg(long, help = "Convert to grayscale before compressing")]
+        grayscale: bool,
+        /// Print the results as JSON instead of a table.
+        #[arg(long, help = "Output machine-readable JSON")]
+        json: bool,
+    },
+    /// Remove identifying metadata from a `.nor` image.
+    #[command(name = "strip-meta")]
+    StripMeta {
         /// Input .nor file path.
-        #[arg(value_name = "IMAGE.nor", help = "Path to .nor image file")]
+        #[arg(value_name = "INPUT.nor")]
+        input: String,
+        /// Output .nor file path (defaults to rewriting the input).
+        #[arg(value_name = "OUTPUT.nor")]
+        output: Option<String>,
+        /// Keep the creation timestamp instead of zeroing it.
+        #[arg(long, help = "Keep the creation date")]
+        keep_date: bool,
+        /// Replace the output file if it already exists.
+        #[arg(short = 'f', long, help = "Overwrite the output file if it exists")]
+        overwrite: bool,
+    },
+    /// Set metadata fields of a `.nor` image, in place when it has reserved
+    /// metadata space.
+    #[command(name = "set-meta")]
+    SetMeta {
+        /// The .nor file to edit.
+        #[arg(value_name = "FILE.nor")]
         input: String,
+        /// Author to record.
+        #[arg(long, help = "Set the author")]
+        author: Option<String>,
+        /// Camera model to record.
+        #[arg(long, help = "Set the camera model")]
+        camera_model: Option<String>,
+        /// Title to record.
+        #[arg(long, help = "Set the title (PNG Title text chunk on export)")]
+        title: Option<String>,
+        /// Description to record.
+        #[arg(long, help = "Set the description (PNG Description text chunk on export)")]
+        description: Option<String>,
+        /// Copyright notice to record.
+        #[arg(long, help = "Set the copyright notice (PNG Copyright text chunk on export)")]
+        copyright: Option<String>,
+        /// Custom fields to set, as `KEY=VALUE`.
+        #[arg(long = "field", value_name = "KEY=VALUE", value_parser = parse_key_value, help = "Set a custom field (repeatable)")]
+        fields: Vec<(String, String)>,
+    },
+    /// Change the compression of a `.nor` image without going through PNG.
+    #[command(name = "recompress")]
+    Recompress {
+        /// Input .nor file path.
+        #[arg(value_name = "INPUT.nor")]
+        input: String,
+        /// Output .nor file path.
+        #[arg(value_name = "OUTPUT.nor")]
+        output: String,
+        /// Compression method for the output.
+        #[arg(short, long, value_enum, help = "Compression method for the output")]
+        compression: CompressType,
+        /// Replace the output file if it already exists.
+        #[arg(short = 'f', long, help = "Overwrite the output file if it exists")]
+        overwrite: bool,
+        /// Reload the written file and print its info.
+        #[arg(long, help = "Print the info of the written file, to confirm what was stored")]
+        show_info: bool,
+    },
+    /// Write the decompressed pixels of a `.nor` image to a `.raw` or Netpbm file.
+    #[command(name = "dump-raw")]
+    DumpRaw {
+        /// Input .nor file path.
+        #[arg(value_name = "INPUT.nor", help = "Path to input .nor file")]
+        input: String,
+        /// Output path; `.raw` for headerless samples, `.pgm`/`.ppm`/`.pnm` for Netpbm.
+        #[arg(value_name = "OUTPUT", help = "Output path (.raw, .pgm, .ppm or .pnm)")]
+        output: String,
+        /// Replace the output file if it already exists.
+        #[arg(short = 'f', long, help = "Overwrite the output file if it exists")]
+        overwrite: bool,
+    },
+    /// Tile thumbnails of several `.nor` images into one PNG grid.
+    #[command(name = "contact-sheet")]
+    ContactSheet {
+        /// Output PNG file path.
+        #[arg(value_name = "OUTPUT.png")]
+        output: String,
+        /// Input .nor files, placed row by row in this order.
+        #[arg(value_name = "INPUT.nor", required = true, num_args = 1..)]
+        inputs: Vec<String>,
+        /// Number of cells per row.
+        #[arg(long, value_name = "N", default_value_t = 4, value_parser = clap::value_parser!(u32).range(1..), help = "Number of cells per row")]
+        cols: u32,
+        /// Size of the square cells, in pixels.
+        #[arg(long, value_name = "PIXELS", default_value_t = 128, value_parser = clap::value_parser!(u32).range(8..), help = "Cell size in pixels; images are scaled down to fit")]
+        cell: u32,
+        /// Leave out the file name labels.
+        #[arg(long, help = "Do not label cells with their file names")]
+        no_labels: bool,
+        /// Replace the output file if it already exists.
+        #[arg(short = 'f', long, help = "Overwrite the output file if it exists")]
+        overwrite: bool,
+    },
+    /// Build a `.nor` image from headerless pixel data of a declared geometry.
+    #[command(name = "raw-to-custom")]
+    RawToCustom {
+        /// Input file of interleaved samples, rows top to bottom.
+        #[arg(value_name = "INPUT", help = "Path to the raw pixel data")]
+        input: String,
+        /// Output .nor file path.
+        #[arg(value_name = "OUTPUT.nor", help = "Path for the output .nor file")]
+        output: String,
+        /// Width of the image in pixels.
+        #[arg(long, help = "Image width in pixels")]
+        width: u32,
+        /// Height of the image in pixels.
+        #[arg(long, help = "Image height in pixels")]
+        height: u32,
+        /// Channel layout of the samples.
+        #[arg(long, value_enum, help = "Color type of the raw samples")]
+        color: RawColor,
+        /// Compression method for the output.
+        #[arg(short, long, value_enum, default_value = "none", help = "Compression method for the output")]
+        compression: CompressType,
+        /// Replace the output file if it already exists.
+        #[arg(short = 'f', long, help = "Overwrite the output file if it exists")]
+        overwrite: bool,
+        /// Reload the written file and print its info.
+        #[arg(long, help = "Print the info of the written file, to confirm what was stored")]
+        show_info: bool,
+    },
+    /// List the compression methods and color types this build supports.
+    #[command(name = "capabilities")]
+    Capabilities {
+        /// Print the capabilities as JSON instead of a list.
+        #[arg(long, help = "Output machine-readable JSON")]
+        json: bool,
+    },
+    /// Round-trip a synthetic image through every color type and compression.
+    #[command(name = "selftest")]
+    Selftest,
+    /// Write reproducible `.nor` regression fixtures into a directory.
+    #[command(name = "gen-test-vectors", hide = true)]
+    GenTestVectors {
+        /// Directory to write the fixtures into (created if missing).
+        #[arg(value_name = "DIR")]
+        dir: String,
     },
     /// Clear the image cache.
     #[command(name = "clear-cache", visible_alias = "cc")]
@@ -193,6 +959,85 @@ enum Commands {
     Interactive,
 }
 
+/// Parses a `RRGGBB` hex color, with or without a leading `#`.
+fn parse_hex_color(value: &str) -> Result<[u8; 3], String> {
+    let hex = value.strip_prefix('#').unwrap_or(value);
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("Invalid color '{}'. Expected RRGGBB", value));
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|e| e.to_string());
+    Ok([channel(0)?, channel(2)?, channel(4)?])
+}
+
+/// Combines per-channel flags into red, green and blue values, filling the
+/// channels left unset from `base`. Returns `None` if no channel was given.
+fn channel_values(base: i32, channels: [Option<i32>; 3]) -> Option<[i32; 3]> {
+    channels.iter().any(Option::is_some).then(|| channels.map(|value| value.unwrap_or(base)))
+}
+
+/// Parses a grayscale method name, or custom `R,G,B` channel weights.
+fn parse_grayscale_method(value: &str) -> Result<GrayscaleMethod, String> {
+    match value {
+        "luma601" => Ok(GrayscaleMethod::Luma601),
+        "luma709" => Ok(GrayscaleMethod::Luma709),
+        "average" => Ok(GrayscaleMethod::Average),
+        _ => {
+            let weights: Vec<f32> = value
+                .split(',')
+                .map(|w| w.trim().parse::<f32>())
+                .collect::<Result<_, _>>()
+                .map_err(|_| format!("Invalid grayscale method '{}'. Expected luma601, luma709, average or R,G,B", value))?;
+            let weights: [f32; 3] = weights
+                .try_into()
+                .map_err(|_| format!("Invalid grayscale weights '{}'. Expected three values R,G,B", value))?;
+            Ok(GrayscaleMethod::Custom(weights))
+        }
+    }
+}
+
+/// Parses a `W:H` aspect ratio with positive terms.
+fn parse_aspect(value: &str) -> Result<(u32, u32), String> {
+    let error = || format!("Invalid aspect ratio '{}'. Expected W:H, e.g. 16:9", value);
+    let (w, h) = value.split_once(':').ok_or_else(error)?;
+    match (w.trim().parse::<u32>(), h.trim().parse::<u32>()) {
+        (Ok(w), Ok(h)) if w > 0 && h > 0 => Ok((w, h)),
+        _ => Err(error()),
+    }
+}
+
+/// Parses a `KEY=VALUE` pair with a non-empty key.
+fn parse_key_value(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("Invalid field '{}'. Expected KEY=VALUE", value)),
+    }
+}
+
+/// Parses a byte count with an optional unit: `K`/`KB` and `M`/`MB` are
+/// decimal (1000), `KiB` and `MiB` binary (1024). Units are case-insensitive.
+fn parse_byte_size(value: &str) -> Result<usize, String> {
+    let error = || format!("Invalid size '{}'. Expected bytes, e.g. 500000, 500K, 2MiB or 1GiB", value);
+    let trimmed = value.trim();
+    let split = trimmed.find(|c: char| !c.is_ascii_digit()).unwrap_or(trimmed.len());
+    let (number, unit) = trimmed.split_at(split);
+    let multiplier = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" => 1_000,
+        "kib" => 1_024,
+        "m" | "mb" => 1_000_000,
+        "mib" => 1_048_576,
+        "g" | "gb" => 1_000_000_000,
+        "gib" => 1_073_741_824,
+        _ => return Err(error()),
+    };
+    number
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .filter(|&n| n > 0)
+        .ok_or_else(error)
+}
+
 /// Validates that the provided path has a `.nor` extension.
 fn validate_nor_extension(path: &str) -> Result<(), String> {
     let ext = Path::new(path)
@@ -206,64 +1051,937 @@ fn validate_nor_extension(path: &str) -> Result<(), String> {
     }
 }
 
-/// Validates that the provided path has a `.png` extension.
-fn validate_png_extension(path: &str) -> Result<(), String> {
-    let ext = Path::new(path)
-        .extension()
-        .and_then(|ext| ext.to_str())
-        .unwrap_or("");
-    if ext == "png" {
-        Ok(())
-    } else {
-        Err(format!("Invalid file extension. Expected .png, got: {}", path))
+/// Validates that the provided path has a `.png` extension.
+fn validate_png_extension(path: &str) -> Result<(), String> {
+    let ext = Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("");
+    if ext == "png" {
+        Ok(())
+    } else {
+        Err(format!("Invalid file extension. Expected .png, got: {}", path))
+    }
+}
+
+/// Validates that the provided path has a `.webp` extension.
+fn validate_webp_extension(path: &str) -> Result<(), String> {
+    let ext = Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("");
+    if ext == "webp" {
+        Ok(())
+    } else {
+        Err(format!("Invalid file extension. Expected .webp, got: {}", path))
+    }
+}
+
+/// Header fields and metadata of a `.nor` image, as printed by `info --json`.
+#[derive(Serialize)]
+struct ImageInfo<'a> {
+    width: u32,
+    height: u32,
+    color_type: ColorType,
+    compression: CompressionType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tile_size: Option<u32>,
+    /// Dimensions of each reduced pyramid level, largest first.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    levels: Vec<(u32, u32)>,
+    stored_bytes: usize,
+    uncompressed_bytes: usize,
+    compression_ratio: f64,
+    checksummed: bool,
+    footer: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reserved_metadata_bytes: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    icc_profile_bytes: Option<usize>,
+    metadata: &'a ImageMetadata,
+}
+
+impl<'a> From<&'a CustomImage> for ImageInfo<'a> {
+    fn from(image: &'a CustomImage) -> Self {
+        ImageInfo {
+            width: image.width,
+            height: image.height,
+            color_type: image.color_type,
+            compression: image.compression,
+            tile_size: image.tiles.as_ref().map(|index| index.tile_size),
+            levels: image.levels.iter().map(|level| (level.width, level.height)).collect(),
+            stored_bytes: image.data.len(),
+            uncompressed_bytes: image.uncompressed_len(),
+            compression_ratio: image.compression_ratio(),
+            checksummed: image.checksummed,
+            footer: image.footer,
+            reserved_metadata_bytes: image.metadata_capacity,
+            icc_profile_bytes: image.icc_profile().map(<[u8]>::len),
+            metadata: &image.metadata,
+        }
+    }
+}
+
+/// Reads back a `.nor` file that was just written and displays its
+/// metadata, showing what was actually stored.
+fn display_written_metadata(path: &str) -> Result<(), CliError> {
+    display_metadata(&CustomImage::from_bytes(&fs::read(path)?)?);
+    Ok(())
+}
+
+/// Displays metadata of a custom image in a formatted way.
+fn display_metadata(image: &CustomImage) {
+    println!("\n{}", "Image Information:".bright_cyan().bold());
+    println!("{}", "-------------------".bright_cyan());
+    println!("{}: {}x{}", "Dimensions".bright_yellow(), image.width, image.height);
+    println!("{}: {:?}", "Color Type".bright_yellow(), image.color_type);
+    println!("{}: {:?}", "Compression".bright_yellow(), image.compression);
+    if image.compression == CompressionType::Lossy {
+        println!("{}: {}", "Lossy Quality".bright_yellow(), image.lossy_quality);
+    }
+    if let Some(index) = &image.tiles {
+        println!(
+            "{}: {}x{} ({} tiles)",
+            "Tiles".bright_yellow(),
+            index.tile_size,
+            index.tile_size,
+            index.lengths.len()
+        );
+    }
+    if !image.levels.is_empty() {
+        let sizes: Vec<String> = image.levels.iter().map(|level| format!("{}x{}", level.width, level.height)).collect();
+        println!("{}: {}", "Pyramid Levels".bright_yellow(), sizes.join(", "));
+    }
+    println!("{}: {} bytes", "Stored Size".bright_yellow(), image.data.len());
+    println!("{}: {} bytes", "Uncompressed Size".bright_yellow(), image.uncompressed_len());
+    println!(
+        "{}: {:.2}x{}",
+        "Compression Ratio".bright_yellow(),
+        image.compression_ratio(),
+        if image.compression == CompressionType::Lossy { " (approximate, lossy)" } else { "" }
+    );
+    if image.checksummed {
+        println!("{}: SHA256", "Checksum".bright_yellow());
+    } else {
+        println!("{}: {}", "Checksum".bright_yellow(), "none (written with --no-checksum)".bright_red());
+    }
+    if image.footer {
+        println!("{}: {}", "Footer".bright_yellow(), String::from_utf8_lossy(FOOTER_MAGIC));
+    }
+    if let Some(capacity) = image.metadata_capacity {
+        println!("{}: {} bytes", "Reserved Metadata".bright_yellow(), capacity);
+    }
+    if let Some(profile) = image.icc_profile() {
+        println!("{}: {} bytes", "ICC Profile".bright_yellow(), profile.len());
+    }
+    
+    println!("\n{}", "Metadata:".bright_cyan().bold());
+    println!("{}: {}", "Creation Date".bright_yellow(), image.metadata.creation_date);
+    
+    if let Some(ref author) = image.metadata.author {
+        println!("{}: {}", "Author".bright_yellow(), author);
+    }
+    
+    if let Some(ref title) = image.metadata.title {
+        println!("{}: {}", "Title".bright_yellow(), title);
+    }
+    
+    if let Some(ref description) = image.metadata.description {
+        println!("{}: {}", "Description".bright_yellow(), description);
+    }
+    
+    if let Some(ref copyright) = image.metadata.copyright {
+        println!("{}: {}", "Copyright".bright_yellow(), copyright);
+    }
+    
+    if let Some(ref camera) = image.metadata.camera_model {
+        println!("{}: {}", "Camera Model".bright_yellow(), camera);
+    }
+    
+    if let Some(exposure) = image.metadata.exposure_time {
+        println!("{}: {}s", "Exposure Time".bright_yellow(), exposure);
+    }
+    
+    if let Some(iso) = image.metadata.iso {
+        println!("{}: {}", "ISO".bright_yellow(), iso);
+    }
+    
+    if let Some(f_number) = image.metadata.f_number {
+        println!("{}: f/{:.1}", "F-Number".bright_yellow(), f_number);
+    }
+    
+    if let Some(focal_length) = image.metadata.focal_length {
+        println!("{}: {}mm", "Focal Length".bright_yellow(), focal_length);
+    }
+    
+    if !image.metadata.custom_fields.is_empty() {
+        println!("\n{}", "Custom Fields:".bright_cyan().bold());
+        for (key, value) in &image.metadata.custom_fields {
+            println!("{}: {}", key.bright_yellow(), value);
+        }
+    }
+}
+
+/// Displays the size a converted image would occupy on disk.
+fn display_size_estimate(image: &CustomImage) -> Result<(), CliError> {
+    let raw_size = image.width as usize * image.height as usize * image.color_type.channels() as usize;
+    let file_size = image.to_bytes()?.len();
+    println!("\n{}", "Size Estimate:".bright_cyan().bold());
+    println!("{}", "--------------".bright_cyan());
+    println!("{}: {}x{}", "Dimensions".bright_yellow(), image.width, image.height);
+    println!("{}: {:?}", "Color Type".bright_yellow(), image.color_type);
+    println!("{}: {:?}", "Compression".bright_yellow(), image.compression);
+    println!("{}: {} bytes", "Raw Size".bright_yellow(), raw_size);
+    println!("{}: {} bytes", "Output Size".bright_yellow(), file_size);
+    println!("{}: {:.2}x", "Compression Ratio".bright_yellow(), raw_size as f64 / file_size as f64);
+    Ok(())
+}
+
+/// Prints the recorded time for each conversion stage and their total.
+fn display_timings(timings: &StageTimings) {
+    let stages = timings.stages();
+    println!("\n{}", "Stage Timings:".bright_cyan().bold());
+    println!("{}", "--------------".bright_cyan());
+    for (stage, elapsed) in &stages {
+        println!("{:<18} {:>10.2} ms", stage.bright_yellow(), elapsed.as_secs_f64() * 1000.0);
+    }
+    let total: f64 = stages.iter().map(|(_, elapsed)| elapsed.as_secs_f64()).sum();
+    println!("{:<18} {:>10.2} ms", "total".bright_yellow().bold(), total * 1000.0);
+}
+
+/// Collects the PNG files in `dir`, and in its subdirectories when
+/// `recursive` is set, in sorted order. Directories or entries that cannot be
+/// read are skipped with a warning.
+fn collect_pngs(dir: &Path, recursive: bool, files: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!("Skipping unreadable directory {}: {}", dir.display(), e);
+            return;
+        }
+    };
+    let mut paths = Vec::new();
+    for entry in entries {
+        match entry.and_then(|entry| Ok((entry.path(), entry.file_type()?.is_dir()))) {
+            Ok(path) => paths.push(path),
+            Err(e) => warn!("Skipping unreadable entry in {}: {}", dir.display(), e),
+        }
+    }
+    paths.sort();
+    for (path, is_dir) in paths {
+        if is_dir {
+            if recursive {
+                collect_pngs(&path, recursive, files);
+            }
+        } else if has_png_extension(&path) {
+            files.push(path);
+        }
+    }
+}
+
+/// Returns whether `path` ends in `.png`, ignoring case.
+fn has_png_extension(path: &Path) -> bool {
+    path.extension().and_then(|ext| ext.to_str()).is_some_and(|ext| ext.eq_ignore_ascii_case("png"))
+}
+
+/// Returns `path`, or `path` with `-1`, `-2`, ... appended to its file stem
+/// if an earlier file in the batch already claimed it, and claims the result.
+///
+/// Paths are compared case-insensitively, so `a.png` and `a.PNG` do not
+/// overwrite each other on case-insensitive file systems.
+fn claim_output(path: PathBuf, taken: &mut HashSet<String>) -> PathBuf {
+    let stem = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+    let mut candidate = path.clone();
+    let mut suffix = 0;
+    while !taken.insert(candidate.to_string_lossy().to_lowercase()) {
+        suffix += 1;
+        candidate = path.with_file_name(format!("{}-{}.nor", stem, suffix));
+    }
+    candidate
+}
+
+/// Converts every PNG in `input_dir` to `.nor` under `output_dir`, mirroring
+/// subdirectories when `recursive` is set.
+fn run_batch(
+    input_dir: &Path,
+    output_dir: &Path,
+    recursive: bool,
+    show_progress: bool,
+    config: &ConversionConfig,
+) -> Result<(), CliError> {
+    if !input_dir.is_dir() {
+        return Err(CliError::InvalidInput(format!("{} is not a directory", input_dir.display())));
+    }
+    let mut inputs = Vec::new();
+    collect_pngs(input_dir, recursive, &mut inputs);
+    println!("\n{} {} PNG files in {}", "Found".bright_cyan().bold(), inputs.len(), input_dir.display());
+
+    let mut taken = HashSet::new();
+    let jobs = inputs
+        .into_iter()
+        .map(|input| {
+            let relative = input.strip_prefix(input_dir).unwrap_or(&input);
+            let output = claim_output(output_dir.join(relative).with_extension("nor"), &mut taken);
+            (input, output)
+        })
+        .collect::<Vec<_>>();
+    convert_batch(&jobs, 0, show_progress, config)
+}
+
+/// Converts the PNG paths read from stdin, one per line, to `.nor` files
+/// named after them in `output_dir`.
+///
+/// Blank lines are ignored. Lines naming a missing file or a non-PNG are
+/// skipped with a warning that gives the line number.
+fn run_batch_from_stdin(output_dir: &Path, show_progress: bool, config: &ConversionConfig) -> Result<(), CliError> {
+    let mut taken = HashSet::new();
+    let mut jobs = Vec::new();
+    let mut skipped = 0;
+    for (index, line) in io::stdin().lock().lines().enumerate() {
+        let line_number = index + 1;
+        let line = match line {
+            Ok(line) => line,
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                skipped += 1;
+                warn!("Skipping line {}: {}", line_number, e);
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let input = PathBuf::from(line);
+        if !input.is_file() {
+            skipped += 1;
+            warn!("Skipping line {}: {} is not a file", line_number, input.display());
+            continue;
+        }
+        if !has_png_extension(&input) {
+            skipped += 1;
+            warn!("Skipping line {}: {} is not a .png file", line_number, input.display());
+            continue;
+        }
+        let name = input.file_name().map(PathBuf::from).unwrap_or_default();
+        let output = claim_output(output_dir.join(name).with_extension("nor"), &mut taken);
+        jobs.push((input, output));
+    }
+    println!("\n{} {} PNG paths from stdin", "Read".bright_cyan().bold(), jobs.len());
+    convert_batch(&jobs, skipped, show_progress, config)
+}
+
+/// Outcome of converting one file in a batch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BatchOutcome {
+    Converted,
+    Skipped,
+    Failed,
+}
+
+/// Converts each `(input, output)` pair in parallel, creating output
+/// directories as needed, then prints a summary that also counts `skipped`
+/// inputs rejected before conversion.
+///
+/// Unreadable files and existing outputs are skipped with a warning. Other
+/// failures are logged and make the batch fail once every file was tried.
+fn convert_batch(
+    jobs: &[(PathBuf, PathBuf)],
+    skipped: usize,
+    show_progress: bool,
+    config: &ConversionConfig,
+) -> Result<(), CliError> {
+    let bar = show_progress.then(|| {
+        let bar = PROGRESS_BARS.add(ProgressBar::new(jobs.len() as u64));
+        bar.set_style(
+            ProgressStyle::with_template("  {prefix} [{bar:20}] {pos}/{len} files")
+                .expect("progress template is valid")
+                .progress_chars("#> "),
+        );
+        bar.set_prefix("Progress:".bright_yellow().to_string());
+        bar
+    });
+    #[cfg(feature = "parallel")]
+    let pending = jobs.par_iter();
+    #[cfg(not(feature = "parallel"))]
+    let pending = jobs.iter();
+    let outcomes: Vec<BatchOutcome> = pending
+        .map(|(input, output)| {
+            let result = match output.parent() {
+                Some(parent) => fs::create_dir_all(parent).map_err(ConversionError::from),
+                None => Ok(()),
+            }
+            .and_then(|()| png_to_custom(input.as_path(), Some(output.as_path()), Some(config.clone())));
+            let outcome = match result {
+                Ok(_) => {
+                    println!("{} {} -> {}", "✓".bright_green(), input.display(), output.display());
+                    BatchOutcome::Converted
+                }
+                Err(e @ ConversionError::ImageError(image::ImageError::IoError(_)))
+                | Err(e @ ConversionError::OutputExists(_)) => {
+                    warn!("Skipping {}: {}", input.display(), e);
+                    BatchOutcome::Skipped
+                }
+                Err(e) => {
+                    error!("Failed to convert {}: {}", input.display(), e);
+                    BatchOutcome::Failed
+                }
+            };
+            if let Some(bar) = &bar {
+                bar.inc(1);
+            }
+            outcome
+        })
+        .collect();
+    if let Some(bar) = &bar {
+        bar.finish();
+    }
+
+    let count = |outcome| outcomes.iter().filter(|&&o| o == outcome).count();
+    let failed = count(BatchOutcome::Failed);
+    println!(
+        "\n{} {} converted, {} skipped, {} failed",
+        "Batch complete:".bright_cyan().bold(),
+        count(BatchOutcome::Converted),
+        skipped + count(BatchOutcome::Skipped),
+        failed
+    );
+    if failed > 0 {
+        return Err(CliError::Other(format!("{} of {} files failed to convert", failed, jobs.len()).into()));
+    }
+    Ok(())
+}
+
+/// Quality metrics of one image against another, as printed by
+/// `compare --json`.
+#[derive(Serialize)]
+struct CompareReport<'a> {
+    reference: &'a str,
+    test: &'a str,
+    width: u32,
+    height: u32,
+    /// File sizes on disk, in bytes.
+    reference_bytes: u64,
+    test_bytes: u64,
+    mse: f64,
+    /// In dB; `null` when the images are identical.
+    psnr: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ssim: Option<f64>,
+}
+
+/// Results of `bench --json`: one entry per compression method.
+#[derive(Serialize)]
+struct BenchReport<'a> {
+    input: &'a str,
+    grayscale: bool,
+    width: u32,
+    height: u32,
+    methods: Vec<BenchResult>,
+}
+
+/// Size, timings and quality of one compression method in `bench`.
+#[derive(Serialize)]
+struct BenchResult {
+    method: CompressionType,
+    /// Serialized file size, in bytes.
+    size: usize,
+    /// Size of the uncompressed file divided by `size`.
+    ratio: f64,
+    /// Full `png_to_custom` conversion, including PNG decoding.
+    encode_ms: f64,
+    /// Parsing and decompressing the serialized file.
+    decode_ms: f64,
+    /// In dB against the uncompressed result; only for lossy compression.
+    /// Serialized as `null` when the result is exact (infinite PSNR).
+    psnr: Option<f64>,
+}
+
+/// Runs `png_to_custom` with every compression method and prints a table of
+/// file sizes, encode/decode timings, and (for lossy) PSNR, or the same
+/// results as JSON.
+fn run_benchmark(input: &str, grayscale: bool, json: bool) -> Result<(), CliError> {
+    let mut reference: Option<CustomImage> = None;
+    let mut rows = Vec::new();
+    let mut dimensions = (0, 0);
+
+    for &method in CompressionType::all() {
+        let config = ConversionConfig {
+            force_grayscale: grayscale,
+            compression: method,
+            use_cache: false,
+            ..ConversionConfig::default()
+        };
+
+        let start = Instant::now();
+        let image = png_to_custom(input, None, Some(config))?;
+        let bytes = image.to_bytes()?;
+        let encode_time = start.elapsed();
+
+        let start = Instant::now();
+        let mut decoded = CustomImage::from_bytes(&bytes)?;
+        ParallelImageProcessor::decompress(&mut decoded)?;
+        let decode_time = start.elapsed();
+
+        let psnr = match (&reference, method) {
+            (Some(reference), CompressionType::Lossy) => {
+                Some(metrics::compare(reference, &decoded, false)?.psnr)
+            }
+            _ => None,
+        };
+        dimensions = (decoded.width, decoded.height);
+        if method == CompressionType::None {
+            reference = Some(decoded);
+        }
+        rows.push(BenchResult {
+            method,
+            size: bytes.len(),
+            ratio: 0.0,
+            encode_ms: encode_time.as_secs_f64() * 1000.0,
+            decode_ms: decode_time.as_secs_f64() * 1000.0,
+            psnr,
+        });
+    }
+
+    // The first method is `None`, so its size is the uncompressed baseline.
+    let raw_size = rows[0].size as f64;
+    for row in &mut rows {
+        row.ratio = raw_size / row.size as f64;
+    }
+
+    if json {
+        let report = BenchReport {
+            input,
+            grayscale,
+            width: dimensions.0,
+            height: dimensions.1,
+            methods: rows,
+        };
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    println!("\n{}", "Compression Benchmark:".bright_cyan().bold());
+    println!(
+        "{:<9} {:>12} {:>8} {:>12} {:>12} {:>10}",
+        "Method", "Size (B)", "Ratio", "Encode (ms)", "Decode (ms)", "PSNR (dB)"
+    );
+    for row in rows {
+        println!(
+            "{:<9} {:>12} {:>8.2} {:>12.2} {:>12.2} {:>10}",
+            format!("{:?}", row.method),
+            row.size,
+            row.ratio,
+            row.encode_ms,
+            row.decode_ms,
+            row.psnr.map_or("-".to_string(), |p| format!("{:.2}", p))
+        );
+    }
+    Ok(())
+}
+
+/// Supported compression methods and color types, as printed by
+/// `capabilities --json`.
+#[derive(Serialize)]
+struct Capabilities {
+    compressions: Vec<Capability>,
+    color_types: Vec<Capability>,
+}
+
+/// One compression method or color type in `capabilities`.
+#[derive(Serialize)]
+struct Capability {
+    name: &'static str,
+    /// Value of the format byte that identifies it in a file header.
+    id: u8,
+    description: &'static str,
+    /// Only set for color types.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    channels: Option<u32>,
+}
+
+/// Prints every supported compression method and color type, or the same
+/// list as JSON.
+fn print_capabilities(json: bool) -> Result<(), CliError> {
+    let capabilities = Capabilities {
+        compressions: CompressionType::all()
+            .iter()
+            .map(|&c| Capability { name: c.name(), id: c as u8, description: c.description(), channels: None })
+            .collect(),
+        color_types: ColorType::all()
+            .iter()
+            .map(|&c| Capability {
+                name: c.name(),
+                id: c as u8,
+                description: c.description(),
+                channels: Some(c.channels()),
+            })
+            .collect(),
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&capabilities)?);
+        return Ok(());
+    }
+
+    println!("{}", "Compression methods:".bright_cyan().bold());
+    for c in &capabilities.compressions {
+        println!("  {:<12} {}", c.name.bright_yellow(), c.description);
+    }
+    println!("\n{}", "Color types:".bright_cyan().bold());
+    for c in &capabilities.color_types {
+        println!(
+            "  {:<12} {} ({} channel{})",
+            c.name.bright_yellow(),
+            c.description,
+            c.channels.unwrap_or(0),
+            if c.channels == Some(1) { "" } else { "s" }
+        );
+    }
+    Ok(())
+}
+
+/// Minimum PSNR, in dB, a lossy round trip must reach in `selftest`.
+const SELFTEST_MIN_PSNR: f64 = 30.0;
+
+/// Builds a test image whose pixels follow a fixed pattern, with metadata
+/// dated at the epoch so the serialized bytes never change between runs.
+fn test_vector(width: u32, height: u32, color_type: ColorType) -> Result<CustomImage, FormatError> {
+    let channels = color_type.channels();
+    let data = (0..height)
+        .flat_map(|y| (0..width).flat_map(move |x| (0..channels).map(move |c| (x * 31 + y * 17 + c * 71) as u8)))
+        .collect();
+    let metadata = ImageMetadata { creation_date: 0, ..ImageMetadata::default() };
+    CustomImage::new(width, height, color_type, data, Some(metadata), CompressionType::None)
+}
+
+/// Returns `image` with its pixel data encoded with `compression`.
+fn compressed(image: &CustomImage, compression: CompressionType) -> Result<CustomImage, FormatError> {
+    Ok(CustomImage { data: image.compress(compression)?, compression, ..image.clone() })
+}
+
+/// Builds a smooth gradient, so lossy round trips are judged on the kind of
+/// content the codec is meant for rather than on noise.
+fn selftest_image(color_type: ColorType) -> Result<CustomImage, FormatError> {
+    const WIDTH: u32 = 64;
+    const HEIGHT: u32 = 48;
+    let channels = color_type.channels();
+    let data = (0..HEIGHT)
+        .flat_map(|y| {
+            (0..WIDTH).flat_map(move |x| {
+                (0..channels).map(move |c| (x * 2 + y * 2 + c * 16) as u8)
+            })
+        })
+        .collect();
+    CustomImage::new(WIDTH, HEIGHT, color_type, data, None, CompressionType::None)
+}
+
+/// Encodes `image` with `compression`, serializes and parses it, then
+/// decodes it again. Returns the PSNR against the original for lossy
+/// compression, or `None` after checking the pixels match exactly.
+fn round_trip(image: &CustomImage, compression: CompressionType) -> Result<Option<f64>, CliError> {
+    let bytes = compressed(image, compression)?.to_bytes()?;
+    let mut decoded = CustomImage::from_bytes(&bytes)?;
+    if decoded.compression != compression {
+        return Err(CliError::Other(
+            format!("compression read back as {:?}", decoded.compression).into(),
+        ));
+    }
+    ParallelImageProcessor::decompress(&mut decoded)?;
+    if (decoded.width, decoded.height, decoded.color_type) != (image.width, image.height, image.color_type) {
+        return Err(CliError::Other("header changed in the round trip".into()));
+    }
+
+    if compression == CompressionType::Lossy {
+        let psnr = metrics::compare(image, &decoded, false)?.psnr;
+        if psnr < SELFTEST_MIN_PSNR {
+            return Err(CliError::Other(
+                format!("PSNR {:.2} dB is below {:.0} dB", psnr, SELFTEST_MIN_PSNR).into(),
+            ));
+        }
+        Ok(Some(psnr))
+    } else if decoded.data != image.data {
+        let first = decoded.data.iter().zip(&image.data).position(|(a, b)| a != b);
+        Err(CliError::Other(match first {
+            Some(i) => format!("pixel data differs at byte {}", i),
+            None => format!("decoded {} bytes, expected {}", decoded.data.len(), image.data.len()),
+        }.into()))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Runs `selftest`, printing one line per color type and compression.
+///
+/// Fails if any combination does not round-trip: lossless methods must
+/// reproduce the pixels exactly and lossy must reach `SELFTEST_MIN_PSNR`.
+fn run_selftest() -> Result<(), CliError> {
+    let mut failed = 0;
+    let mut total = 0;
+    for &color_type in ColorType::all() {
+        let image = selftest_image(color_type)?;
+        for &compression in CompressionType::all() {
+            total += 1;
+            let label = format!("{:?} / {:?}", color_type, compression);
+            match round_trip(&image, compression) {
+                Ok(None) => println!("{} {:<22} exact", "✓".bright_green(), label),
+                Ok(Some(psnr)) => println!("{} {:<22} {:.2} dB", "✓".bright_green(), label, psnr),
+                Err(e) => {
+                    failed += 1;
+                    println!("{} {:<22} {}", "✗".bright_red(), label, e);
+                }
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(CliError::Other(format!("{} of {} self-test combinations failed", failed, total).into()));
+    }
+    println!("{} All {} combinations passed", "✓".bright_green(), total);
+    Ok(())
+}
+
+/// Writes the regression fixtures for `gen-test-vectors` and returns how
+/// many files were written.
+///
+/// Covers every color type with every compression, tiny and odd sizes,
+/// empty and rich metadata, the optional layouts (tiled, pyramid, no
+/// checksum, footer), and a few deliberately corrupted files whose names
+/// start with `corrupt-`.
+fn generate_test_vectors(dir: &Path) -> Result<usize, CliError> {
+    let mut vectors: Vec<(String, Vec<u8>)> = Vec::new();
+
+    for &color_type in ColorType::all() {
+        let image = test_vector(16, 16, color_type)?;
+        for &compression in CompressionType::all() {
+            let name = format!("{:?}-{:?}", color_type, compression).to_lowercase();
+            vectors.push((name, compressed(&image, compression)?.to_bytes()?));
+        }
+    }
+
+    for (width, height) in [(1, 1), (7, 5), (33, 1), (1, 33)] {
+        let image = test_vector(width, height, ColorType::Rgb)?;
+        vectors.push((format!("rgb-{}x{}", width, height), image.to_bytes()?));
+        let name = format!("rgb-{}x{}-rle", width, height);
+        vectors.push((name, compressed(&image, CompressionType::RLE)?.to_bytes()?));
+    }
+
+    let mut empty = test_vector(8, 8, ColorType::Gray)?;
+    empty.metadata = empty.metadata.stripped(false);
+    vectors.push(("metadata-empty".to_string(), empty.to_bytes()?));
+
+    let mut rich = test_vector(8, 8, ColorType::Rgb)?;
+    rich.metadata = ImageMetadata {
+        creation_date: 1_700_000_000,
+        author: Some("nor-image test vectors".to_string()),
+        title: Some("Test pattern".to_string()),
+        description: Some("Gradient used to check metadata round trips".to_string()),
+        copyright: Some("public domain".to_string()),
+        camera_model: Some("Pattern Generator".to_string()),
+        exposure_time: Some(0.008),
+        iso: Some(200),
+        f_number: Some(2.8),
+        focal_length: Some(35.0),
+        custom_fields: [("caption", "ünïcödé ✓")]
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect(),
+    };
+    vectors.push(("metadata-rich".to_string(), rich.to_bytes()?));
+
+    let base = test_vector(37, 29, ColorType::Rgb)?;
+    vectors.push(("tiled-16-rle".to_string(), base.tiled(16, CompressionType::RLE)?.to_bytes()?));
+    let mut pyramid = compressed(&base, CompressionType::Delta)?;
+    pyramid.set_pyramid(&base.build_pyramid(2)?)?;
+    vectors.push(("pyramid-2-delta".to_string(), pyramid.to_bytes()?));
+    let mut unchecked = base.clone();
+    unchecked.checksummed = false;
+    vectors.push(("no-checksum".to_string(), unchecked.to_bytes()?));
+    let mut footer = base.clone();
+    footer.footer = true;
+    vectors.push(("footer".to_string(), footer.to_bytes()?));
+
+    // Checksummed files fail verification before their header is parsed, so
+    // the header corruptions start from the unchecked file instead.
+    let valid = base.to_bytes()?;
+    let mut flipped = valid.clone();
+    let last_pixel = valid.len() - 33;
+    flipped[last_pixel] ^= 0xFF;
+    vectors.push(("corrupt-checksum".to_string(), flipped));
+    vectors.push(("corrupt-truncated".to_string(), valid[..valid.len() / 2].to_vec()));
+    let unchecked = unchecked.to_bytes()?;
+    vectors.push(("corrupt-header-truncated".to_string(), unchecked[..10].to_vec()));
+    let mut bad_magic = unchecked.clone();
+    bad_magic[..4].copy_from_slice(b"XIMG");
+    vectors.push(("corrupt-magic".to_string(), bad_magic));
+    let mut bad_version = unchecked;
+    bad_version[4] = 99;
+    vectors.push(("corrupt-version".to_string(), bad_version));
+
+    fs::create_dir_all(dir)?;
+    for (name, bytes) in &vectors {
+        fs::write(dir.join(format!("{}.nor", name)), bytes)?;
+    }
+    Ok(vectors.len())
+}
+
+lazy_static::lazy_static! {
+    /// Progress bars currently on screen; log records are printed above them.
+    static ref PROGRESS_BARS: MultiProgress = MultiProgress::new();
+}
+
+/// Logger that hides the progress bars while writing a record, so log lines
+/// and bars never overwrite each other.
+struct ProgressAwareLogger(env_logger::Logger);
+
+impl Log for ProgressAwareLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.0.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if self.0.matches(record) {
+            PROGRESS_BARS.suspend(|| self.0.log(record));
+        }
+    }
+
+    fn flush(&self) {
+        self.0.flush();
+    }
+}
+
+/// Builds a progress callback that draws a bar advancing once per
+/// conversion stage.
+fn progress_bar() -> ProgressCallback {
+    let bar = PROGRESS_BARS.add(ProgressBar::new(100));
+    bar.set_style(
+        ProgressStyle::with_template("  {prefix} [{bar:20}] {pos:>3}%")
+            .expect("progress template is valid")
+            .progress_chars("#> "),
+    );
+    bar.set_prefix("Progress:".bright_yellow().to_string());
+    ProgressCallback::new(move |fraction| {
+        bar.set_position((fraction * 100.0).round() as u64);
+        if fraction >= 1.0 {
+            bar.finish();
+        }
+    })
+}
+
+/// Asks before replacing an existing output file in interactive mode.
+///
+/// Returns `true` if the path is free or the user agreed to overwrite it.
+#[cfg(feature = "interactive")]
+fn confirm_overwrite(theme: &ColorfulTheme, output: &str) -> io::Result<bool> {
+    if !Path::new(output).exists() {
+        return Ok(true);
+    }
+    Confirm::with_theme(theme)
+        .with_prompt(format!("{} already exists. Overwrite it?", output))
+        .default(false)
+        .interact()
+}
+
+/// Turns a prompted adjustment into a config value, leaving it unset when
+/// the prompt returned the unset default of 0 so a preset can fill it in.
+#[cfg(feature = "interactive")]
+fn prompted_adjustment(value: i32, default: Option<i32>) -> Option<i32> {
+    (value != 0 || default.is_some()).then_some(value)
+}
+
+/// Loads conversion defaults from the config file, if any.
+///
+/// Precedence is: built-in defaults < config file < command-line flags. An
+/// explicit `--config` path must exist; otherwise `nor-image.toml` in the
+/// working directory is used when present.
+fn load_config(path: Option<&str>) -> Result<ConversionConfig, CliError> {
+    match path {
+        Some(path) => Ok(ConversionConfig::from_toml_file(path)?),
+        None if Path::new(DEFAULT_CONFIG_FILE).exists() => {
+            Ok(ConversionConfig::from_toml_file(DEFAULT_CONFIG_FILE)?)
+        }
+        None => Ok(ConversionConfig::default()),
+    }
+}
+
+/// Applies the resize flags on top of the config file's resize settings.
+///
+/// `--scale` replaces a configured width/height and vice versa, since the
+/// two cannot be combined.
+fn apply_resize_flags(config: &mut ConversionConfig, width: Option<u32>, height: Option<u32>, scale: Option<f32>) {
+    if scale.is_some() {
+        config.scale = scale;
+        config.resize_width = None;
+        config.resize_height = None;
+    } else if width.is_some() || height.is_some() {
+        config.scale = None;
+        config.resize_width = width.or(config.resize_width);
+        config.resize_height = height.or(config.resize_height);
     }
 }
 
-/// Displays metadata of a custom image in a formatted way.
-fn display_metadata(image: &CustomImage) {
-    println!("\n{}", "Image Information:".bright_cyan().bold());
-    println!("{}", "-------------------".bright_cyan());
-    println!("{}: {}x{}", "Dimensions".bright_yellow(), image.width, image.height);
-    println!("{}: {:?}", "Color Type".bright_yellow(), image.color_type);
-    println!("{}: {:?}", "Compression".bright_yellow(), image.compression);
-    
-    println!("\n{}", "Metadata:".bright_cyan().bold());
-    println!("{}: {}", "Creation Date".bright_yellow(), image.metadata.creation_date);
-    
-    if let Some(ref author) = image.metadata.author {
-        println!("{}: {}", "Author".bright_yellow(), author);
-    }
-    
-    if let Some(ref camera) = image.metadata.camera_model {
-        println!("{}: {}", "Camera Model".bright_yellow(), camera);
-    }
-    
-    if let Some(exposure) = image.metadata.exposure_time {
-        println!("{}: {}s", "Exposure Time".bright_yellow(), exposure);
-    }
-    
-    if let Some(iso) = image.metadata.iso {
-        println!("{}: {}", "ISO".bright_yellow(), iso);
-    }
-    
-    if let Some(f_number) = image.metadata.f_number {
-        println!("{}: f/{:.1}", "F-Number".bright_yellow(), f_number);
+/// Loads a `.nor` file for export through the image cache, decompressed.
+///
+/// An exact width and height is loaded already resized (and cached that
+/// way), and cleared from `config` so the export does not resize again.
+#[cfg(feature = "cache")]
+fn load_for_export(input: &str, config: &mut ConversionConfig) -> Result<std::sync::Arc<CustomImage>, ConversionError> {
+    let target = config.resize_width.zip(config.resize_height).filter(|_| config.scale.is_none());
+    let image = match target {
+        Some((width, height)) => {
+            config.resize_width = None;
+            config.resize_height = None;
+            CachedImageLoader::load_resized(input, width, height, image::imageops::FilterType::Lanczos3)?
+        }
+        None => CachedImageLoader::load_decompressed(input)?,
+    };
+    Ok(image)
+}
+
+/// Applies `--chunk-size`, given in megabytes, on top of the config file's
+/// chunk size in bytes.
+fn apply_chunk_size_flag(config: &mut ConversionConfig, megabytes: Option<u32>) {
+    if let Some(megabytes) = megabytes {
+        config.chunk_size = (megabytes as usize).saturating_mul(1024 * 1024);
     }
-    
-    if let Some(focal_length) = image.metadata.focal_length {
-        println!("{}: {}mm", "Focal Length".bright_yellow(), focal_length);
+}
+
+/// Applies the watermark flags on top of the config file's watermark.
+///
+/// `--watermark-text` or `--watermark-image` replaces the configured content
+/// but keeps its position and opacity; those can be overridden separately.
+fn apply_watermark_flags(
+    config: &mut ConversionConfig,
+    text: Option<String>,
+    image: Option<PathBuf>,
+    position: Option<WatermarkCorner>,
+    opacity: Option<f32>,
+) {
+    let content = text.map(WatermarkContent::Text).or(image.map(WatermarkContent::Image));
+    if let Some(content) = content {
+        config.watermark = Some(match config.watermark.take() {
+            Some(existing) => Watermark { content, ..existing },
+            None => Watermark::new(content),
+        });
     }
-    
-    if !image.metadata.custom_fields.is_empty() {
-        println!("\n{}", "Custom Fields:".bright_cyan().bold());
-        for (key, value) in &image.metadata.custom_fields {
-            println!("{}: {}", key.bright_yellow(), value);
+    if let Some(watermark) = config.watermark.as_mut() {
+        if let Some(position) = position {
+            watermark.position = position.into();
         }
+        watermark.opacity = opacity.unwrap_or(watermark.opacity);
     }
 }
 
+/// Formats an optional dimension as prefilled prompt text.
+#[cfg(feature = "interactive")]
+fn initial_dimension(value: Option<u32>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
 /// Runs the interactive mode using dialoguer prompts.
-fn interactive_mode() -> Result<(), Box<dyn Error>> {
+///
+/// Prompts are prefilled from `defaults`, which come from the config file.
+#[cfg(feature = "interactive")]
+fn interactive_mode(defaults: &ConversionConfig) -> Result<(), CliError> {
     let theme = ColorfulTheme::default();
     
     loop {
@@ -272,7 +1990,7 @@ fn interactive_mode() -> Result<(), Box<dyn Error>> {
         
         let choices = &[
             "🔄 Convert PNG to custom (.nor)",
-            "🔄 Convert custom (.nor) to PNG",
+            "🔄 Convert custom (.nor) to PNG or WebP",
             "👁  View a .nor image",
             "ℹ️  Display image metadata",
             "🗑  Clear cache",
@@ -302,14 +2020,24 @@ fn interactive_mode() -> Result<(), Box<dyn Error>> {
                     eprintln!("{}: {}", "Error".bright_red().bold(), e);
                     continue;
                 }
+                if !confirm_overwrite(&theme, &output)? {
+                    continue;
+                }
                 let grayscale: bool = Confirm::with_theme(&theme)
                     .with_prompt("Convert to grayscale?")
-                    .default(false)
+                    .default(defaults.force_grayscale)
                     .interact()?;
-                let compression_options = &["None", "RLE", "Delta", "Lossy"];
+                let compression_options = &["None", "RLE", "Delta", "Lossy", "RLE (varint counts)", "Auto (smallest lossless)"];
                 let comp_index = Select::with_theme(&theme)
                     .with_prompt("Select compression method")
-                    .default(0)
+                    .default(match defaults.compression {
+                        _ if defaults.auto_compression => 5,
+                        CompressionType::None => 0,
+                        CompressionType::RLE => 1,
+                        CompressionType::Delta => 2,
+                        CompressionType::Lossy => 3,
+                        CompressionType::RleVarint => 4,
+                    })
                     .items(compression_options)
                     .interact()?;
                 let compression = match comp_index {
@@ -317,10 +2045,13 @@ fn interactive_mode() -> Result<(), Box<dyn Error>> {
                     1 => CompressType::Rle,
                     2 => CompressType::Delta,
                     3 => CompressType::Lossy,
+                    4 => CompressType::RleVarint,
+                    5 => CompressType::Auto,
                     _ => CompressType::None,
                 };
                 let width_input: String = Input::with_theme(&theme)
                     .with_prompt("Enter target width (leave blank for unchanged)")
+                    .with_initial_text(initial_dimension(defaults.resize_width))
                     .allow_empty(true)
                     .interact_text()?;
                 let width = if width_input.trim().is_empty() {
@@ -336,6 +2067,7 @@ fn interactive_mode() -> Result<(), Box<dyn Error>> {
                 };
                 let height_input: String = Input::with_theme(&theme)
                     .with_prompt("Enter target height (leave blank for unchanged)")
+                    .with_initial_text(initial_dimension(defaults.resize_height))
                     .allow_empty(true)
                     .interact_text()?;
                 let height = if height_input.trim().is_empty() {
@@ -351,35 +2083,52 @@ fn interactive_mode() -> Result<(), Box<dyn Error>> {
                 };
                 let brightness: i32 = Input::with_theme(&theme)
                     .with_prompt("Enter brightness adjustment (-255 to 255)")
-                    .default(0)
+                    .default(defaults.brightness.unwrap_or(0))
                     .interact_text()?;
                 let contrast: i32 = Input::with_theme(&theme)
                     .with_prompt("Enter contrast adjustment (-255 to 255)")
-                    .default(0)
+                    .default(defaults.contrast.unwrap_or(0))
                     .interact_text()?;
                 let no_cache: bool = Confirm::with_theme(&theme)
                     .with_prompt("Disable caching?")
-                    .default(false)
+                    .default(!defaults.use_cache)
                     .interact()?;
 
-                let config = ConversionConfig {
+                let mut config = ConversionConfig {
                     resize_width: width,
                     resize_height: height,
-                    brightness,
-                    contrast,
+                    brightness: prompted_adjustment(brightness, defaults.brightness),
+                    contrast: prompted_adjustment(contrast, defaults.contrast),
                     force_grayscale: grayscale,
-                    compression: compression.into(),
                     use_cache: !no_cache,
+                    overwrite: true,
+                    progress: Some(progress_bar()),
+                    ..defaults.clone()
                 };
+                compression.apply_to(&mut config);
 
                 println!("\n{} {} to {}...", "Converting".bright_yellow(), input, output);
                 match png_to_custom(&input, Some(&output), Some(config)) {
-                    Ok(_) => println!("{} Successfully converted {} to {}", "✓".bright_green(), input, output),
+                    Ok(_) => {
+                        println!("{} Successfully converted {} to {}", "✓".bright_green(), input, output);
+                        let view_now = cfg!(feature = "viewer")
+                            && Confirm::with_theme(&theme)
+                                .with_prompt("View the result now?")
+                                .default(false)
+                                .interact()?;
+                        if view_now {
+                            // The output was just rewritten, so a cached
+                            // copy of it would be stale.
+                            if let Err(e) = view_custom_image(&output, false) {
+                                eprintln!("{} {}", "Error:".bright_red().bold(), e);
+                            }
+                        }
+                    }
                     Err(e) => eprintln!("{} {}", "Error:".bright_red().bold(), e),
                 }
             }
             1 => {
-                println!("\n{}", "NOR to PNG Conversion".bright_green().bold());
+                println!("\n{}", "NOR to PNG/WebP Conversion".bright_green().bold());
                 let input: String = Input::with_theme(&theme)
                     .with_prompt("Enter input .nor file path")
                     .interact_text()?;
@@ -388,14 +2137,21 @@ fn interactive_mode() -> Result<(), Box<dyn Error>> {
                     continue;
                 }
                 let output: String = Input::with_theme(&theme)
-                    .with_prompt("Enter output PNG file path")
+                    .with_prompt("Enter output PNG or WebP file path")
                     .interact_text()?;
-                if let Err(e) = validate_png_extension(&output) {
-                    eprintln!("{}: {}", "Error".bright_red().bold(), e);
+                let to_webp = validate_webp_extension(&output).is_ok();
+                if !to_webp {
+                    if let Err(e) = validate_png_extension(&output) {
+                        eprintln!("{}: {}", "Error".bright_red().bold(), e);
+                        continue;
+                    }
+                }
+                if !confirm_overwrite(&theme, &output)? {
                     continue;
                 }
                 let width_input: String = Input::with_theme(&theme)
                     .with_prompt("Enter target width (leave blank for unchanged)")
+                    .with_initial_text(initial_dimension(defaults.resize_width))
                     .allow_empty(true)
                     .interact_text()?;
                 let width = if width_input.trim().is_empty() {
@@ -411,6 +2167,7 @@ fn interactive_mode() -> Result<(), Box<dyn Error>> {
                 };
                 let height_input: String = Input::with_theme(&theme)
                     .with_prompt("Enter target height (leave blank for unchanged)")
+                    .with_initial_text(initial_dimension(defaults.resize_height))
                     .allow_empty(true)
                     .interact_text()?;
                 let height = if height_input.trim().is_empty() {
@@ -426,36 +2183,50 @@ fn interactive_mode() -> Result<(), Box<dyn Error>> {
                 };
                 let brightness: i32 = Input::with_theme(&theme)
                     .with_prompt("Enter brightness adjustment (-255 to 255)")
-                    .default(0)
+                    .default(defaults.brightness.unwrap_or(0))
                     .interact_text()?;
                 let contrast: i32 = Input::with_theme(&theme)
                     .with_prompt("Enter contrast adjustment (-255 to 255)")
-                    .default(0)
+                    .default(defaults.contrast.unwrap_or(0))
                     .interact_text()?;
 
-                match fs::read(&input) {
-                    Ok(bytes) => {
-                        match CustomImage::from_bytes(&bytes) {
-                            Ok(custom_img) => {
-                                let config = ConversionConfig {
-                                    resize_width: width,
-                                    resize_height: height,
-                                    brightness,
-                                    contrast,
-                                    force_grayscale: false,
-                                    compression: CompressionType::None,
-                                    use_cache: false,
-                                };
-                                println!("\n{} {} to {}...", "Converting".bright_yellow(), input, output);
-                                match custom_to_png(&custom_img, &output, Some(config)) {
-                                    Ok(_) => println!("{} Successfully converted {} to {}", "✓".bright_green(), input, output),
-                                    Err(e) => eprintln!("{} {}", "Error:".bright_red().bold(), e),
-                                }
-                            }
-                            Err(e) => eprintln!("{} Reading custom image: {}", "Error:".bright_red().bold(), e),
+                // Cached decompressed pixels make repeated exports of the
+                // same file in one session cheap; resized exports cache the
+                // resized pixels too.
+                let target = width.zip(height);
+                let loaded = match target {
+                    Some((width, height)) => {
+                        CachedImageLoader::load_resized(&input, width, height, image::imageops::FilterType::Lanczos3)
+                    }
+                    None => CachedImageLoader::load_decompressed(&input),
+                };
+                match loaded {
+                    Ok(custom_img) => {
+                        let config = ConversionConfig {
+                            resize_width: width.filter(|_| target.is_none()),
+                            resize_height: height.filter(|_| target.is_none()),
+                            scale: defaults.scale.filter(|_| target.is_none()),
+                            brightness: Some(brightness),
+                            contrast: Some(contrast),
+                            force_grayscale: false,
+                            compression: CompressionType::None,
+                            use_cache: false,
+                            overwrite: true,
+                            progress: Some(progress_bar()),
+                            ..defaults.clone()
+                        };
+                        println!("\n{} {} to {}...", "Converting".bright_yellow(), input, output);
+                        let result = if to_webp {
+                            custom_to_webp(&custom_img, &output, Some(config))
+                        } else {
+                            custom_to_png(&custom_img, &output, Some(config))
+                        };
+                        match result {
+                            Ok(_) => println!("{} Successfully converted {} to {}", "✓".bright_green(), input, output),
+                            Err(e) => eprintln!("{} {}", "Error:".bright_red().bold(), e),
                         }
                     }
-                    Err(e) => eprintln!("{} Reading file: {}", "Error:".bright_red().bold(), e),
+                    Err(e) => eprintln!("{} Reading custom image: {}", "Error:".bright_red().bold(), e),
                 }
             }
             2 => {
@@ -467,11 +2238,11 @@ fn interactive_mode() -> Result<(), Box<dyn Error>> {
                     eprintln!("{}: {}", "Error".bright_red().bold(), e);
                     continue;
                 }
-                let _use_cache: bool = Confirm::with_theme(&theme)
+                let use_cache: bool = Confirm::with_theme(&theme)
                     .with_prompt("Use cached version?")
                     .default(false)
                     .interact()?;
-                match view_custom_image(&input) {
+                match view_custom_image(&input, use_cache) {
                     Ok(_) => println!("{} Opened viewer for {}", "✓".bright_green(), input),
                     Err(e) => eprintln!("{} {}", "Error:".bright_red().bold(), e),
                 }
@@ -521,11 +2292,93 @@ fn interactive_mode() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+/// Error message of the `interactive` command in builds without the
+/// `interactive` feature.
+#[cfg(not(feature = "interactive"))]
+const NO_INTERACTIVE: &str = "built without interactive mode; rebuild with `--features interactive`, or run a subcommand (see --help)";
+
+/// Error message of the viewer commands in builds without the `viewer`
+/// feature.
+const NO_VIEWER: &str = "built without viewer support; rebuild with `--features viewer` to open images in a window";
+
+/// Fails with `NO_VIEWER` unless the `viewer` feature is enabled, so the
+/// viewer commands stop before doing any work.
+fn require_viewer() -> Result<(), CliError> {
+    if cfg!(feature = "viewer") {
+        Ok(())
+    } else {
+        Err(CliError::Other(NO_VIEWER.into()))
+    }
+}
+
+/// Stand-ins for the viewer entry points in builds without the `viewer`
+/// feature; each fails with `NO_VIEWER`.
+#[cfg(not(feature = "viewer"))]
+mod no_viewer {
+    use std::error::Error;
+
+    pub fn view_custom_image(_path: &str, _use_cache: bool) -> Result<(), Box<dyn Error>> {
+        Err(super::NO_VIEWER.into())
+    }
+
+    pub fn view_png_image(_path: &str) -> Result<(), Box<dyn Error>> {
+        Err(super::NO_VIEWER.into())
+    }
+
+    pub fn view_comparison(_left_path: &str, _right_path: &str) -> Result<(), Box<dyn Error>> {
+        Err(super::NO_VIEWER.into())
+    }
+}
+
+/// Stand-in for `colored` in builds without the `interactive` feature: every
+/// style returns the text unchanged, so all output is plain.
+#[cfg(not(feature = "interactive"))]
+mod no_colors {
+    use std::fmt::Display;
+
+    pub trait Colorize: Display + Sized {
+        fn bold(self) -> String {
+            self.to_string()
+        }
+        fn red(self) -> String {
+            self.to_string()
+        }
+        fn green(self) -> String {
+            self.to_string()
+        }
+        fn yellow(self) -> String {
+            self.to_string()
+        }
+        fn bright_red(self) -> String {
+            self.to_string()
+        }
+        fn bright_green(self) -> String {
+            self.to_string()
+        }
+        fn bright_yellow(self) -> String {
+            self.to_string()
+        }
+        fn bright_blue(self) -> String {
+            self.to_string()
+        }
+        fn bright_magenta(self) -> String {
+            self.to_string()
+        }
+        fn bright_cyan(self) -> String {
+            self.to_string()
+        }
+    }
+
+    impl<T: Display> Colorize for T {}
+}
+
 /// Main entry point.
-fn main() -> Result<(), Box<dyn Error>> {
+fn main() {
+    let cli = Cli::parse();
+
     // Initialize custom logging with full colored output.
-    Builder::new()
-        .filter_level(LevelFilter::Info)
+    let logger = Builder::new()
+        .filter_level(cli.log_level())
         .format(|buf, record: &Record| {
             let ts = buf.timestamp();
             let level = record.level();
@@ -536,132 +2389,714 @@ fn main() -> Result<(), Box<dyn Error>> {
                 Level::Debug => level.to_string().bright_blue().bold(),
                 Level::Trace => level.to_string().bright_magenta().bold(),
             };
-            writeln!(buf, "{} [{}] {}", ts.to_string(), level_str, record.args())
+            writeln!(buf, "{} [{}] {}", ts, level_str, record.args())
         })
-        .init();
+        .build();
+    log::set_max_level(logger.filter());
+    if log::set_boxed_logger(Box::new(ProgressAwareLogger(logger))).is_err() {
+        eprintln!("{} logger was already initialized", "Warning:".bright_yellow().bold());
+    }
 
-    let cli = Cli::parse();
+    if let Err(e) = run(cli) {
+        eprintln!("{} {}", "Error:".bright_red().bold(), e);
+        std::process::exit(e.exit_code());
+    }
+}
+
+/// Runs the parsed command.
+fn run(cli: Cli) -> Result<(), CliError> {
+    // Install the pool before any parallel work, since rayon's global pool
+    // can only be configured once.
+    #[cfg(feature = "parallel")]
+    {
+        if let Some(threads) = cli.threads {
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(threads as usize)
+                .build_global()
+                .map_err(|e| CliError::Other(Box::new(e)))?;
+        }
+        debug!("Using {} worker threads", rayon::current_num_threads());
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        if cli.threads.is_some() {
+            warn!("--threads has no effect in builds without the parallel feature");
+        }
+        debug!("Using 1 worker thread");
+    }
+    if let Some(limit) = cli.limit_memory {
+        format::set_max_pixel_bytes(limit);
+    }
+    let show_progress = cli.progress;
+    let mut defaults = load_config(cli.config.as_deref())?;
+    defaults.strict |= cli.strict;
 
     match cli.command {
         Some(Commands::PngToCustom {
             input,
             output,
             grayscale,
+            auto_grayscale,
+            grayscale_method,
             compression,
+            allow_lossy,
+            lossy_quality,
+            max_bytes,
+            tiled,
+            tile_size,
+            pyramid,
             width,
             height,
+            scale,
+            aspect,
             brightness,
             contrast,
+            brightness_r,
+            brightness_g,
+            brightness_b,
+            contrast_r,
+            contrast_g,
+            contrast_b,
+            contrast_curve,
+            preset,
+            median,
+            quantize,
+            seed,
+            awb,
+            watermark_text,
+            watermark_image,
+            watermark_position,
+            watermark_opacity,
+            background,
+            preserve_text,
+            to_srgb,
+            now,
             no_cache,
-            no_streaming: _,
-            chunk_size: _,
+            no_checksum,
+            footer,
+            reserve_metadata,
+            no_streaming,
+            chunk_size,
+            dry_run,
+            timings,
+            overwrite,
+            show_info,
         }) => {
-            validate_png_extension(&input)
-                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
-            validate_nor_extension(&output)
-                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+            validate_png_extension(&input).map_err(CliError::InvalidInput)?;
+            validate_nor_extension(&output).map_err(CliError::InvalidInput)?;
 
-            let config = ConversionConfig {
-                resize_width: width,
-                resize_height: height,
-                brightness,
-                contrast,
-                force_grayscale: grayscale,
-                compression: compression.into(),
-                use_cache: !no_cache,
-            };
+            // Command-line flags override values from the config file.
+            let mut config = defaults;
+            apply_resize_flags(&mut config, width, height, scale);
+            apply_chunk_size_flag(&mut config, chunk_size);
+            config.streaming &= !no_streaming;
+            config.crop_aspect = aspect.or(config.crop_aspect);
+            config.brightness = brightness.or(config.brightness);
+            config.contrast = contrast.or(config.contrast);
+            config.brightness_rgb = channel_values(config.brightness.unwrap_or(0), [brightness_r, brightness_g, brightness_b])
+                .or(config.brightness_rgb);
+            config.contrast_rgb = channel_values(config.contrast.unwrap_or(0), [contrast_r, contrast_g, contrast_b])
+                .or(config.contrast_rgb);
+            config.contrast_curve = contrast_curve.map_or(config.contrast_curve, Into::into);
+            config.force_grayscale |= grayscale;
+            config.auto_grayscale |= auto_grayscale;
+            config.grayscale_method = grayscale_method.unwrap_or(config.grayscale_method);
+            if grayscale_method.is_some() && !config.force_grayscale {
+                warn!("--grayscale-method has no effect without --grayscale");
+            }
+            if config.force_grayscale && (config.brightness_rgb.is_some() || config.contrast_rgb.is_some()) {
+                warn!("Per-channel brightness/contrast has no effect with --grayscale; the single values are used");
+            }
+            config.background_color = background.or(config.background_color);
+            if let Some(compression) = compression {
+                compression.apply_to(&mut config);
+            }
+            config.auto_allow_lossy |= allow_lossy;
+            config.lossy_quality = lossy_quality.unwrap_or(config.lossy_quality);
+            config.target_bytes = max_bytes.or(config.target_bytes);
+            config.overwrite |= overwrite;
+            config.preset = preset.map(Into::into).or(config.preset);
+            config.median_filter = median.or(config.median_filter);
+            config.quantize_colors = quantize.or(config.quantize_colors);
+            config.seed = seed.or(config.seed);
+            config.auto_white_balance |= awb;
+            if config.auto_white_balance && config.force_grayscale {
+                warn!("--awb has no effect with --grayscale");
+            }
+            apply_watermark_flags(&mut config, watermark_text, watermark_image, watermark_position, watermark_opacity);
+            config.tile_size = tile_size
+                .or(tiled.then_some(DEFAULT_TILE_SIZE))
+                .or(config.tile_size);
+            config.pyramid_levels = pyramid.or(config.pyramid_levels);
+            if no_cache {
+                config.use_cache = false;
+            }
+            config.skip_checksum = no_checksum;
+            config.footer_magic |= footer;
+            config.reserve_metadata = reserve_metadata.or(config.reserve_metadata);
+            config.preserve_text |= preserve_text;
+            config.to_srgb |= to_srgb;
+            config.creation_date_now |= now;
+            let stage_timings = StageTimings::new();
+            if timings {
+                config.timings = Some(stage_timings.clone());
+            }
             
             println!("\n{}", "Conversion Settings:".bright_cyan().bold());
             println!("  {} {}", "Input:".bright_yellow(), input);
             println!("  {} {}", "Output:".bright_yellow(), output);
-            println!("  {} {}", "Grayscale:".bright_yellow(), if grayscale { "yes" } else { "no" });
-            println!("  {} {:?}", "Compression:".bright_yellow(), compression);
-            if width.is_some() || height.is_some() {
+            if config.force_grayscale {
+                println!("  {} yes ({:?})", "Grayscale:".bright_yellow(), config.grayscale_method);
+            } else if config.auto_grayscale {
+                println!("  {} auto", "Grayscale:".bright_yellow());
+            } else {
+                println!("  {} no", "Grayscale:".bright_yellow());
+            }
+            if config.auto_compression {
+                println!("  {} Auto{}", "Compression:".bright_yellow(), if config.auto_allow_lossy { " (lossy allowed)" } else { "" });
+            } else {
+                println!("  {} {:?}", "Compression:".bright_yellow(), config.compression);
+            }
+            if let Some(target) = config.target_bytes {
+                println!("  {} {} bytes", "Max size:".bright_yellow(), target);
+            }
+            if let Some(preset) = config.preset {
+                println!("  {} {:?}", "Preset:".bright_yellow(), preset);
+            }
+            if let Some(radius) = config.median_filter.filter(|&radius| radius > 0) {
+                println!("  {} median, radius {}", "Denoise:".bright_yellow(), radius);
+            }
+            if let Some(colors) = config.quantize_colors {
+                println!("  {} {} colors (seed {})", "Quantize:".bright_yellow(), colors, config.rng_seed());
+            }
+            if config.auto_white_balance {
+                println!("  {} auto (gray world)", "White balance:".bright_yellow());
+            }
+            if let Some(watermark) = &config.watermark {
+                println!("  {} {:?} at {:?}, opacity {}", "Watermark:".bright_yellow(), watermark.content, watermark.position, watermark.opacity);
+            }
+            if let Some(tile_size) = config.tile_size {
+                println!("  {} {}x{}", "Tiles:".bright_yellow(), tile_size, tile_size);
+            }
+            if let Some(levels) = config.pyramid_levels {
+                println!("  {} {} levels", "Pyramid:".bright_yellow(), levels);
+            }
+            if let Some((w, h)) = config.crop_aspect {
+                println!("  {} {}:{} (centered)", "Crop:".bright_yellow(), w, h);
+            }
+            if let Some(scale) = config.scale {
+                println!("  {} {}%", "Resize:".bright_yellow(), scale);
+            } else if config.resize_width.is_some() || config.resize_height.is_some() {
                 println!(
                     "  {} {}x{}", 
                     "Resize:".bright_yellow(),
-                    width.map_or("unchanged".to_string(), |w| w.to_string()),
-                    height.map_or("unchanged".to_string(), |h| h.to_string())
+                    config.resize_width.map_or("unchanged".to_string(), |w| w.to_string()),
+                    config.resize_height.map_or("unchanged".to_string(), |h| h.to_string())
+                );
+            }
+            if config.adjusts() {
+                println!(
+                    "  {} brightness={}, contrast={} ({:?})",
+                    "Adjustments:".bright_yellow(),
+                    config.brightness_rgb.map_or(config.brightness.unwrap_or(0).to_string(), |rgb| format!("{:?}", rgb)),
+                    config.contrast_rgb.map_or(config.contrast.unwrap_or(0).to_string(), |rgb| format!("{:?}", rgb)),
+                    config.contrast_curve
                 );
             }
-            if brightness != 0 || contrast != 0 {
-                println!("  {} brightness={}, contrast={}", "Adjustments:".bright_yellow(), brightness, contrast);
+            if config.to_srgb {
+                println!("  {} convert ICC profiles to sRGB", "Color:".bright_yellow());
+            }
+            println!("  {} {}", "Caching:".bright_yellow(), if config.use_cache { "enabled" } else { "disabled" });
+            if config.skip_checksum {
+                println!("  {} {}", "Checksum:".bright_yellow(), "none (integrity is not verified on read)".bright_red());
             }
-            println!("  {} {}", "Caching:".bright_yellow(), if !no_cache { "enabled" } else { "disabled" });
             
-            println!("\n{} Converting...", "⚙️".bright_yellow());
-            match png_to_custom(&input, Some(&output), Some(config)) {
-                Ok(_) => println!("{} Successfully converted {} to {}", "✓".bright_green(), input, output),
-                Err(e) => {
-                    eprintln!("{} {}", "Error:".bright_red().bold(), e);
-                    return Err(Box::new(std::io::Error::new(std::io::ErrorKind::Other, e)));
+            if dry_run {
+                println!("\n{} Estimating (dry run, nothing will be written)...", "⚙️".bright_yellow());
+                let custom_img = png_to_custom(&input, None, Some(config))?;
+                display_size_estimate(&custom_img)?;
+                if timings {
+                    display_timings(&stage_timings);
                 }
+                return Ok(());
+            }
+
+            println!("\n{} Converting...", "⚙️".bright_yellow());
+            if show_progress {
+                config.progress = Some(progress_bar());
+            }
+            png_to_custom(&input, Some(&output), Some(config))?;
+            println!("{} Successfully converted {} to {}", "✓".bright_green(), input, output);
+            if timings {
+                display_timings(&stage_timings);
+            }
+            if show_info {
+                display_written_metadata(&output)?;
             }
         }
         Some(Commands::CustomToPng {
             input,
             output,
+            format,
             width,
             height,
+            scale,
             brightness,
             contrast,
-            no_streaming: _,
-            chunk_size: _,
+            contrast_curve,
+            png_compression,
+            preserve_text,
+            no_streaming,
+            chunk_size,
+            overwrite,
         }) => {
-            validate_nor_extension(&input)
-                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
-            validate_png_extension(&output)
-                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
-            
-            let bytes = fs::read(&input)?;
-            let custom_img = CustomImage::from_bytes(&bytes)?;
+            validate_nor_extension(&input).map_err(CliError::InvalidInput)?;
+            let format = match format {
+                Some(format) => format.into(),
+                None => ExportFormat::from_path(Path::new(&output))?,
+            };
             
-            let config = ConversionConfig {
-                resize_width: width,
-                resize_height: height,
-                brightness,
-                contrast,
+            // Command-line flags override values from the config file.
+            let mut config = ConversionConfig {
+                brightness: brightness.or(defaults.brightness),
+                contrast: contrast.or(defaults.contrast),
+                contrast_curve: contrast_curve.map_or(defaults.contrast_curve, Into::into),
                 force_grayscale: false,
                 compression: CompressionType::None,
                 use_cache: false,
+                png_compression: png_compression.map_or(defaults.png_compression, Into::into),
+                overwrite: overwrite || defaults.overwrite,
+                preserve_text: preserve_text || defaults.preserve_text,
+                streaming: !no_streaming && defaults.streaming,
+                ..defaults
             };
+            apply_resize_flags(&mut config, width, height, scale);
+            apply_chunk_size_flag(&mut config, chunk_size);
             
             println!("\n{}", "Conversion Settings:".bright_cyan().bold());
             println!("  {} {}", "Input:".bright_yellow(), input);
             println!("  {} {}", "Output:".bright_yellow(), output);
-            if width.is_some() || height.is_some() {
+            println!("  {} {:?}", "Format:".bright_yellow(), format);
+            if let Some(scale) = config.scale {
+                println!("  {} {}%", "Resize:".bright_yellow(), scale);
+            } else if config.resize_width.is_some() || config.resize_height.is_some() {
                 println!(
                     "  {} {}x{}", 
                     "Resize:".bright_yellow(),
-                    width.map_or("unchanged".to_string(), |w| w.to_string()),
-                    height.map_or("unchanged".to_string(), |h| h.to_string())
+                    config.resize_width.map_or("unchanged".to_string(), |w| w.to_string()),
+                    config.resize_height.map_or("unchanged".to_string(), |h| h.to_string())
                 );
             }
-            if brightness != 0 || contrast != 0 {
-                println!("  {} brightness={}, contrast={}", "Adjustments:".bright_yellow(), brightness, contrast);
+            if config.adjusts() {
+                println!(
+                    "  {} brightness={}, contrast={} ({:?})",
+                    "Adjustments:".bright_yellow(),
+                    config.brightness_rgb.map_or(config.brightness.unwrap_or(0).to_string(), |rgb| format!("{:?}", rgb)),
+                    config.contrast_rgb.map_or(config.contrast.unwrap_or(0).to_string(), |rgb| format!("{:?}", rgb)),
+                    config.contrast_curve
+                );
             }
             
             println!("\n{} Converting...", "⚙️".bright_yellow());
-            match custom_to_png(&custom_img, &output, Some(config)) {
-                Ok(_) => println!("{} Successfully converted {} to {}", "✓".bright_green(), input, output),
-                Err(e) => {
-                    eprintln!("{} {}", "Error:".bright_red().bold(), e);
-                    return Err(Box::new(std::io::Error::new(std::io::ErrorKind::Other, e)));
-                }
+            // Decompressed (or resized) pixels are cached, so repeated
+            // exports of the same file in one process decode it once.
+            #[cfg(feature = "cache")]
+            let custom_img = load_for_export(&input, &mut config)?;
+            #[cfg(not(feature = "cache"))]
+            let custom_img = read_custom(&input, &config)?;
+            if show_progress {
+                config.progress = Some(progress_bar());
+            }
+            custom_to_file(&custom_img, &output, Some(format), Some(config))?;
+            println!("{} Successfully converted {} to {}", "✓".bright_green(), input, output);
+        }
+        Some(Commands::CustomToWebp {
+            input,
+            output,
+            width,
+            height,
+            scale,
+            brightness,
+            contrast,
+            contrast_curve,
+            quality,
+            overwrite,
+        }) => {
+            validate_nor_extension(&input).map_err(CliError::InvalidInput)?;
+            validate_webp_extension(&output).map_err(CliError::InvalidInput)?;
+
+            let bytes = fs::read(&input)?;
+            let custom_img = CustomImage::from_bytes(&bytes)?;
+
+            // Command-line flags override values from the config file.
+            let mut config = ConversionConfig {
+                brightness: brightness.or(defaults.brightness),
+                contrast: contrast.or(defaults.contrast),
+                contrast_curve: contrast_curve.map_or(defaults.contrast_curve, Into::into),
+                webp_quality: quality.or(defaults.webp_quality),
+                overwrite: overwrite || defaults.overwrite,
+                ..defaults
+            };
+            apply_resize_flags(&mut config, width, height, scale);
+
+            println!("\n{}", "Conversion Settings:".bright_cyan().bold());
+            println!("  {} {}", "Input:".bright_yellow(), input);
+            println!("  {} {}", "Output:".bright_yellow(), output);
+            match config.webp_quality {
+                Some(q) => println!("  {} lossy, quality {}", "Encoding:".bright_yellow(), q),
+                None => println!("  {} lossless", "Encoding:".bright_yellow()),
+            }
+
+            println!("\n{} Converting...", "⚙️".bright_yellow());
+            if show_progress {
+                config.progress = Some(progress_bar());
             }
+            custom_to_webp(&custom_img, &output, Some(config))?;
+            println!("{} Successfully converted {} to {}", "✓".bright_green(), input, output);
         }
-        Some(Commands::View { input, use_cache: _ }) => {
-            validate_nor_extension(&input)
-                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+        Some(Commands::View { input, use_cache }) => {
+            require_viewer()?;
+            let is_png = validate_png_extension(&input).is_ok();
+            if !is_png {
+                validate_nor_extension(&input).map_err(CliError::InvalidInput)?;
+            }
             println!("\n{} Opening viewer...", "👁".bright_yellow());
-            view_custom_image(&input)?;
+            if is_png {
+                view_png_image(&input)?;
+            } else {
+                view_custom_image(&input, use_cache)?;
+            }
+        }
+        Some(Commands::Preview { input, width }) => {
+            validate_nor_extension(&input).map_err(CliError::InvalidInput)?;
+            let image = CustomImage::from_bytes(&fs::read(&input)?)?;
+            // Output that is not going to a terminal falls back to 80 columns.
+            let columns = width
+                .or_else(|| terminal_size::terminal_size().map(|(terminal_size::Width(w), _)| w))
+                .unwrap_or(80);
+            // https://no-color.org: any non-empty value disables color.
+            let style = match std::env::var_os("NO_COLOR") {
+                Some(value) if !value.is_empty() => PreviewStyle::Ascii,
+                _ => PreviewStyle::HalfBlock,
+            };
+            print!("{}", render_preview(&image, columns.max(1) as u32, style)?);
+        }
+        Some(Commands::CompareView { left, right }) => {
+            require_viewer()?;
+            validate_nor_extension(&left).map_err(CliError::InvalidInput)?;
+            validate_nor_extension(&right).map_err(CliError::InvalidInput)?;
+            println!("\n{} Opening comparison viewer...", "👁".bright_yellow());
+            view_comparison(&left, &right)?;
+        }
+        Some(Commands::Info { input, json }) => {
+            let custom_img = if input == "-" {
+                CustomImage::from_reader(io::stdin().lock())?
+            } else {
+                validate_nor_extension(&input).map_err(CliError::InvalidInput)?;
+                CustomImage::from_bytes(&fs::read(&input)?)?
+            };
+            if json {
+                println!("{}", serde_json::to_string_pretty(&ImageInfo::from(&custom_img))?);
+            } else {
+                display_metadata(&custom_img);
+            }
+        }
+        Some(Commands::Identify { paths }) => {
+            let mut others = 0;
+            for path in &paths {
+                // Read what `is_nor_reader` would, once, and sniff other
+                // formats from the same bytes.
+                let mut prefix = Vec::new();
+                let sniffed = fs::File::open(path)
+                    .and_then(|file| file.take(NOR_SNIFF_LEN as u64).read_to_end(&mut prefix))
+                    .and_then(|_| CustomImage::is_nor_reader(prefix.as_slice()));
+                let version = match sniffed {
+                    Ok(true) => CustomImage::nor_version(&prefix),
+                    Ok(false) => None,
+                    Err(e) => {
+                        others += 1;
+                        println!("{}: unreadable ({})", path, e);
+                        continue;
+                    }
+                };
+                match (version, image::guess_format(&prefix)) {
+                    (Some(version), _) => println!("{}: nor-image, version {}", path, version),
+                    (None, Ok(format)) => {
+                        others += 1;
+                        println!("{}: {:?} image, not .nor", path, format);
+                    }
+                    (None, Err(_)) => {
+                        others += 1;
+                        println!("{}: unknown format", path);
+                    }
+                }
+            }
+            if others > 0 {
+                return Err(CliError::InvalidInput(format!("{} of {} files are not .nor images", others, paths.len())));
+            }
+        }
+        Some(Commands::MetaDiff { old, new }) => {
+            validate_nor_extension(&old).map_err(CliError::InvalidInput)?;
+            validate_nor_extension(&new).map_err(CliError::InvalidInput)?;
+            let old_img = load_image(&old)?;
+            let new_img = load_image(&new)?;
+
+            let changes = old_img.metadata.diff(&new_img.metadata);
+            println!("Metadata {} -> {}:", old, new);
+            if changes.is_empty() {
+                println!("  identical");
+            }
+            for change in &changes {
+                let line = change.to_string();
+                match change {
+                    MetadataChange::Added { .. } => println!("  {}", line.green()),
+                    MetadataChange::Removed { .. } => println!("  {}", line.red()),
+                    MetadataChange::Changed { .. } => println!("  {}", line.yellow()),
+                }
+            }
+
+            // Hash the decoded pixels, so recompressed copies of the same
+            // image still compare equal.
+            let pixel_checksum = |image: &CustomImage| {
+                let mut hasher = Sha256::new();
+                hasher.update(image.width.to_le_bytes());
+                hasher.update(image.height.to_le_bytes());
+                hasher.update([image.color_type as u8]);
+                hasher.update(&image.data);
+                hasher.finalize()
+            };
+            let (old_sum, new_sum) = (pixel_checksum(&old_img), pixel_checksum(&new_img));
+            if old_sum == new_sum {
+                println!("Pixels: identical");
+            } else {
+                println!(
+                    "Pixels: {} ({:x} -> {:x})",
+                    "differ".yellow(),
+                    old_sum,
+                    new_sum
+                );
+            }
+        }
+        Some(Commands::Compare { reference, test, ssim, json }) => {
+            let reference_img = load_image(&reference)?;
+            let test_img = load_image(&test)?;
+            let result = metrics::compare(&reference_img, &test_img, ssim)?;
+            if json {
+                let report = CompareReport {
+                    reference: &reference,
+                    test: &test,
+                    width: reference_img.width,
+                    height: reference_img.height,
+                    reference_bytes: fs::metadata(&reference)?.len(),
+                    test_bytes: fs::metadata(&test)?.len(),
+                    mse: result.mse,
+                    psnr: Some(result.psnr).filter(|psnr| psnr.is_finite()),
+                    ssim: result.ssim,
+                };
+                println!("{}", serde_json::to_string_pretty(&report)?);
+                return Ok(());
+            }
+            // Plain `key: value` lines so the output is easy to parse in scripts.
+            println!("mse: {:.6}", result.mse);
+            println!("psnr: {:.4}", result.psnr);
+            if let Some(ssim) = result.ssim {
+                println!("ssim: {:.6}", ssim);
+            }
+        }
+        Some(Commands::Batch {
+            input_dir,
+            output_dir,
+            output_dir_flag,
+            // clap makes INPUT_DIR absent exactly when --from-stdin is set.
+            from_stdin: _,
+            recursive,
+            compression,
+            grayscale,
+            overwrite,
+        }) => {
+            let output_dir = output_dir
+                .or(output_dir_flag)
+                .ok_or_else(|| CliError::InvalidInput("batch needs an output directory".to_string()))?;
+            let mut config = ConversionConfig {
+                force_grayscale: grayscale || defaults.force_grayscale,
+                overwrite: overwrite || defaults.overwrite,
+                // One-off conversions gain nothing from the cache.
+                use_cache: false,
+                progress: None,
+                ..defaults
+            };
+            if let Some(compression) = compression {
+                compression.apply_to(&mut config);
+            }
+            match input_dir {
+                Some(input_dir) => run_batch(Path::new(&input_dir), Path::new(&output_dir), recursive, show_progress, &config)?,
+                None => run_batch_from_stdin(Path::new(&output_dir), show_progress, &config)?,
+            }
+        }
+        Some(Commands::Bench { input, grayscale, json }) => {
+            validate_png_extension(&input).map_err(CliError::InvalidInput)?;
+            run_benchmark(&input, grayscale, json)?;
+        }
+        Some(Commands::StripMeta { input, output, keep_date, overwrite }) => {
+            validate_nor_extension(&input).map_err(CliError::InvalidInput)?;
+            let output = output.unwrap_or_else(|| input.clone());
+            validate_nor_extension(&output).map_err(CliError::InvalidInput)?;
+            if output != input && !overwrite && !defaults.overwrite && Path::new(&output).exists() {
+                return Err(ConversionError::OutputExists(PathBuf::from(output)).into());
+            }
+
+            let bytes = fs::read(&input)?;
+            let mut custom_img = CustomImage::from_bytes(&bytes)?;
+            // Only the metadata changes; the pixel data is written back as-is.
+            custom_img.metadata = custom_img.metadata.stripped(keep_date);
+            write_atomic(&output, |writer| custom_img.write_to(writer))?;
+            println!("{} Stripped metadata from {} into {}", "✓".bright_green(), input, output);
+        }
+        Some(Commands::SetMeta { input, author, camera_model, title, description, copyright, fields }) => {
+            validate_nor_extension(&input).map_err(CliError::InvalidInput)?;
+            let edit = |metadata: &mut ImageMetadata| {
+                if author.is_some() {
+                    metadata.author = author.clone();
+                }
+                if camera_model.is_some() {
+                    metadata.camera_model = camera_model.clone();
+                }
+                if title.is_some() {
+                    metadata.title = title.clone();
+                }
+                if description.is_some() {
+                    metadata.description = description.clone();
+                }
+                if copyright.is_some() {
+                    metadata.copyright = copyright.clone();
+                }
+                metadata.custom_fields.extend(fields.iter().cloned());
+            };
+
+            let mut file = fs::OpenOptions::new().read(true).write(true).open(&input)?;
+            if CustomImage::rewrite_metadata(&mut file, edit)? {
+                println!("{} Updated metadata of {} in place", "✓".bright_green(), input);
+            } else {
+                // No reserved space, or the new metadata outgrew it.
+                warn!("{} has no room for the metadata in place; rewriting the whole file", input);
+                drop(file);
+                let mut custom_img = CustomImage::from_bytes(&fs::read(&input)?)?;
+                edit(&mut custom_img.metadata);
+                write_atomic(&input, |writer| custom_img.write_to(writer))?;
+                println!("{} Rewrote {} with updated metadata", "✓".bright_green(), input);
+            }
         }
-        Some(Commands::Info { input }) => {
-            validate_nor_extension(&input)
-                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+        Some(Commands::Recompress { input, output, compression, overwrite, show_info }) => {
+            validate_nor_extension(&input).map_err(CliError::InvalidInput)?;
+            validate_nor_extension(&output).map_err(CliError::InvalidInput)?;
+            if !overwrite && !defaults.overwrite && Path::new(&output).exists() {
+                return Err(ConversionError::OutputExists(PathBuf::from(output)).into());
+            }
+            let Some(compression) = compression.codec() else {
+                return Err(CliError::InvalidInput(
+                    "recompress needs an explicit compression method, not auto".to_string(),
+                ));
+            };
+
             let bytes = fs::read(&input)?;
-            let custom_img = CustomImage::from_bytes(&bytes)?;
-            display_metadata(&custom_img);
+            let mut custom_img = CustomImage::from_bytes(&bytes)?;
+            let source = custom_img.compression;
+            if source == CompressionType::Lossy && compression != CompressionType::Lossy {
+                warn!("{} is lossy: its approximated pixels are kept, the original detail cannot be restored", input);
+            } else if compression == CompressionType::Lossy && source != CompressionType::Lossy {
+                warn!("Recompressing to lossy discards detail that cannot be recovered from {}", output);
+            } else if compression == CompressionType::Lossy {
+                warn!("Re-encoding lossy data as lossy adds a second generation of loss");
+            }
+
+            let stored_before = custom_img.data.len();
+            ParallelImageProcessor::recompress(&mut custom_img, compression)?;
+            write_atomic(&output, |writer| custom_img.write_to(writer))?;
+            println!(
+                "{} Recompressed {} ({:?}, {} bytes) into {} ({:?}, {} bytes)",
+                "✓".bright_green(),
+                input,
+                source,
+                stored_before,
+                output,
+                compression,
+                custom_img.data.len()
+            );
+            if show_info {
+                display_written_metadata(&output)?;
+            }
+        }
+        Some(Commands::DumpRaw { input, output, overwrite }) => {
+            validate_nor_extension(&input).map_err(CliError::InvalidInput)?;
+            RawFormat::from_path(Path::new(&output))?;
+            let custom_img = CustomImage::from_bytes(&fs::read(&input)?)?;
+            let config = ConversionConfig { overwrite: overwrite || defaults.overwrite, ..ConversionConfig::default() };
+            custom_to_raw(&custom_img, &output, Some(config))?;
+            println!(
+                "{} Wrote {}x{} {:?} pixels to {}",
+                "✓".bright_green(),
+                custom_img.width,
+                custom_img.height,
+                custom_img.color_type,
+                output
+            );
+        }
+        Some(Commands::ContactSheet { output, inputs, cols, cell, no_labels, overwrite }) => {
+            validate_png_extension(&output).map_err(CliError::InvalidInput)?;
+            let mut images = Vec::with_capacity(inputs.len());
+            for input in &inputs {
+                validate_nor_extension(input).map_err(CliError::InvalidInput)?;
+                let label = Path::new(input).file_name().map_or_else(|| input.clone(), |name| name.to_string_lossy().into_owned());
+                images.push((label, CustomImage::from_bytes(&fs::read(input)?)?));
+            }
+            let sheet = contact_sheet(&images, cols, cell, !no_labels)?;
+            let config = ConversionConfig {
+                overwrite: overwrite || defaults.overwrite,
+                png_compression: defaults.png_compression,
+                ..ConversionConfig::default()
+            };
+            custom_to_png(&sheet, &output, Some(config))?;
+            println!(
+                "{} Wrote a {}x{} contact sheet of {} images to {}",
+                "✓".bright_green(),
+                sheet.width,
+                sheet.height,
+                images.len(),
+                output
+            );
+        }
+        Some(Commands::RawToCustom { input, output, width, height, color, compression, overwrite, show_info }) => {
+            validate_nor_extension(&output).map_err(CliError::InvalidInput)?;
+            if !overwrite && !defaults.overwrite && Path::new(&output).exists() {
+                return Err(ConversionError::OutputExists(PathBuf::from(output)).into());
+            }
+            let Some(compression) = compression.codec() else {
+                return Err(CliError::InvalidInput(
+                    "raw-to-custom needs an explicit compression method, not auto".to_string(),
+                ));
+            };
+
+            let custom_img = raw_to_custom(fs::read(&input)?, width, height, color.into(), compression)?;
+            write_atomic(&output, |writer| custom_img.write_to(writer))?;
+            println!(
+                "{} Imported {}x{} {:?} pixels from {} into {} ({:?})",
+                "✓".bright_green(),
+                width,
+                height,
+                custom_img.color_type,
+                input,
+                output,
+                compression
+            );
+            if show_info {
+                display_written_metadata(&output)?;
+            }
+        }
+        Some(Commands::Capabilities { json }) => print_capabilities(json)?,
+        Some(Commands::Selftest) => run_selftest()?,
+        Some(Commands::GenTestVectors { dir }) => {
+            let count = generate_test_vectors(Path::new(&dir))?;
+            println!("{} Wrote {} test vectors to {}", "✓".bright_green(), count, dir);
         }
         Some(Commands::ClearCache) => {
             use crate::processing::IMAGE_CACHE;
@@ -672,8 +3107,16 @@ fn main() -> Result<(), Box<dyn Error>> {
                 eprintln!("{} Failed to clear cache: could not acquire lock", "Error:".bright_red().bold());
             }
         }
-        _ => {
-            interactive_mode()?;
+        #[cfg(feature = "interactive")]
+        Some(Commands::Interactive) | None => interactive_mode(&defaults)?,
+        #[cfg(not(feature = "interactive"))]
+        Some(Commands::Interactive) => return Err(CliError::Other(NO_INTERACTIVE.into())),
+        // Without interactive mode there is nothing to fall back to, so
+        // show the usage instead.
+        #[cfg(not(feature = "interactive"))]
+        None => {
+            eprint!("{}", <Cli as clap::CommandFactory>::command().render_help());
+            return Err(CliError::InvalidInput("no command given".to_string()));
         }
     }
     Ok(())
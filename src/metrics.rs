@@ -0,0 +1,246 @@
+// Copyright 2025 Grish
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Image quality metrics.
+//!
+//! This module provides functionality for quantifying the difference between
+//! two images of the same size:
+//! - Mean squared error (MSE)
+//! - Peak signal-to-noise ratio (PSNR)
+//! - Structural similarity (SSIM), computed on luma over 8x8 windows
+
+use crate::converter::ConversionError;
+use crate::format::{ColorType, CompressionType, CustomImage};
+use crate::processing::ParallelImageProcessor;
+
+/// Side length of the square windows used for SSIM.
+const SSIM_WINDOW: usize = 8;
+
+/// Quality metrics comparing a test image against a reference.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QualityMetrics {
+    /// Mean squared error over all channels.
+    pub mse: f64,
+    /// Peak signal-to-noise ratio in dB (infinite for identical images).
+    pub psnr: f64,
+    /// Mean structural similarity (1.0 for identical images), if requested.
+    pub ssim: Option<f64>,
+}
+
+/// Compares two images and computes their quality metrics.
+///
+/// Compressed images are decompressed first. When the color types differ,
+/// grayscale pixels are expanded to RGB so both images are compared in the
+/// same space.
+///
+/// # Errors
+///
+/// Returns `DimensionMismatch` if the images are not the same size, or a
+/// format error if either image fails to decompress.
+pub fn compare(
+    reference: &CustomImage,
+    test: &CustomImage,
+    compute_ssim: bool,
+) -> Result<QualityMetrics, ConversionError> {
+    if (reference.width, reference.height) != (test.width, test.height) {
+        return Err(ConversionError::DimensionMismatch {
+            expected: (reference.width, reference.height),
+            actual: (test.width, test.height),
+        });
+    }
+
+    let reference = decompressed(reference)?;
+    let test = decompressed(test)?;
+    let (a, b) = if reference.color_type == test.color_type {
+        (reference.data, test.data)
+    } else {
+        (to_rgb(&reference), to_rgb(&test))
+    };
+
+    let mse = mean_squared_error(&a, &b);
+    let psnr = if mse == 0.0 {
+        f64::INFINITY
+    } else {
+        10.0 * (255.0 * 255.0 / mse).log10()
+    };
+    let ssim = if compute_ssim {
+        let width = reference.width as usize;
+        let height = reference.height as usize;
+        Some(structural_similarity(
+            &luma(&a, width * height),
+            &luma(&b, width * height),
+            width,
+            height,
+        ))
+    } else {
+        None
+    };
+
+    Ok(QualityMetrics { mse, psnr, ssim })
+}
+
+/// Returns an uncompressed copy of the image.
+fn decompressed(image: &CustomImage) -> Result<CustomImage, ConversionError> {
+    let mut image = image.clone();
+    if image.compression != CompressionType::None {
+        ParallelImageProcessor::decompress(&mut image)?;
+    }
+    Ok(image)
+}
+
+/// Expands the image's pixel data to interleaved RGB, dropping any alpha.
+fn to_rgb(image: &CustomImage) -> Vec<u8> {
+    match image.color_type {
+        ColorType::Gray => image.data.iter().flat_map(|&v| [v, v, v]).collect(),
+        ColorType::Rgb => image.data.clone(),
+        ColorType::GrayAlpha => image.data.chunks_exact(2).flat_map(|p| [p[0], p[0], p[0]]).collect(),
+    }
+}
+
+/// Computes the mean squared error between two equally sized buffers.
+fn mean_squared_error(a: &[u8], b: &[u8]) -> f64 {
+    let len = a.len().min(b.len());
+    if len == 0 {
+        return 0.0;
+    }
+    let sum: f64 = a.iter()
+        .zip(b.iter())
+        .map(|(&x, &y)| {
+            let diff = x as f64 - y as f64;
+            diff * diff
+        })
+        .sum();
+    sum / len as f64
+}
+
+/// Converts interleaved gray, gray-alpha or RGB samples to a luma plane.
+fn luma(data: &[u8], pixels: usize) -> Vec<f64> {
+    match data.len().checked_div(pixels) {
+        Some(1) => data.iter().map(|&v| v as f64).collect(),
+        Some(2) => data.iter().step_by(2).map(|&v| v as f64).collect(),
+        _ => data
+            .chunks_exact(3)
+            .map(|p| 0.299 * p[0] as f64 + 0.587 * p[1] as f64 + 0.114 * p[2] as f64)
+            .collect(),
+    }
+}
+
+/// Computes the mean SSIM over non-overlapping windows of two luma planes.
+fn structural_similarity(a: &[f64], b: &[f64], width: usize, height: usize) -> f64 {
+    const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+    const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+
+    let mut total = 0.0;
+    let mut windows = 0usize;
+    for wy in (0..height).step_by(SSIM_WINDOW) {
+        for wx in (0..width).step_by(SSIM_WINDOW) {
+            let mut samples = Vec::with_capacity(SSIM_WINDOW * SSIM_WINDOW);
+            for y in wy..(wy + SSIM_WINDOW).min(height) {
+                for x in wx..(wx + SSIM_WINDOW).min(width) {
+                    samples.push((a[y * width + x], b[y * width + x]));
+                }
+            }
+            let n = samples.len() as f64;
+            let mean_a = samples.iter().map(|s| s.0).sum::<f64>() / n;
+            let mean_b = samples.iter().map(|s| s.1).sum::<f64>() / n;
+            let mut var_a = 0.0;
+            let mut var_b = 0.0;
+            let mut covar = 0.0;
+            for &(x, y) in &samples {
+                var_a += (x - mean_a) * (x - mean_a);
+                var_b += (y - mean_b) * (y - mean_b);
+                covar += (x - mean_a) * (y - mean_b);
+            }
+            var_a /= n;
+            var_b /= n;
+            covar /= n;
+
+            total += ((2.0 * mean_a * mean_b + C1) * (2.0 * covar + C2))
+                / ((mean_a * mean_a + mean_b * mean_b + C1) * (var_a + var_b + C2));
+            windows += 1;
+        }
+    }
+    if windows == 0 {
+        1.0
+    } else {
+        total / windows as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(width: u32, height: u32, color_type: ColorType, data: Vec<u8>) -> CustomImage {
+        CustomImage::new(width, height, color_type, data, None, CompressionType::None).unwrap()
+    }
+
+    #[test]
+    fn identical_images_have_zero_error() {
+        // 10x10 leaves partial windows along the right and bottom edges.
+        let data: Vec<u8> = (0..10 * 10 * 3).map(|i| (i * 7 % 256) as u8).collect();
+        let reference = image(10, 10, ColorType::Rgb, data);
+        let metrics = compare(&reference, &reference, true).unwrap();
+        assert_eq!(metrics.mse, 0.0);
+        assert_eq!(metrics.psnr, f64::INFINITY);
+        assert!((metrics.ssim.unwrap() - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn uniform_offset_gives_exact_mse_and_psnr() {
+        let reference = image(4, 4, ColorType::Gray, vec![100; 16]);
+        let test = image(4, 4, ColorType::Gray, vec![110; 16]);
+        let metrics = compare(&reference, &test, false).unwrap();
+        assert_eq!(metrics.mse, 100.0);
+        assert_eq!(metrics.psnr, 10.0 * (255.0f64 * 255.0 / 100.0).log10());
+        assert_eq!(metrics.ssim, None);
+    }
+
+    #[test]
+    fn ssim_averages_partial_edge_windows() {
+        // A 9x9 image has an 8x8 window, two 8x1 strips and a 1x1 corner;
+        // only the corner differs.
+        let reference = image(9, 9, ColorType::Gray, vec![100; 81]);
+        let mut data = vec![100; 81];
+        data[80] = 200;
+        let test = image(9, 9, ColorType::Gray, data);
+
+        let (c1, a, b) = ((0.01 * 255.0f64).powi(2), 100.0, 200.0);
+        let corner = (2.0 * a * b + c1) / (a * a + b * b + c1);
+        let ssim = compare(&reference, &test, true).unwrap().ssim.unwrap();
+        assert!((ssim - (3.0 + corner) / 4.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn gray_is_compared_against_rgb_and_gray_alpha() {
+        let gray = image(2, 1, ColorType::Gray, vec![100, 50]);
+        let rgb = image(2, 1, ColorType::Rgb, vec![100, 100, 130, 50, 50, 50]);
+        // The alpha channel is dropped.
+        let gray_alpha = image(2, 1, ColorType::GrayAlpha, vec![100, 0, 50, 255]);
+
+        assert_eq!(compare(&gray, &rgb, false).unwrap().mse, 30.0 * 30.0 / 6.0);
+        assert_eq!(compare(&gray, &gray_alpha, true).unwrap().mse, 0.0);
+        assert_eq!(compare(&gray_alpha, &rgb, false).unwrap().mse, 30.0 * 30.0 / 6.0);
+    }
+
+    #[test]
+    fn mismatched_sizes_are_rejected() {
+        let reference = image(4, 4, ColorType::Gray, vec![0; 16]);
+        let test = image(4, 2, ColorType::Gray, vec![0; 8]);
+        assert!(matches!(
+            compare(&reference, &test, false),
+            Err(ConversionError::DimensionMismatch { expected: (4, 4), actual: (4, 2) })
+        ));
+    }
+}
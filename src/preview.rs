@@ -0,0 +1,136 @@
+// Copyright 2025 Grish
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Terminal previews.
+//!
+//! Renders an image as text for a quick look where no window can be opened:
+//! - Half-block mode packs two pixels into each character cell, using 24-bit
+//!   ANSI foreground and background colors
+//! - ASCII mode maps luminance to a character ramp, for terminals without
+//!   color or when `NO_COLOR` is set
+//!
+//! The image is decoded and downsampled to fit the requested number of
+//! columns; it is never scaled up.
+
+use std::fmt::Write;
+
+use image::imageops::FilterType;
+
+use crate::format::{ColorType, CustomImage, FormatError};
+
+/// Characters from darkest to brightest for ASCII previews.
+const ASCII_RAMP: &[u8] = b" .:-=+*#%@";
+
+/// How a preview is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreviewStyle {
+    /// `▀` cells colored with ANSI truecolor escapes, two pixels per cell.
+    HalfBlock,
+    /// A plain luminance ramp, one pixel per cell.
+    Ascii,
+}
+
+/// Renders `image` as lines of text at most `columns` characters wide.
+///
+/// Terminal cells are about twice as tall as they are wide, so half-block
+/// previews use one pixel row per half cell and ASCII previews sample every
+/// other row to keep the aspect ratio. Alpha is composited over black.
+///
+/// ```rust
+/// use nor_image::format::{CustomImage, ColorType, CompressionType};
+/// use nor_image::preview::{render_preview, PreviewStyle};
+///
+/// let data: Vec<u8> = (0..64).map(|i| if i % 8 < 4 { 0 } else { 255 }).collect();
+/// let image = CustomImage::new(8, 8, ColorType::Gray, data, None, CompressionType::None)?;
+///
+/// let ascii = render_preview(&image, 8, PreviewStyle::Ascii)?;
+/// assert_eq!(ascii.lines().count(), 4);
+/// assert!(ascii.lines().all(|line| line == "    @@@@"));
+///
+/// let blocks = render_preview(&image, 4, PreviewStyle::HalfBlock)?;
+/// assert_eq!(blocks.lines().count(), 2);
+/// assert_eq!(blocks.lines().next().unwrap().matches('▀').count(), 4);
+/// # Ok::<(), nor_image::format::FormatError>(())
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if the image's pixel data cannot be decoded.
+pub fn render_preview(image: &CustomImage, columns: u32, style: PreviewStyle) -> Result<String, FormatError> {
+    let width = columns.clamp(1, image.width);
+    let scaled_height = |divisor: f64| {
+        ((image.height as f64 * width as f64 / image.width as f64 / divisor).round() as u32).max(1)
+    };
+    let height = match style {
+        PreviewStyle::HalfBlock => scaled_height(1.0),
+        PreviewStyle::Ascii => scaled_height(2.0),
+    };
+
+    let scaled = image.resized(width, height, FilterType::Triangle)?;
+    let pixels: Vec<[u8; 3]> = scaled
+        .data
+        .chunks_exact(scaled.color_type.channels() as usize)
+        .map(|p| rgb(p, scaled.color_type))
+        .collect();
+    let rows: Vec<&[[u8; 3]]> = pixels.chunks_exact(width as usize).collect();
+
+    let mut out = String::new();
+    match style {
+        PreviewStyle::HalfBlock => {
+            for pair in rows.chunks(2) {
+                for (x, top) in pair[0].iter().enumerate() {
+                    match pair.get(1) {
+                        Some(bottom) => {
+                            let bottom = bottom[x];
+                            let _ = write!(
+                                out,
+                                "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m▀",
+                                top[0], top[1], top[2], bottom[0], bottom[1], bottom[2]
+                            );
+                        }
+                        // An odd last row only fills the upper half of its cells.
+                        None => {
+                            let _ = write!(out, "\x1b[49m\x1b[38;2;{};{};{}m▀", top[0], top[1], top[2]);
+                        }
+                    }
+                }
+                out.push_str("\x1b[0m\n");
+            }
+        }
+        PreviewStyle::Ascii => {
+            for row in rows {
+                out.extend(row.iter().map(|&p| {
+                    let index = luminance(p) as usize * (ASCII_RAMP.len() - 1) / 255;
+                    ASCII_RAMP[index] as char
+                }));
+                out.push('\n');
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Expands one pixel to RGB, compositing any alpha over black.
+fn rgb(pixel: &[u8], color_type: ColorType) -> [u8; 3] {
+    match color_type {
+        ColorType::Gray => [pixel[0]; 3],
+        ColorType::GrayAlpha => [(pixel[0] as u16 * pixel[1] as u16 / 255) as u8; 3],
+        ColorType::Rgb => [pixel[0], pixel[1], pixel[2]],
+    }
+}
+
+/// Returns the Rec. 601 luma of an RGB pixel.
+fn luminance([r, g, b]: [u8; 3]) -> u8 {
+    ((299 * r as u32 + 587 * g as u32 + 114 * b as u32) / 1000) as u8
+}
@@ -22,18 +22,23 @@
 //! - Parallel compression/decompression
 
 use std::path::Path;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::{self, Read, Write, BufReader, BufWriter};
 use std::sync::Arc;
+#[cfg(feature = "parallel")]
 use rayon::prelude::*;
+use image::imageops::FilterType;
 use lru::LruCache;
 use std::sync::Mutex;
 use std::num::NonZeroUsize;
-use crossbeam_channel::{bounded, Sender, Receiver};
+use crossbeam_channel::{unbounded, Sender, Receiver};
 use bytes::{BytesMut, BufMut};
 use std::error::Error as StdError;
+use std::time::Instant;
+use log::{debug, trace};
 
-use crate::format::{CustomImage, CompressionType, FormatError};
+use crate::atomic::write_atomic;
+use crate::format::{ChunkIndex, CustomImage, CompressionType, FormatError};
 
 /// Default chunk size for parallel processing (1MB)
 pub const CHUNK_SIZE: usize = 1024 * 1024;
@@ -43,10 +48,41 @@ const DEFAULT_CACHE_SIZE: usize = 10;
 
 lazy_static::lazy_static! {
     /// Global LRU cache for storing processed images
-    pub static ref IMAGE_CACHE: Mutex<LruCache<String, Arc<CustomImage>>> = 
+    ///
+    /// Full-size and resized entries share its capacity.
+    pub static ref IMAGE_CACHE: Mutex<LruCache<CacheKey, Arc<CustomImage>>> = 
         Mutex::new(LruCache::new(NonZeroUsize::new(DEFAULT_CACHE_SIZE).unwrap()));
 }
 
+/// Identifies a cached image: a file as stored, or resized from it
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    path: String,
+    /// Target width, height and filter of a resized entry
+    resized: Option<(u32, u32, u8)>,
+}
+
+impl CacheKey {
+    /// Key of the file at `path` as stored
+    fn full<P: AsRef<Path>>(path: P) -> Self {
+        CacheKey { path: path.as_ref().to_string_lossy().to_string(), resized: None }
+    }
+
+    /// Key of the file at `path` resized to `width`x`height` with `filter`
+    #[allow(dead_code)]
+    fn resized<P: AsRef<Path>>(path: P, width: u32, height: u32, filter: FilterType) -> Self {
+        // `FilterType` is not `Eq` in every supported `image` release.
+        let filter = match filter {
+            FilterType::Nearest => 0,
+            FilterType::Triangle => 1,
+            FilterType::CatmullRom => 2,
+            FilterType::Gaussian => 3,
+            FilterType::Lanczos3 => 4,
+        };
+        CacheKey { resized: Some((width, height, filter)), ..Self::full(path) }
+    }
+}
+
 /// Errors that can occur during image processing operations
 #[derive(Debug)]
 pub enum ProcessingError {
@@ -97,9 +133,35 @@ impl From<FormatError> for ProcessingError {
 ///
 /// Processed image data as a vector of bytes
 pub fn process_parallel(data: &[u8], chunk_size: usize) -> Vec<u8> {
-    data.par_chunks(chunk_size)
-        .flat_map(|chunk| chunk.to_vec())
-        .collect()
+    #[cfg(feature = "parallel")]
+    let chunks = data.par_chunks(chunk_size);
+    #[cfg(not(feature = "parallel"))]
+    let chunks = data.chunks(chunk_size);
+    chunks.flat_map(|chunk| chunk.to_vec()).collect()
+}
+
+/// Runs `f` over `data` in parallel, one task per `chunk_size` bytes (the
+/// last chunk may be shorter), and returns how many chunks there were.
+///
+/// Pixel data should be split at a multiple of its pixel size, so no pixel
+/// straddles two chunks.
+///
+/// # Panics
+///
+/// Panics if `chunk_size` is zero.
+pub fn process_chunks_mut<F>(data: &mut [u8], chunk_size: usize, f: F) -> usize
+where
+    F: Fn(&mut [u8]) + Sync + Send,
+{
+    #[cfg(feature = "parallel")]
+    return data.par_chunks_mut(chunk_size).map(f).count();
+    #[cfg(not(feature = "parallel"))]
+    {
+        let chunks = data.chunks_mut(chunk_size);
+        let count = chunks.len();
+        chunks.for_each(f);
+        count
+    }
 }
 
 /// Streaming processor for handling large image files
@@ -116,7 +178,9 @@ impl StreamingProcessor {
     ///
     /// * `chunk_size` - Size of chunks for streaming processing
     pub fn new(chunk_size: usize) -> Self {
-        let (sender, receiver) = bounded(4); // Buffer up to 4 chunks
+        // Unbounded, since callers drain the chunks only after the whole
+        // stream has been read; a bounded channel would block on large files.
+        let (sender, receiver) = unbounded();
         StreamingProcessor {
             sender,
             receiver,
@@ -145,7 +209,7 @@ impl StreamingProcessor {
             // Process chunk in parallel
             let processed = process_parallel(&buffer[..n], self.chunk_size);
             self.sender.send(processed).map_err(|_| {
-                io::Error::new(io::ErrorKind::Other, "Failed to send processed chunk")
+                io::Error::other("Failed to send processed chunk")
             })?;
         }
         
@@ -171,26 +235,138 @@ impl CachedImageLoader {
     /// # Returns
     ///
     /// Arc-wrapped CustomImage or ProcessingError
+    #[allow(dead_code)]
     pub fn load<P: AsRef<Path>>(path: P) -> Result<Arc<CustomImage>, ProcessingError> {
-        let path_str = path.as_ref().to_string_lossy().to_string();
+        Self::load_with(path, true)
+    }
+
+    /// Like `load`, but reads a file missing from the cache with `read`,
+    /// streaming it only when `streaming` is set.
+    pub fn load_with<P: AsRef<Path>>(path: P, streaming: bool) -> Result<Arc<CustomImage>, ProcessingError> {
+        let key = CacheKey::full(&path);
         
         // Try to get from cache first
-        if let Some(cached) = IMAGE_CACHE.lock().unwrap().get(&path_str) {
+        if let Some(cached) = IMAGE_CACHE.lock().unwrap().get(&key) {
+            debug!("Cache hit for {}", key.path);
             return Ok(Arc::clone(cached));
         }
+        debug!("Cache miss for {}, loading from disk", key.path);
         
         // Load and process the image
-        let file = File::open(&path)?;
-        let reader = BufReader::new(file);
-        let image = Self::load_with_streaming(reader)?;
+        let image = Self::read(&path, streaming)?;
         
         // Cache the result
         let arc_image = Arc::new(image);
-        IMAGE_CACHE.lock().unwrap().put(path_str, Arc::clone(&arc_image));
+        IMAGE_CACHE.lock().unwrap().put(key, Arc::clone(&arc_image));
         
         Ok(arc_image)
     }
 
+    /// Loads an image resized to `width`x`height` with `filter`, with caching
+    ///
+    /// The resized pixels are cached under their own entry, so repeated
+    /// requests for the same size (such as gallery thumbnails) skip both the
+    /// decode and the resize. A cached full-size image is resized when
+    /// present; otherwise the file is read without caching it at full size,
+    /// so thumbnails do not crowd each other out of the cache.
+    ///
+    /// The result is uncompressed, like `CustomImage::resized`.
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use image::imageops::FilterType;
+    /// use nor_image::format::{CustomImage, ColorType, CompressionType};
+    /// use nor_image::processing::CachedImageLoader;
+    ///
+    /// let image = CustomImage::new(8, 8, ColorType::Gray, (0..64).collect(), None, CompressionType::None)?;
+    /// let path = std::env::temp_dir().join(format!("nor-thumb-{}.nor", std::process::id()));
+    /// std::fs::write(&path, image.to_bytes()?)?;
+    ///
+    /// let thumb = CachedImageLoader::load_resized(&path, 4, 4, FilterType::Triangle)?;
+    /// assert_eq!((thumb.width, thumb.height), (4, 4));
+    /// // The second request is served from the cache.
+    /// assert!(Arc::ptr_eq(&thumb, &CachedImageLoader::load_resized(&path, 4, 4, FilterType::Triangle)?));
+    /// assert!(!Arc::ptr_eq(&thumb, &CachedImageLoader::load_resized(&path, 4, 4, FilterType::Nearest)?));
+    /// std::fs::remove_file(&path)?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[cfg_attr(not(feature = "cache"), allow(dead_code))]
+    pub fn load_resized<P: AsRef<Path>>(
+        path: P,
+        width: u32,
+        height: u32,
+        filter: FilterType,
+    ) -> Result<Arc<CustomImage>, ProcessingError> {
+        let key = CacheKey::resized(&path, width, height, filter);
+        if let Some(cached) = IMAGE_CACHE.lock().unwrap().get(&key) {
+            debug!("Cache hit for {} at {}x{}", key.path, width, height);
+            return Ok(Arc::clone(cached));
+        }
+
+        let full = IMAGE_CACHE.lock().unwrap().get(&CacheKey::full(&path)).cloned();
+        let full = match full {
+            Some(full) => full,
+            None => {
+                debug!("Cache miss for {} at {}x{}, loading from disk", key.path, width, height);
+                Arc::new(Self::read(&path, true)?)
+            }
+        };
+        let resized = Arc::new(full.resized(width, height, filter)?);
+        IMAGE_CACHE.lock().unwrap().put(key, Arc::clone(&resized));
+        Ok(resized)
+    }
+
+    /// Loads an image like `load`, but with its pixel data decompressed
+    ///
+    /// The decompressed image replaces the compressed one in the cache, so
+    /// repeated exports of the same file decompress it only once, and the
+    /// cache never holds two copies of the same image.
+    #[cfg_attr(not(feature = "cache"), allow(dead_code))]
+    pub fn load_decompressed<P: AsRef<Path>>(path: P) -> Result<Arc<CustomImage>, ProcessingError> {
+        let image = Self::load(&path)?;
+        if image.compression == CompressionType::None {
+            return Ok(image);
+        }
+
+        let mut decompressed = CustomImage::clone(&image);
+        ParallelImageProcessor::decompress(&mut decompressed)?;
+        let decompressed = Arc::new(decompressed);
+        let key = CacheKey::full(&path);
+        debug!("Caching decompressed pixels for {}", key.path);
+        IMAGE_CACHE.lock().unwrap().put(key, Arc::clone(&decompressed));
+        Ok(decompressed)
+    }
+
+    /// Reads an image from disk without touching the cache.
+    ///
+    /// With `streaming`, the file is read in `CHUNK_SIZE` pieces that pass
+    /// through a `StreamingProcessor` and are then reassembled, so only one
+    /// piece is read at a time but the pieces and the reassembled copy peak
+    /// at about twice the file size. Without it, the file is read with a
+    /// single `fs::read` into one buffer of the file size, which is faster
+    /// and lighter for files that fit in memory comfortably.
+    ///
+    /// ```rust
+    /// use nor_image::format::{CustomImage, ColorType, CompressionType};
+    /// use nor_image::processing::CachedImageLoader;
+    ///
+    /// let image = CustomImage::new(4, 4, ColorType::Gray, (0..16).collect(), None, CompressionType::None)?;
+    /// let path = std::env::temp_dir().join(format!("nor-read-{}.nor", std::process::id()));
+    /// std::fs::write(&path, image.to_bytes()?)?;
+    /// assert_eq!(CachedImageLoader::read(&path, true)?, image);
+    /// assert_eq!(CachedImageLoader::read(&path, false)?, image);
+    /// std::fs::remove_file(&path)?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn read<P: AsRef<Path>>(path: P, streaming: bool) -> Result<CustomImage, ProcessingError> {
+        if streaming {
+            let reader = BufReader::new(File::open(&path)?);
+            return Self::load_with_streaming(reader);
+        }
+        trace!("Reading {} without streaming", path.as_ref().display());
+        Ok(CustomImage::from_bytes(&fs::read(&path)?)?)
+    }
+
     /// Internal helper for streaming image loads
     fn load_with_streaming<R: Read>(reader: R) -> Result<CustomImage, ProcessingError> {
         let processor = StreamingProcessor::new(CHUNK_SIZE);
@@ -199,6 +375,7 @@ impl CachedImageLoader {
         processor.process_stream(reader)?;
         
         for chunk in processor.receive_chunks() {
+            trace!("Received {} byte chunk", chunk.len());
             processed_data.put_slice(&chunk);
         }
         
@@ -224,21 +401,14 @@ impl OptimizedImageWriter {
         }
     }
 
-    /// Writes an image to disk with parallel processing
+    /// Writes an image to disk, streaming it through a buffered writer
     #[allow(dead_code)]
     pub fn write(&self, image: &CustomImage) -> Result<(), ProcessingError> {
-        let file = File::create(&self.path)?;
-        let mut writer = BufWriter::new(file);
-        
-        let bytes = image.to_bytes()?;
-        let processed = process_parallel(&bytes, self.chunk_size);
-        
-        for chunk in processed.chunks(self.chunk_size) {
-            writer.write_all(chunk)?;
-        }
-        
-        writer.flush()?;
-        Ok(())
+        write_atomic(&self.path, |writer| {
+            let mut writer = BufWriter::with_capacity(self.chunk_size, writer);
+            image.write_to(&mut writer)?;
+            Ok(writer.flush()?)
+        })
     }
 }
 
@@ -247,68 +417,248 @@ pub struct ParallelImageProcessor;
 
 impl ParallelImageProcessor {
     /// Compresses image data using the specified compression type
+    ///
+    /// RLE and varint RLE are encoded in parallel chunks of `CHUNK_SIZE`
+    /// bytes, and the chunk lengths are recorded in `image.chunks` so that
+    /// `decompress` can decode them in parallel too.
     #[allow(dead_code)]
     pub fn compress(image: &mut CustomImage, compression: CompressionType) -> Result<(), FormatError> {
         if image.compression != CompressionType::None {
             return Err(FormatError::CompressionError("Already compressed".to_string()));
         }
 
+        let start = Instant::now();
+        let mut chunk_lengths = None;
         let processed_data = match compression {
             CompressionType::None => image.data.clone(),
             CompressionType::RLE => {
                 // Process RLE compression in parallel chunks
-                let chunks: Vec<_> = image.data.par_chunks(CHUNK_SIZE)
-                    .map(|chunk| CustomImage::compress_rle(chunk))
+                #[cfg(feature = "parallel")]
+                let pending = image.data.par_chunks(CHUNK_SIZE);
+                #[cfg(not(feature = "parallel"))]
+                let pending = image.data.chunks(CHUNK_SIZE);
+                let chunks: Vec<_> = pending
+                    .map(CustomImage::compress_rle)
                     .collect();
-                
-                let mut result = Vec::new();
-                for chunk in chunks {
-                    result.extend(chunk);
-                }
-                result
+                chunk_lengths = Some(chunks.iter().map(|c| c.len() as u32).collect());
+                chunks.concat()
             }
             CompressionType::Delta => {
                 // Delta compression needs sequential processing
                 CustomImage::compress_delta(&image.data)
             }
             CompressionType::Lossy => {
-                image.compress_lossy(50)?
+                image.compress_lossy(image.lossy_quality)?
+            }
+            CompressionType::RleVarint => {
+                // Chunks encode independently; a run split at a boundary
+                // just becomes two runs.
+                #[cfg(feature = "parallel")]
+                let pending = image.data.par_chunks(CHUNK_SIZE);
+                #[cfg(not(feature = "parallel"))]
+                let pending = image.data.chunks(CHUNK_SIZE);
+                let chunks: Vec<_> = pending
+                    .map(CustomImage::compress_rle_varint)
+                    .collect();
+                chunk_lengths = Some(chunks.iter().map(|c| c.len() as u32).collect());
+                chunks.concat()
             }
         };
 
+        debug!(
+            "Compressed {} -> {} bytes with {:?} in {:.2?}",
+            image.data.len(),
+            processed_data.len(),
+            compression,
+            start.elapsed()
+        );
         image.data = processed_data;
         image.compression = compression;
+        image.chunks = chunk_lengths.map(|lengths| ChunkIndex {
+            chunk_size: CHUNK_SIZE as u32,
+            lengths,
+        });
+        image.recompress_levels(CompressionType::None, compression)?;
         Ok(())
     }
 
     /// Decompresses image data based on its current compression type
+    ///
+    /// Tiled images are reassembled into a single uncompressed buffer, with
+    /// their tiles decoded in parallel. Chunked RLE data is likewise decoded
+    /// one chunk per task. Pyramid levels are decompressed along with the
+    /// image.
+    ///
+    /// ```rust
+    /// use nor_image::format::{CustomImage, ColorType, CompressionType};
+    /// use nor_image::processing::{ParallelImageProcessor, CHUNK_SIZE};
+    ///
+    /// // Large enough to span several chunks.
+    /// let data: Vec<u8> = (0..1024 * 1024 * 3).map(|i| (i / 7 % 256) as u8).collect();
+    /// let mut image = CustomImage::new(1024, 1024, ColorType::Rgb, data.clone(), None, CompressionType::None)?;
+    /// ParallelImageProcessor::compress(&mut image, CompressionType::RLE)?;
+    /// assert_eq!(image.chunks.as_ref().unwrap().lengths.len(), data.len().div_ceil(CHUNK_SIZE));
+    ///
+    /// let serial = CustomImage::decompress(&image.data, 1024, 1024, ColorType::Rgb, CompressionType::RLE, image.lossy_quality)?;
+    /// ParallelImageProcessor::decompress(&mut image)?;
+    /// assert_eq!(image.data, serial);
+    /// assert_eq!(image.data, data);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
     pub fn decompress(image: &mut CustomImage) -> Result<(), FormatError> {
-        match image.compression {
-            CompressionType::None => Ok(()),
-            CompressionType::RLE => {
-                let decompressed = CustomImage::decompress_rle(&image.data)?;
-                image.data = decompressed;
-                image.compression = CompressionType::None;
-                Ok(())
-            }
-            CompressionType::Delta => {
-                let decompressed = CustomImage::decompress_delta(&image.data);
-                image.data = decompressed;
-                image.compression = CompressionType::None;
-                Ok(())
+        let start = Instant::now();
+        image.recompress_levels(image.compression, CompressionType::None)?;
+        if image.tiles.is_some() {
+            let mut decoded = image.decode_region(image.full_region())?;
+            decoded.levels = std::mem::take(&mut image.levels);
+            debug!(
+                "Decompressed tiled {:?} data: {} -> {} bytes in {:.2?}",
+                image.compression,
+                image.data.len(),
+                decoded.data.len(),
+                start.elapsed()
+            );
+            *image = decoded;
+            return Ok(());
+        }
+        if let Some(index) = image.chunks.take() {
+            let decompressed = Self::decompress_chunks(image, &index)?;
+            debug!(
+                "Decompressed {} {:?} chunks: {} -> {} bytes in {:.2?}",
+                index.lengths.len(),
+                image.compression,
+                image.data.len(),
+                decompressed.len(),
+                start.elapsed()
+            );
+            image.data = decompressed;
+            image.compression = CompressionType::None;
+            return Ok(());
+        }
+        let decompressed = match image.compression {
+            CompressionType::None => return Ok(()),
+            CompressionType::RLE => CustomImage::decompress_rle(&image.data)?,
+            CompressionType::Delta => CustomImage::decompress_delta(&image.data),
+            CompressionType::Lossy => CustomImage::decompress_lossy(
+                &image.data,
+                image.width,
+                image.height,
+                image.color_type,
+                image.lossy_quality,
+            )?,
+            CompressionType::RleVarint => {
+                let expected_len = image.width as usize
+                    * image.height as usize
+                    * image.color_type.channels() as usize;
+                CustomImage::decompress_rle_varint(&image.data, expected_len)?
             }
-            CompressionType::Lossy => {
-                let decompressed = CustomImage::decompress_lossy(
-                    &image.data,
-                    image.width,
-                    image.height,
-                    image.color_type,
-                    50
-                )?;
-                image.data = decompressed;
-                image.compression = CompressionType::None;
-                Ok(())
+        };
+        debug!(
+            "Decompressed {:?} data: {} -> {} bytes in {:.2?}",
+            image.compression,
+            image.data.len(),
+            decompressed.len(),
+            start.elapsed()
+        );
+        image.data = decompressed;
+        image.compression = CompressionType::None;
+        Ok(())
+    }
+
+    /// Re-encodes image data with a different compression type
+    ///
+    /// The pixels are decoded with `decompress` and encoded again with
+    /// `compress`, so no intermediate PNG is involved. Tiled images are
+    /// re-tiled at the same tile size, and pyramid levels and metadata are
+    /// kept. Since tiles are always compressed, a tiled image recompressed
+    /// to `None` is stored untiled.
+    ///
+    /// ```rust
+    /// use nor_image::format::{CustomImage, ColorType, CompressionType};
+    /// use nor_image::processing::ParallelImageProcessor;
+    ///
+    /// let data: Vec<u8> = (0..64 * 48).map(|i| (i / 5 % 256) as u8).collect();
+    /// let image = CustomImage::new(64, 48, ColorType::Gray, data.clone(), None, CompressionType::None)?;
+    /// let mut tiled = image.tiled(16, CompressionType::RLE)?;
+    /// ParallelImageProcessor::recompress(&mut tiled, CompressionType::Delta)?;
+    /// assert_eq!(tiled.compression, CompressionType::Delta);
+    /// assert_eq!(tiled.tiles.as_ref().map(|index| index.tile_size), Some(16));
+    /// assert_eq!(tiled.decode_region(tiled.full_region())?.data, data);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn recompress(image: &mut CustomImage, compression: CompressionType) -> Result<(), FormatError> {
+        let tile_size = image.tiles.as_ref().map(|index| index.tile_size);
+        Self::decompress(image)?;
+        match tile_size {
+            Some(tile_size) if compression != CompressionType::None => {
+                let levels = std::mem::take(&mut image.levels);
+                *image = image.tiled(tile_size, compression)?;
+                image.levels = levels;
+                image.recompress_levels(CompressionType::None, compression)?;
             }
+            _ => Self::compress(image, compression)?,
         }
+        Ok(())
     }
-} 
\ No newline at end of file
+
+    /// Decodes each chunk of chunked RLE data in parallel.
+    fn decompress_chunks(image: &CustomImage, index: &ChunkIndex) -> Result<Vec<u8>, FormatError> {
+        let total = image.width as usize * image.height as usize * image.color_type.channels() as usize;
+        let chunk_size = index.chunk_size as usize;
+        #[cfg(feature = "parallel")]
+        let ranges = index.ranges().into_par_iter();
+        #[cfg(not(feature = "parallel"))]
+        let ranges = index.ranges().into_iter();
+        let chunks = ranges
+            .enumerate()
+            .map(|(i, range)| {
+                let compressed = image.data.get(range).ok_or(FormatError::DataTooShort)?;
+                let expected_len = chunk_size.min(total - (i * chunk_size).min(total));
+                let chunk = match image.compression {
+                    CompressionType::RLE => CustomImage::decompress_rle_capped(compressed, expected_len)?,
+                    CompressionType::RleVarint => {
+                        CustomImage::decompress_rle_varint(compressed, expected_len)?
+                    }
+                    other => {
+                        return Err(FormatError::CompressionError(format!(
+                            "{:?} data cannot be chunked",
+                            other
+                        )))
+                    }
+                };
+                if chunk.len() != expected_len {
+                    return Err(FormatError::DataLengthMismatch {
+                        expected: expected_len,
+                        actual: chunk.len(),
+                    });
+                }
+                Ok(chunk)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(chunks.concat())
+    }
+} 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunk_size_sets_the_parallel_partitioning() {
+        let mut large = vec![1u8; 3 * CHUNK_SIZE];
+        let mut small = large.clone();
+        assert_eq!(process_chunks_mut(&mut large, CHUNK_SIZE, |chunk| chunk.fill(2)), 3);
+        assert_eq!(process_chunks_mut(&mut small, CHUNK_SIZE / 4, |chunk| chunk.fill(2)), 12);
+        assert_eq!(large, small);
+    }
+
+    #[test]
+    fn last_chunk_may_be_shorter() {
+        let mut data = vec![0u8; 10];
+        let mut sizes = std::sync::Mutex::new(Vec::new());
+        assert_eq!(process_chunks_mut(&mut data, 4, |chunk| sizes.lock().unwrap().push(chunk.len())), 3);
+        let sizes = sizes.get_mut().unwrap();
+        sizes.sort_unstable();
+        assert_eq!(sizes, &[2, 4, 4]);
+    }
+}
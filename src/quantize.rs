@@ -0,0 +1,198 @@
+// Copyright 2025 Grish
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Color quantization.
+//!
+//! Reduces the number of distinct colors in raw pixel data with median-cut:
+//! - The set of colors is repeatedly split along its widest channel
+//! - Each resulting box contributes its weighted average as a palette entry
+//! - Every pixel is snapped to the nearest palette entry
+//!
+//! The pixel layout is unchanged, so quantized RGB data is still stored as
+//! RGB rather than as palette indices.
+//!
+//! Ties are broken by a seed rather than by the histogram's iteration order:
+//! colors that share the value of the channel being split, and boxes with
+//! the same channel range, are ordered by a hash of the seed. The same
+//! input and seed always give the same output; another seed can move a
+//! split between tied colors.
+
+use std::collections::HashMap;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::format::ColorType;
+
+/// A distinct color and the number of pixels that use it.
+type ColorCount = ([u8; 3], u32);
+
+/// Reduces `data` to at most `max_colors` distinct colors in place, breaking
+/// ties between equally good splits with `seed`.
+///
+/// Data that already uses no more than `max_colors` colors is left
+/// untouched.
+///
+/// ```rust
+/// use nor_image::format::ColorType;
+/// use nor_image::quantize::quantize_colors;
+///
+/// let mut data: Vec<u8> = (0..=255).flat_map(|v| [v, 255 - v, 128]).collect();
+/// quantize_colors(&mut data, ColorType::Rgb, 4, 42);
+///
+/// let mut colors: Vec<&[u8]> = data.chunks_exact(3).collect();
+/// colors.sort();
+/// colors.dedup();
+/// assert_eq!(colors.len(), 4);
+///
+/// // Colors sharing a channel value split the same way on every run with
+/// // the same seed.
+/// let original: Vec<u8> = (0..4096u32).flat_map(|i| [(i % 5 * 60) as u8, (i / 5 % 7 * 40) as u8, (i % 251) as u8]).collect();
+/// let mut first = original.clone();
+/// quantize_colors(&mut first, ColorType::Rgb, 6, 7);
+/// for _ in 0..8 {
+///     let mut again = original.clone();
+///     quantize_colors(&mut again, ColorType::Rgb, 6, 7);
+///     assert_eq!(again, first);
+/// }
+/// ```
+pub fn quantize_colors(data: &mut [u8], color_type: ColorType, max_colors: u16, seed: u64) {
+    let channels = color_type.channels() as usize;
+    let max_colors = (max_colors as usize).max(1);
+
+    let mut histogram: HashMap<[u8; 3], u32> = HashMap::new();
+    for pixel in data.chunks_exact(channels) {
+        *histogram.entry(key(pixel)).or_insert(0) += 1;
+    }
+    if histogram.len() <= max_colors {
+        return;
+    }
+
+    let palette = median_cut(histogram.iter().map(|(&c, &n)| (c, n)).collect(), max_colors, seed);
+    #[cfg(feature = "parallel")]
+    let colors = histogram.par_iter();
+    #[cfg(not(feature = "parallel"))]
+    let colors = histogram.iter();
+    let mapping: HashMap<[u8; 3], [u8; 3]> = colors
+        .map(|(&color, _)| (color, nearest(&palette, color)))
+        .collect();
+
+    #[cfg(feature = "parallel")]
+    let pixels = data.par_chunks_exact_mut(channels);
+    #[cfg(not(feature = "parallel"))]
+    let pixels = data.chunks_exact_mut(channels);
+    pixels.for_each(|pixel| {
+        let snapped = mapping[&key(pixel)];
+        pixel.copy_from_slice(&snapped[..channels]);
+    });
+}
+
+/// Packs a gray or RGB pixel into a fixed-size key (unused channels are 0).
+fn key(pixel: &[u8]) -> [u8; 3] {
+    let mut key = [0; 3];
+    key[..pixel.len()].copy_from_slice(pixel);
+    key
+}
+
+/// Builds a palette of at most `max_colors` entries from a color histogram.
+fn median_cut(colors: Vec<ColorCount>, max_colors: usize, seed: u64) -> Vec<[u8; 3]> {
+    let mut boxes = vec![colors];
+    while boxes.len() < max_colors {
+        // Split the box with the widest channel range, picking among equal
+        // ranges by seed; stop once every box holds a single color.
+        let Some((index, channel, _)) = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, colors)| colors.len() > 1)
+            .map(|(i, colors)| {
+                let (channel, range) = widest_channel(colors);
+                (i, channel, range)
+            })
+            .max_by_key(|&(i, _, range)| (range, tie_break(seed, i as u64)))
+        else {
+            break;
+        };
+
+        let mut colors = boxes.swap_remove(index);
+        // Order colors sharing the channel value by seed, then by the whole
+        // color, so the split never depends on the histogram's iteration
+        // order.
+        colors.sort_unstable_by_key(|&(color, _)| {
+            let packed = u32::from_le_bytes([color[0], color[1], color[2], 0]);
+            (color[channel], tie_break(seed, packed as u64), color)
+        });
+        let total: u64 = colors.iter().map(|&(_, n)| n as u64).sum();
+        let mut seen = 0u64;
+        let mut split = colors.len() - 1;
+        for (i, &(_, n)) in colors.iter().enumerate() {
+            seen += n as u64;
+            if seen * 2 >= total {
+                split = i + 1;
+                break;
+            }
+        }
+        let upper = colors.split_off(split.clamp(1, colors.len() - 1));
+        boxes.push(colors);
+        boxes.push(upper);
+    }
+
+    boxes.iter().map(|colors| average(colors)).collect()
+}
+
+/// Mixes `seed` and `value` into a well-spread ordering key (SplitMix64).
+fn tie_break(seed: u64, value: u64) -> u64 {
+    let mut z = seed ^ value.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Returns the channel with the largest spread of values and that spread.
+fn widest_channel(colors: &[ColorCount]) -> (usize, u8) {
+    (0..3)
+        .map(|channel| {
+            let min = colors.iter().map(|(c, _)| c[channel]).min().unwrap_or(0);
+            let max = colors.iter().map(|(c, _)| c[channel]).max().unwrap_or(0);
+            (channel, max - min)
+        })
+        .max_by_key(|&(_, range)| range)
+        .unwrap_or((0, 0))
+}
+
+/// Returns the pixel-count weighted average of a box of colors.
+fn average(colors: &[ColorCount]) -> [u8; 3] {
+    let mut sums = [0u64; 3];
+    let mut total = 0u64;
+    for &(color, n) in colors {
+        for (sum, &value) in sums.iter_mut().zip(color.iter()) {
+            *sum += value as u64 * n as u64;
+        }
+        total += n as u64;
+    }
+    sums.map(|sum| ((sum + total / 2) / total.max(1)) as u8)
+}
+
+/// Returns the palette entry closest to `color` by squared distance.
+fn nearest(palette: &[[u8; 3]], color: [u8; 3]) -> [u8; 3] {
+    *palette
+        .iter()
+        .min_by_key(|entry| {
+            entry
+                .iter()
+                .zip(color.iter())
+                .map(|(&a, &b)| (a as i32 - b as i32).pow(2))
+                .sum::<i32>()
+        })
+        .expect("palette is never empty")
+}
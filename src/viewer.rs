@@ -12,9 +12,16 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use minifb::{Window, WindowOptions, Key, Scale, KeyRepeat, MouseButton};
-use crate::format::{CustomImage, ColorType};
+use minifb::{Window, WindowOptions, Key, Scale, KeyRepeat, MouseButton, MouseMode};
+use crate::adjust::{adjustment_lut, map_packed_rgb, ContrastCurve};
+use crate::converter::{png_to_custom, ConversionConfig};
+use crate::format::{CustomImage, ColorType, CompressionType, FormatError};
+use crate::processing::{CachedImageLoader, ParallelImageProcessor};
+use std::collections::VecDeque;
 use std::fs;
+use std::ops::Range;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use std::error::Error;
 
 // Zoom configuration constants.
@@ -22,30 +29,236 @@ const MIN_ZOOM: f32 = 0.1;
 const MAX_ZOOM: f32 = 10.0;
 const ZOOM_STEP: f32 = 0.1;
 const PANEL_WIDTH: usize = 200;
+const MIN_GAMMA: f32 = 0.1;
+const MAX_GAMMA: f32 = 5.0;
+const GAMMA_STEP: f32 = 0.1;
+// Maximum number of undo steps kept.
+const MAX_HISTORY: usize = 100;
+// Size of the checkerboard squares shown behind transparent pixels.
+const CHECKER_SIZE: usize = 8;
+// Sobel magnitude above which edge detection shows a pixel as an edge.
+const EDGE_THRESHOLD: u32 = 50;
+// Sobel magnitude shown at full brightness by the continuous edge modes.
+const EDGE_FULL_SCALE: u32 = 255;
+// Focus peaking threshold range and step; the Sobel magnitude of 8-bit luma
+// stays below 1443.
+const DEFAULT_PEAKING_THRESHOLD: u32 = 150;
+const MIN_PEAKING_THRESHOLD: u32 = 10;
+const MAX_PEAKING_THRESHOLD: u32 = 1000;
+const PEAKING_STEP: u32 = 10;
+// Color painted over in-focus pixels by focus peaking.
+const PEAKING_COLOR: u32 = 0xFF2020;
+// How long a confirmation stays in the window title.
+const NOTICE_DURATION: Duration = Duration::from_millis(1500);
+
+/// Snapshot of the adjustable view settings, used for undo/redo.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ViewState {
+    zoom: f32,
+    brightness: i32,
+    contrast: i32,
+    gamma: f32,
+    pan_x: f32,
+    pan_y: f32,
+    edge_detection: bool,
+    edge_mode: EdgeMode,
+    focus_peaking: bool,
+    peaking_threshold: u32,
+    palette: Palette,
+}
+
+/// How edge detection renders the Sobel gradient.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EdgeMode {
+    /// White where the magnitude exceeds `EDGE_THRESHOLD`, black elsewhere.
+    Binary,
+    /// Gray level proportional to the magnitude, for smooth edges.
+    Continuous,
+    /// Hue from the gradient direction, brightness from the magnitude.
+    Direction,
+}
+
+impl EdgeMode {
+    /// Returns the next edge mode in the cycle.
+    fn next(self) -> Self {
+        match self {
+            EdgeMode::Binary => EdgeMode::Continuous,
+            EdgeMode::Continuous => EdgeMode::Direction,
+            EdgeMode::Direction => EdgeMode::Binary,
+        }
+    }
+
+    /// Returns the packed RGB color of a pixel with Sobel gradient `(gx, gy)`.
+    fn color(self, (gx, gy): (i32, i32)) -> u32 {
+        let magnitude = ((gx * gx + gy * gy) as f32).sqrt() as u32;
+        let level = magnitude.min(EDGE_FULL_SCALE) * 255 / EDGE_FULL_SCALE;
+        match self {
+            EdgeMode::Binary if magnitude > EDGE_THRESHOLD => 0xFFFFFF,
+            EdgeMode::Binary => 0,
+            EdgeMode::Continuous => (level << 16) | (level << 8) | level,
+            EdgeMode::Direction => {
+                let hue = (gy as f32).atan2(gx as f32).to_degrees().rem_euclid(360.0);
+                let [r, g, b] = hue_rgb(hue).map(|c| (c * level as f32).round() as u32);
+                (r << 16) | (g << 8) | b
+            }
+        }
+    }
+}
+
+/// Returns the fully saturated color of `hue` (in degrees), with channels
+/// from 0.0 to 1.0.
+fn hue_rgb(hue: f32) -> [f32; 3] {
+    [0.0, 120.0, 240.0].map(|offset: f32| {
+        let distance = (hue - offset).rem_euclid(360.0);
+        let distance = distance.min(360.0 - distance);
+        (2.0 - distance / 60.0).clamp(0.0, 1.0)
+    })
+}
+
+/// How image pixels are sampled when drawn at a non-integer zoom.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Sampling {
+    /// Smooth interpolation between neighboring pixels (best for photos).
+    Bilinear,
+    /// Each screen pixel shows the closest image pixel (best for pixel art).
+    Nearest,
+}
+
+impl Sampling {
+    /// Returns the other sampling mode.
+    fn toggled(self) -> Self {
+        match self {
+            Sampling::Bilinear => Sampling::Nearest,
+            Sampling::Nearest => Sampling::Bilinear,
+        }
+    }
+}
+
+/// Color map that grayscale intensities are displayed through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Palette {
+    /// Plain gray levels (false color off).
+    Grayscale,
+    /// Perceptually uniform dark purple to yellow map.
+    Viridis,
+    /// Classic blue-cyan-yellow-red rainbow map.
+    Jet,
+}
+
+/// Viridis colors at evenly spaced intensities, interpolated in between.
+const VIRIDIS_STOPS: [[u8; 3]; 9] = [
+    [68, 1, 84],
+    [71, 44, 122],
+    [59, 81, 139],
+    [44, 113, 142],
+    [33, 144, 141],
+    [39, 173, 129],
+    [92, 200, 99],
+    [170, 220, 50],
+    [253, 231, 37],
+];
+
+impl Palette {
+    /// Returns the next palette in the cycle.
+    fn next(self) -> Self {
+        match self {
+            Palette::Grayscale => Palette::Viridis,
+            Palette::Viridis => Palette::Jet,
+            Palette::Jet => Palette::Grayscale,
+        }
+    }
+
+    /// Builds a table mapping each gray level to a packed RGB color.
+    fn lut(self) -> [u32; 256] {
+        let mut lut = [0u32; 256];
+        for (value, out) in lut.iter_mut().enumerate() {
+            let t = value as f32 / 255.0;
+            let [r, g, b] = match self {
+                Palette::Grayscale => [t; 3],
+                Palette::Viridis => {
+                    let pos = t * (VIRIDIS_STOPS.len() - 1) as f32;
+                    let i = (pos as usize).min(VIRIDIS_STOPS.len() - 2);
+                    let frac = pos - i as f32;
+                    let (lo, hi) = (VIRIDIS_STOPS[i], VIRIDIS_STOPS[i + 1]);
+                    [0, 1, 2].map(|c| (lo[c] as f32 + (hi[c] as f32 - lo[c] as f32) * frac) / 255.0)
+                }
+                Palette::Jet => [3.0, 2.0, 1.0].map(|offset: f32| (1.5 - (4.0 * t - offset).abs()).clamp(0.0, 1.0)),
+            };
+            let [r, g, b] = [r, g, b].map(|c| (c * 255.0).round() as u32);
+            *out = (r << 16) | (g << 8) | b;
+        }
+        lut
+    }
+}
+
+/// Adjustment that a typed value is applied to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EntryTarget {
+    Brightness,
+    Contrast,
+}
+
+/// A numeric value being typed in, shown in the title bar until applied.
+#[derive(Debug, Clone, PartialEq)]
+struct ValueEntry {
+    target: EntryTarget,
+    text: String,
+}
+
+/// A codec currently previewed in place of the original pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct CodecPreview {
+    compression: CompressionType,
+    /// Size of the encoded pixel data, in bytes.
+    size: usize,
+}
 
 /// A basic image viewer.
 pub struct ImageViewer {
     window: Window,
     buffer: Vec<u32>,          // Processed (adjusted) image data
     original_buffer: Vec<u32>, // Original image data (RGB)
+    straight_alpha: Option<(Vec<u32>, Vec<u8>)>, // Unblended colors and alpha, if transparent
+    source: CustomImage,       // Uncompressed image that codec previews encode
+    codec: Option<CodecPreview>, // Codec whose round trip is shown, if any
     width: usize,
     height: usize,
     zoom: f32,
     brightness: i32,
     contrast: i32,
+    gamma: f32,                // Gamma correction (>1.0 brightens midtones)
     color_type: ColorType,
     pan_x: f32,                // Pan offset as fraction (0.0 to 1.0)
     pan_y: f32,                // Pan offset as fraction (0.0 to 1.0)
     edge_detection: bool,
+    edge_mode: EdgeMode,       // How edge detection renders gradients
+    focus_peaking: bool,       // Tint sharp pixels over the image
+    peaking_threshold: u32,    // Sobel magnitude that counts as sharp
+    palette: Palette,          // False color map for grayscale images
+    palette_in_screenshots: bool, // Whether screenshots keep the false colors
+    sampling: Sampling,        // Display interpolation mode
     show_panel: bool,          // Toggle for side panel UI
+    entry: Option<ValueEntry>, // Value being typed in, if any
+    notice: Option<(String, Instant)>, // Confirmation shown in the title, and when it was set
+    clipboard: Clipboard,      // System clipboard for copied colors
+    undo_stack: VecDeque<ViewState>,
+    redo_stack: Vec<ViewState>,
 }
 
 impl ImageViewer {
-    /// Create a new viewer using the provided custom image.
-    /// The window size is set to the image dimensions.
+    /// Create a new viewer using the provided custom image, which is
+    /// decompressed first if needed. The window size is set to the image
+    /// dimensions.
     pub fn new(custom_image: CustomImage) -> Result<Self, Box<dyn Error>> {
         let width = custom_image.width as usize;
         let height = custom_image.height as usize;
+        let color_type = custom_image.color_type;
+        let mut custom_image = custom_image;
+        ParallelImageProcessor::decompress(&mut custom_image)?;
+        let straight_alpha = Self::split_alpha(&custom_image);
+        // Convert the custom image's data into a u32 RGB buffer before
+        // opening a window, so bad pixel data fails early.
+        let original_buffer = display_buffer(custom_image.clone())?;
         
         // Create the window with dimensions equal to the image.
         // (The window can later be resized by the user.)
@@ -63,23 +276,35 @@ impl ImageViewer {
         // Limit FPS (~60 FPS)
         window.limit_update_rate(Some(std::time::Duration::from_micros(16_600)));
 
-        // Convert the custom image's data into a u32 RGB buffer.
-        let original_buffer = Self::convert_to_rgb(&custom_image);
-
         let mut viewer = ImageViewer {
             window,
             buffer: original_buffer.clone(),
             original_buffer,
+            straight_alpha,
+            source: custom_image,
+            codec: None,
             width,
             height,
             zoom: 1.0,
             brightness: 0,
             contrast: 0,
-            color_type: custom_image.color_type,
+            gamma: 1.0,
+            color_type,
             pan_x: 0.0,
             pan_y: 0.0,
             edge_detection: false,
+            edge_mode: EdgeMode::Binary,
+            focus_peaking: false,
+            peaking_threshold: DEFAULT_PEAKING_THRESHOLD,
+            palette: Palette::Grayscale,
+            palette_in_screenshots: false,
+            sampling: Sampling::Bilinear,
             show_panel: false,
+            entry: None,
+            notice: None,
+            clipboard: Clipboard::default(),
+            undo_stack: VecDeque::new(),
+            redo_stack: Vec::new(),
         };
 
         // Apply initial adjustments and render.
@@ -88,14 +313,15 @@ impl ImageViewer {
         Ok(viewer)
     }
 
-    /// Converts the custom image pixel data to a 32-bit RGB buffer.
+    /// Converts uncompressed pixel data of the expected length to a 32-bit
+    /// RGB buffer.
     fn convert_to_rgb(image: &CustomImage) -> Vec<u32> {
         let mut buffer = vec![0u32; (image.width as usize) * (image.height as usize)];
         match image.color_type {
             ColorType::Gray => {
-                for i in 0..buffer.len() {
+                for (i, out) in buffer.iter_mut().enumerate() {
                     let pixel = image.data[i] as u32;
-                    buffer[i] = (pixel << 16) | (pixel << 8) | pixel;
+                    *out = (pixel << 16) | (pixel << 8) | pixel;
                 }
             }
             ColorType::Rgb => {
@@ -106,45 +332,138 @@ impl ImageViewer {
                     buffer[i] = (r << 16) | (g << 8) | b;
                 }
             }
+            ColorType::GrayAlpha => {
+                // Composite onto a checkerboard so transparent areas stay visible.
+                let width = image.width as usize;
+                for (i, chunk) in image.data.chunks_exact(2).enumerate() {
+                    let (x, y) = (i % width, i / width);
+                    let backdrop = if (x / CHECKER_SIZE + y / CHECKER_SIZE).is_multiple_of(2) { 0xCC } else { 0x99 };
+                    let alpha = chunk[1] as u32;
+                    let value = (chunk[0] as u32 * alpha + backdrop * (255 - alpha) + 127) / 255;
+                    buffer[i] = (value << 16) | (value << 8) | value;
+                }
+            }
         }
         buffer
     }
 
-    /// Applies brightness and contrast adjustments (or edge detection) to the image.
+    /// Splits an uncompressed image with alpha into packed RGB colors (not
+    /// composited onto the checkerboard) and its alpha plane.
+    fn split_alpha(image: &CustomImage) -> Option<(Vec<u32>, Vec<u8>)> {
+        match image.color_type {
+            ColorType::GrayAlpha => Some(
+                image
+                    .data
+                    .chunks_exact(2)
+                    .map(|chunk| {
+                        let value = chunk[0] as u32;
+                        ((value << 16) | (value << 8) | value, chunk[1])
+                    })
+                    .unzip(),
+            ),
+            ColorType::Gray | ColorType::Rgb => None,
+        }
+    }
+
+    /// Builds a lookup table mapping each channel value through the gamma curve.
+    fn gamma_lut(&self) -> [u8; 256] {
+        let mut lut = [0u8; 256];
+        let exponent = 1.0 / self.gamma;
+        for (value, out) in lut.iter_mut().enumerate() {
+            *out = (255.0 * (value as f32 / 255.0).powf(exponent)).round() as u8;
+        }
+        lut
+    }
+
+    /// Applies brightness, contrast and gamma adjustments (or edge detection)
+    /// to the image, then the false color palette for grayscale images and
+    /// the focus peaking overlay.
     fn apply_adjustments(&mut self) {
-        self.buffer = self.original_buffer.clone();
         if self.edge_detection {
+            self.buffer = self.original_buffer.clone();
             self.apply_edge_detection();
             return;
         }
-        for pixel in self.buffer.iter_mut() {
-            let r = (((*pixel >> 16) & 0xFF) as i32 + self.brightness).clamp(0, 255);
-            let g = (((*pixel >> 8) & 0xFF) as i32 + self.brightness).clamp(0, 255);
-            let b = (((*pixel) & 0xFF) as i32 + self.brightness).clamp(0, 255);
-            let contrast = self.contrast.clamp(-255, 255);
-            let factor = (259.0 * (contrast as f32 + 255.0)) / (255.0 * (259.0 - contrast as f32));
-            let r_adj = (factor * (r as f32 - 128.0) + 128.0).clamp(0.0, 255.0) as u32;
-            let g_adj = (factor * (g as f32 - 128.0) + 128.0).clamp(0.0, 255.0) as u32;
-            let b_adj = (factor * (b as f32 - 128.0) + 128.0).clamp(0.0, 255.0) as u32;
-            *pixel = (r_adj << 16) | (g_adj << 8) | b_adj;
+        self.buffer = self.adjusted_buffer(true);
+        if self.focus_peaking {
+            self.apply_focus_peaking();
         }
     }
 
-    /// Applies a Sobel edge detection filter.
+    /// Returns the original pixels with the display adjustments applied,
+    /// mapped through the palette if `false_color` is set and the image is
+    /// grayscale.
+    fn adjusted_buffer(&self, false_color: bool) -> Vec<u32> {
+        let mut buffer = self.original_buffer.clone();
+        map_packed_rgb(&mut buffer, &self.display_lut());
+        if false_color && self.false_color_active() {
+            let palette = self.palette.lut();
+            for pixel in &mut buffer {
+                *pixel = palette[(*pixel & 0xFF) as usize];
+            }
+        }
+        buffer
+    }
+
+    /// Whether a false color palette is shown; it only applies to
+    /// grayscale images.
+    fn false_color_active(&self) -> bool {
+        self.color_type == ColorType::Gray && self.palette != Palette::Grayscale
+    }
+
+    /// Same brightness/contrast mapping as the converter, followed by gamma.
+    fn display_lut(&self) -> [u8; 256] {
+        let gamma = self.gamma_lut();
+        adjustment_lut(self.brightness, self.contrast, ContrastCurve::Linear).map(|value| gamma[value as usize])
+    }
+
+    /// Applies a Sobel edge detection filter, rendered by `edge_mode`.
     fn apply_edge_detection(&mut self) {
+        self.buffer = self
+            .sobel_gradients()
+            .into_iter()
+            .map(|gradient| self.edge_mode.color(gradient))
+            .collect();
+    }
+
+    /// Paints pixels whose Sobel magnitude exceeds the peaking threshold
+    /// with `PEAKING_COLOR`, leaving the rest of the adjusted image as is.
+    fn apply_focus_peaking(&mut self) {
+        let magnitudes = self.sobel_magnitudes();
+        for (pixel, magnitude) in self.buffer.iter_mut().zip(magnitudes) {
+            if magnitude > self.peaking_threshold {
+                *pixel = PEAKING_COLOR;
+            }
+        }
+    }
+
+    /// Returns the Sobel gradient magnitude of each pixel's luma, after
+    /// brightness and gamma; border pixels are 0.
+    fn sobel_magnitudes(&self) -> Vec<u32> {
+        self.sobel_gradients()
+            .into_iter()
+            .map(|(gx, gy)| ((gx * gx + gy * gy) as f32).sqrt() as u32)
+            .collect()
+    }
+
+    /// Returns the horizontal and vertical Sobel gradients of each pixel's
+    /// luma, after brightness and gamma; border pixels are `(0, 0)`.
+    fn sobel_gradients(&self) -> Vec<(i32, i32)> {
+        let lut = self.gamma_lut();
         let mut grayscale = vec![0u8; self.width * self.height];
         for (i, &pixel) in self.original_buffer.iter().enumerate() {
-            let r = ((pixel >> 16) & 0xFF) as u32;
-            let g = ((pixel >> 8) & 0xFF) as u32;
-            let b = (pixel & 0xFF) as u32;
-            grayscale[i] = ((0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32) as i32 + self.brightness)
-                          .clamp(0, 255) as u8;
+            let r = (pixel >> 16) & 0xFF;
+            let g = (pixel >> 8) & 0xFF;
+            let b = pixel & 0xFF;
+            let luma = ((0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32) as i32 + self.brightness)
+                          .clamp(0, 255) as usize;
+            grayscale[i] = lut[luma];
         }
         let sobel_x = [[-1, 0, 1], [-2, 0, 2], [-1, 0, 1]];
         let sobel_y = [[-1, -2, -1], [0, 0, 0], [1, 2, 1]];
-        let mut edges = vec![0u32; self.width * self.height];
-        for y in 1..(self.height - 1) {
-            for x in 1..(self.width - 1) {
+        let mut gradients = vec![(0, 0); self.width * self.height];
+        for y in 1..self.height.saturating_sub(1) {
+            for x in 1..self.width.saturating_sub(1) {
                 let mut gx = 0;
                 let mut gy = 0;
                 for ky in 0..3 {
@@ -154,19 +473,10 @@ impl ImageViewer {
                         gy += pixel * sobel_y[ky][kx];
                     }
                 }
-                let magnitude = ((gx * gx + gy * gy) as f32).sqrt() as u32;
-                let edge = if magnitude > 50 { 255 } else { 0 };
-                edges[y * self.width + x] = (edge << 16) | (edge << 8) | edge;
+                gradients[y * self.width + x] = (gx, gy);
             }
         }
-        self.buffer = edges;
-    }
-
-    /// Performs bilinear interpolation on one channel.
-    fn bilinear_interpolate(p00: u32, p10: u32, p01: u32, p11: u32, fx: f32, fy: f32) -> u32 {
-        let interp0 = p00 as f32 * (1.0 - fx) + p10 as f32 * fx;
-        let interp1 = p01 as f32 * (1.0 - fx) + p11 as f32 * fx;
-        (interp0 * (1.0 - fy) + interp1 * fy).round() as u32
+        gradients
     }
 
     /// Updates the window buffer by scaling, panning, and interpolating.
@@ -176,52 +486,26 @@ impl ImageViewer {
         // Determine panel width if enabled.
         let panel_width = if self.show_panel { PANEL_WIDTH } else { 0 };
         // Update window title with overlay information.
-        let overlay = format!(
-            "Zoom: {:.1}x | Brightness: {} | Contrast: {} | Edge: {} | Panel: {}",
-            self.zoom,
-            self.brightness,
-            self.contrast,
-            if self.edge_detection { "On" } else { "Off" },
-            if self.show_panel { "On" } else { "Off" }
-        );
-        self.window.set_title(&format!("Image Viewer - {}", overlay));
-
-        let scaled_width = (self.width as f32 * self.zoom) as usize;
-        let scaled_height = (self.height as f32 * self.zoom) as usize;
+        self.window.set_title(&self.title());
 
-        // Calculate maximum pan offsets.
-        let max_pan_x = if scaled_width > win_width - panel_width { scaled_width as i32 - (win_width - panel_width) as i32 } else { 0 };
-        let max_pan_y = if scaled_height > win_height { scaled_height as i32 - win_height as i32 } else { 0 };
-        let offset_x = ((self.pan_x * scaled_width as f32) as i32).clamp(0, max_pan_x);
-        let offset_y = ((self.pan_y * scaled_height as f32) as i32).clamp(0, max_pan_y);
+        let view_width = win_width - panel_width;
+        let offset_x = pan_offset(self.pan_x, self.width, self.zoom, view_width);
+        let offset_y = pan_offset(self.pan_y, self.height, self.zoom, win_height);
 
         let mut new_buffer = vec![0u32; win_width * win_height];
 
         // Draw the main image (only in the area left of the side panel, if active).
         for win_y in 0..win_height {
-            for win_x in 0..(win_width - panel_width) {
+            for win_x in 0..view_width {
                 let img_x = (win_x as i32 + offset_x) as f32 / self.zoom;
                 let img_y = (win_y as i32 + offset_y) as f32 / self.zoom;
-                if img_x < 0.0 || img_y < 0.0 || img_x >= (self.width - 1) as f32 || img_y >= (self.height - 1) as f32 {
-                    continue;
+                let pixel = match self.sampling {
+                    Sampling::Bilinear => sample_bilinear(&self.buffer, self.width, self.height, img_x, img_y),
+                    Sampling::Nearest => sample_nearest(&self.buffer, self.width, self.height, img_x, img_y),
+                };
+                if let Some(pixel) = pixel {
+                    new_buffer[win_y * win_width + win_x] = pixel;
                 }
-                let x0 = img_x.floor() as usize;
-                let y0 = img_y.floor() as usize;
-                let x1 = (x0 + 1).min(self.width - 1);
-                let y1 = (y0 + 1).min(self.height - 1);
-                let fx = img_x - x0 as f32;
-                let fy = img_y - y0 as f32;
-                let p00 = self.buffer[y0 * self.width + x0];
-                let p10 = self.buffer[y0 * self.width + x1];
-                let p01 = self.buffer[y1 * self.width + x0];
-                let p11 = self.buffer[y1 * self.width + x1];
-                let r = Self::bilinear_interpolate((p00 >> 16) & 0xFF, (p10 >> 16) & 0xFF,
-                                                   (p01 >> 16) & 0xFF, (p11 >> 16) & 0xFF, fx, fy);
-                let g = Self::bilinear_interpolate((p00 >> 8) & 0xFF, (p10 >> 8) & 0xFF,
-                                                   (p01 >> 8) & 0xFF, (p11 >> 8) & 0xFF, fx, fy);
-                let b = Self::bilinear_interpolate(p00 & 0xFF, p10 & 0xFF,
-                                                   p01 & 0xFF, p11 & 0xFF, fx, fy);
-                new_buffer[win_y * win_width + win_x] = (r << 16) | (g << 8) | b;
             }
         }
 
@@ -235,8 +519,201 @@ impl ImageViewer {
         Ok(())
     }
 
+    /// Returns the window title: the current settings, or the value being
+    /// typed in while in entry mode.
+    fn title(&self) -> String {
+        if let Some(entry) = &self.entry {
+            return format!(
+                "Image Viewer - Set {:?}: {}_ (Enter to apply, Esc to cancel)",
+                entry.target, entry.text
+            );
+        }
+        let codec = match self.codec {
+            Some(preview) => format!(
+                " | Codec: {:?} {} B ({:.2}x)",
+                preview.compression,
+                preview.size,
+                self.source.data.len() as f64 / preview.size.max(1) as f64
+            ),
+            None => String::new(),
+        };
+        let notice = match &self.notice {
+            Some((text, _)) => format!(" | {}", text),
+            None => String::new(),
+        };
+        format!(
+            "Image Viewer - Zoom: {:.1}x | Brightness: {} | Contrast: {} | Gamma: {:.1} | Edge: {} | Peaking: {} | Palette: {:?} | Sampling: {:?} | Panel: {}{}{}",
+            self.zoom,
+            self.brightness,
+            self.contrast,
+            self.gamma,
+            self.edge_label(),
+            self.peaking_label(),
+            self.palette,
+            self.sampling,
+            if self.show_panel { "On" } else { "Off" },
+            codec,
+            notice
+        )
+    }
+
+    /// Shows `text` in the window title for `NOTICE_DURATION`.
+    fn notify(&mut self, text: impl Into<String>) {
+        self.notice = Some((text.into(), Instant::now()));
+    }
+
+    /// Returns the color of the image pixel under the cursor, or the average
+    /// color of the visible part of the image when the cursor is not over
+    /// it, along with a description of where it was sampled.
+    ///
+    /// Colors are the image's own, before display adjustments, false colors
+    /// and checkerboard blending. Returns `None` when no pixel is visible.
+    fn sampled_color(&self) -> Option<(u32, String)> {
+        let colors = match &self.straight_alpha {
+            Some((colors, _)) => colors,
+            None => &self.original_buffer,
+        };
+        let (win_width, win_height) = self.window.get_size();
+        let panel_width = if self.show_panel { PANEL_WIDTH } else { 0 };
+        let view_width = win_width.saturating_sub(panel_width);
+        let offset_x = pan_offset(self.pan_x, self.width, self.zoom, view_width);
+        let offset_y = pan_offset(self.pan_y, self.height, self.zoom, win_height);
+
+        let hovered = self.window.get_mouse_pos(MouseMode::Discard)
+            .filter(|&(win_x, _)| win_x < view_width as f32)
+            .map(|(win_x, win_y)| {
+                let img_x = ((win_x + offset_x as f32) / self.zoom).floor();
+                let img_y = ((win_y + offset_y as f32) / self.zoom).floor();
+                (img_x, img_y)
+            })
+            .filter(|&(x, y)| x >= 0.0 && y >= 0.0 && (x as usize) < self.width && (y as usize) < self.height);
+        if let Some((x, y)) = hovered {
+            let (x, y) = (x as usize, y as usize);
+            return Some((colors[y * self.width + x], format!("pixel {}, {}", x, y)));
+        }
+
+        let visible = |offset: i32, view_len: usize, image_len: usize| {
+            let start = ((offset as f32 / self.zoom) as usize).min(image_len);
+            let end = (((offset as usize + view_len) as f32 / self.zoom).ceil() as usize).min(image_len);
+            start..end
+        };
+        let xs = visible(offset_x, view_width, self.width);
+        let ys = visible(offset_y, win_height, self.height);
+        average_color(colors, self.width, xs, ys).map(|color| (color, "visible average".to_string()))
+    }
+
+    /// Copies the hex color sampled by `sampled_color` to the clipboard and
+    /// confirms it in the title.
+    fn copy_color(&mut self) {
+        let Some((color, source)) = self.sampled_color() else {
+            self.notify("Nothing to sample");
+            return;
+        };
+        let hex = format!("#{:06X}", color & 0xFFFFFF);
+        println!("{} ({})", hex, source);
+        match self.clipboard.set_text(&hex) {
+            Ok(()) => self.notify(format!("Copied {} ({})", hex, source)),
+            Err(e) => {
+                eprintln!("Failed to copy to the clipboard: {}", e);
+                self.notify(format!("{} ({}); copy failed", hex, source));
+            }
+        }
+    }
+
+    /// Describes the edge detection setting: its mode, or "Off".
+    fn edge_label(&self) -> String {
+        if self.edge_detection {
+            format!("{:?}", self.edge_mode)
+        } else {
+            "Off".to_string()
+        }
+    }
+
+    /// Describes the focus peaking setting: its threshold, or "Off".
+    fn peaking_label(&self) -> String {
+        if self.focus_peaking {
+            format!(">{}", self.peaking_threshold)
+        } else {
+            "Off".to_string()
+        }
+    }
+
+    /// Switches to previewing the next codec in `CompressionType::all()`,
+    /// going back to the original pixels after the last one.
+    ///
+    /// The source pixels are encoded and decoded again with the format's own
+    /// codecs, so the preview shows exactly what a file with that
+    /// compression would contain.
+    fn cycle_codec(&mut self) -> Result<(), FormatError> {
+        let codecs = CompressionType::all();
+        let next = match self.codec {
+            None => codecs.first(),
+            Some(preview) => codecs
+                .iter()
+                .position(|&c| c == preview.compression)
+                .and_then(|i| codecs.get(i + 1)),
+        };
+
+        let shown = match next {
+            Some(&compression) => {
+                let encoded = self.source.compress(compression)?;
+                let data = self.source.decompress_pixels(
+                    &encoded,
+                    self.source.width,
+                    self.source.height,
+                    compression,
+                )?;
+                self.codec = Some(CodecPreview { compression, size: encoded.len() });
+                CustomImage { data, ..self.source.clone() }
+            }
+            None => {
+                self.codec = None;
+                self.source.clone()
+            }
+        };
+        self.straight_alpha = Self::split_alpha(&shown);
+        self.original_buffer = display_buffer(shown)?;
+        Ok(())
+    }
+
+    /// Handles a key press while a value is being typed in.
+    ///
+    /// Digits and a leading minus sign are collected; Enter applies the value
+    /// (clamped to -255..=255), Backspace deletes, and Escape cancels. Returns
+    /// `true` if an adjustment changed.
+    fn handle_entry_key(&mut self, key: Key) -> bool {
+        let Some(entry) = &mut self.entry else {
+            return false;
+        };
+        match key {
+            Key::Minus | Key::NumPadMinus if entry.text.is_empty() => entry.text.push('-'),
+            Key::Backspace => {
+                entry.text.pop();
+            }
+            Key::Escape => self.entry = None,
+            Key::Enter | Key::NumPadEnter => {
+                let value = entry.text.parse::<i32>().ok().map(|value| value.clamp(-255, 255));
+                let target = entry.target;
+                self.entry = None;
+                match (target, value) {
+                    (EntryTarget::Brightness, Some(value)) => self.brightness = value,
+                    (EntryTarget::Contrast, Some(value)) => self.contrast = value,
+                    (_, None) => return false,
+                }
+                return true;
+            }
+            key => {
+                // Cap the length so the title stays readable.
+                if let Some(digit) = key_digit(key).filter(|_| entry.text.len() < 4) {
+                    entry.text.push(digit);
+                }
+            }
+        }
+        false
+    }
+
     /// Draws a simple side panel with colored status bars for controls.
-    fn draw_side_panel(&self, buffer: &mut Vec<u32>, win_width: usize, win_height: usize) {
+    fn draw_side_panel(&self, buffer: &mut [u32], win_width: usize, win_height: usize) {
         let start = win_width - PANEL_WIDTH;
         // Fill panel background.
         for y in 0..win_height {
@@ -277,9 +754,20 @@ impl ImageViewer {
                 }
             }
         }
+        // Draw a gamma bar.
+        let gamma_norm = ((self.gamma - MIN_GAMMA) / (MAX_GAMMA - MIN_GAMMA)) * ((PANEL_WIDTH - 20) as f32);
+        let gamma_bar_length = gamma_norm as usize;
+        let gamma_y = contrast_y + bar_height + 10;
+        for y in gamma_y..(gamma_y + bar_height) {
+            for x in (start + 10)..(start + 10 + gamma_bar_length) {
+                if x < win_width && y < win_height {
+                    buffer[y * win_width + x] = 0xFF00FF; // magenta for gamma
+                }
+            }
+        }
         // Draw an indicator for edge detection.
         let edge_color = if self.edge_detection { 0x00FF00 } else { 0xFF0000 };
-        let edge_y = contrast_y + bar_height + 10;
+        let edge_y = gamma_y + bar_height + 10;
         for y in edge_y..(edge_y + 20) {
             for x in (start + 10)..(start + 30) {
                 if x < win_width && y < win_height {
@@ -289,11 +777,104 @@ impl ImageViewer {
         }
     }
 
+    /// Captures the current adjustment state.
+    fn view_state(&self) -> ViewState {
+        ViewState {
+            zoom: self.zoom,
+            brightness: self.brightness,
+            contrast: self.contrast,
+            gamma: self.gamma,
+            pan_x: self.pan_x,
+            pan_y: self.pan_y,
+            edge_detection: self.edge_detection,
+            edge_mode: self.edge_mode,
+            focus_peaking: self.focus_peaking,
+            peaking_threshold: self.peaking_threshold,
+            palette: self.palette,
+        }
+    }
+
+    /// Restores a previously captured adjustment state.
+    fn restore_view_state(&mut self, state: ViewState) {
+        self.zoom = state.zoom;
+        self.brightness = state.brightness;
+        self.contrast = state.contrast;
+        self.gamma = state.gamma;
+        self.pan_x = state.pan_x;
+        self.pan_y = state.pan_y;
+        self.edge_detection = state.edge_detection;
+        self.edge_mode = state.edge_mode;
+        self.focus_peaking = state.focus_peaking;
+        self.peaking_threshold = state.peaking_threshold;
+        self.palette = state.palette;
+    }
+
+    /// Records the state before a change, dropping the oldest entry once the
+    /// history is full. Any redo history is discarded.
+    fn push_history(&mut self, previous: ViewState) {
+        if self.undo_stack.len() == MAX_HISTORY {
+            self.undo_stack.pop_front();
+        }
+        self.undo_stack.push_back(previous);
+        self.redo_stack.clear();
+    }
+
+    /// Steps back to the previous state. Returns `false` if there is nothing to undo.
+    fn undo(&mut self) -> bool {
+        match self.undo_stack.pop_back() {
+            Some(state) => {
+                self.redo_stack.push(self.view_state());
+                self.restore_view_state(state);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Re-applies the last undone state. Returns `false` if there is nothing to redo.
+    fn redo(&mut self) -> bool {
+        match self.redo_stack.pop() {
+            Some(state) => {
+                self.undo_stack.push_back(self.view_state());
+                self.restore_view_state(state);
+                true
+            }
+            None => false,
+        }
+    }
+
     /// Saves the current view as a PNG screenshot using the image crate.
+    ///
+    /// Transparent images are saved as RGBA with their adjusted colors and
+    /// original alpha; opaque images and edge detection are saved as RGB.
+    /// False colors are left out unless `palette_in_screenshots` is set.
     fn save_screenshot(&self) -> Result<(), Box<dyn Error>> {
+        if let (Some((colors, alpha)), false) = (&self.straight_alpha, self.edge_detection) {
+            let mut colors = colors.clone();
+            map_packed_rgb(&mut colors, &self.display_lut());
+            let mut imgbuf = image::RgbaImage::new(self.width as u32, self.height as u32);
+            for (i, (pixel, &a)) in colors.iter().zip(alpha).enumerate() {
+                let r = ((pixel >> 16) & 0xFF) as u8;
+                let g = ((pixel >> 8) & 0xFF) as u8;
+                let b = (pixel & 0xFF) as u8;
+                let x = (i % self.width) as u32;
+                let y = (i / self.width) as u32;
+                imgbuf.put_pixel(x, y, image::Rgba([r, g, b, a]));
+            }
+            imgbuf.save("screenshot.png")?;
+            return Ok(());
+        }
+
         // Save the original adjusted buffer (at image resolution).
+        let plain;
+        let buffer = if self.false_color_active() && !self.palette_in_screenshots && !self.edge_detection {
+            plain = self.adjusted_buffer(false);
+            &plain
+        } else {
+            &self.buffer
+        };
         let mut imgbuf = image::RgbImage::new(self.width as u32, self.height as u32);
-        for (i, pixel) in self.buffer.iter().enumerate() {
+        for (i, pixel) in buffer.iter().enumerate() {
             let r = ((pixel >> 16) & 0xFF) as u8;
             let g = ((pixel >> 8) & 0xFF) as u8;
             let b = (pixel & 0xFF) as u8;
@@ -310,31 +891,101 @@ impl ImageViewer {
         self.show_help();
         let mut last_win_size = self.window.get_size();
         let mut last_mouse_pos: Option<(f32, f32)> = None;
+        // State when the current drag started, recorded as one undo step on release.
+        let mut drag_start: Option<ViewState> = None;
 
-        while self.window.is_open() && !self.window.is_key_down(Key::Escape) {
+        let mut quit = false;
+        while self.window.is_open() && !quit {
             let mut needs_update = false;
+            let frame_start = self.view_state();
+            let mut history_changed = false;
+            let ctrl = self.window.is_key_down(Key::LeftCtrl) || self.window.is_key_down(Key::RightCtrl);
+            let shift = self.window.is_key_down(Key::LeftShift) || self.window.is_key_down(Key::RightShift);
 
             // Process keyboard input.
             for key in self.window.get_keys_pressed(KeyRepeat::Yes) {
+                // While typing a value, keys edit the entry instead.
+                if self.entry.is_some() {
+                    needs_update |= self.handle_entry_key(key);
+                    self.window.set_title(&self.title());
+                    continue;
+                }
                 match key {
+                    Key::Escape => quit = true,
+                    Key::C if ctrl => { self.copy_color(); needs_update = true; }
+                    Key::B | Key::C => {
+                        let target = if key == Key::B { EntryTarget::Brightness } else { EntryTarget::Contrast };
+                        self.entry = Some(ValueEntry { target, text: String::new() });
+                        self.window.set_title(&self.title());
+                    }
+                    Key::U => { history_changed |= self.undo(); needs_update = true; }
+                    Key::Z if ctrl => { history_changed |= self.undo(); needs_update = true; }
+                    Key::Y => { history_changed |= self.redo(); needs_update = true; }
                     Key::H => self.show_help(),
                     Key::I => self.show_info(),
+                    Key::E if shift => {
+                        self.edge_mode = self.edge_mode.next();
+                        self.edge_detection = true;
+                        needs_update = true;
+                    }
                     Key::E => { self.edge_detection = !self.edge_detection; needs_update = true; }
+                    Key::O => { self.focus_peaking = !self.focus_peaking; needs_update = true; }
+                    Key::LeftBracket if self.focus_peaking => {
+                        self.peaking_threshold = self.peaking_threshold.saturating_sub(PEAKING_STEP).max(MIN_PEAKING_THRESHOLD);
+                        needs_update = true;
+                    }
+                    Key::RightBracket if self.focus_peaking => {
+                        self.peaking_threshold = (self.peaking_threshold + PEAKING_STEP).min(MAX_PEAKING_THRESHOLD);
+                        needs_update = true;
+                    }
+                    Key::N => { self.sampling = self.sampling.toggled(); needs_update = true; }
+                    Key::F if self.color_type == ColorType::Gray => { self.palette = self.palette.next(); needs_update = true; }
+                    Key::F => println!("False color is only available for grayscale images"),
+                    Key::M => {
+                        self.palette_in_screenshots = !self.palette_in_screenshots;
+                        println!(
+                            "Screenshots {} false colors",
+                            if self.palette_in_screenshots { "include" } else { "leave out" }
+                        );
+                    }
                     Key::R => {
                         self.brightness = 0;
                         self.contrast = 0;
+                        self.gamma = 1.0;
                         self.zoom = 1.0;
                         self.pan_x = 0.0;
                         self.pan_y = 0.0;
                         self.edge_detection = false;
+                        self.edge_mode = EdgeMode::Binary;
+                        self.focus_peaking = false;
+                        self.peaking_threshold = DEFAULT_PEAKING_THRESHOLD;
+                        self.palette = Palette::Grayscale;
                         needs_update = true;
                     }
                     Key::Equal | Key::NumPadPlus => { self.zoom = (self.zoom + ZOOM_STEP).min(MAX_ZOOM); needs_update = true; }
                     Key::Minus | Key::NumPadMinus => { self.zoom = (self.zoom - ZOOM_STEP).max(MIN_ZOOM); needs_update = true; }
+                    // Shift resets a single adjustment, keeping zoom and pan.
+                    Key::Up | Key::Down if shift => {
+                        self.brightness = 0;
+                        self.notify("Brightness reset");
+                        needs_update = true;
+                    }
+                    Key::Left | Key::Right if shift => {
+                        self.contrast = 0;
+                        self.notify("Contrast reset");
+                        needs_update = true;
+                    }
+                    Key::Comma | Key::Period if shift => {
+                        self.gamma = 1.0;
+                        self.notify("Gamma reset");
+                        needs_update = true;
+                    }
                     Key::Up => { self.brightness = (self.brightness + 5).min(255); needs_update = true; }
                     Key::Down => { self.brightness = (self.brightness - 5).max(-255); needs_update = true; }
                     Key::Right => { self.contrast = (self.contrast + 5).min(255); needs_update = true; }
                     Key::Left => { self.contrast = (self.contrast - 5).max(-255); needs_update = true; }
+                    Key::Period => { self.gamma = (self.gamma + GAMMA_STEP).min(MAX_GAMMA); needs_update = true; }
+                    Key::Comma => { self.gamma = (self.gamma - GAMMA_STEP).max(MIN_GAMMA); needs_update = true; }
                     Key::S => {
                         if let Err(e) = self.save_screenshot() {
                             eprintln!("Failed to save screenshot: {}", e);
@@ -343,6 +994,10 @@ impl ImageViewer {
                         }
                     }
                     Key::P => { self.show_panel = !self.show_panel; needs_update = true; }
+                    Key::K => match self.cycle_codec() {
+                        Ok(()) => needs_update = true,
+                        Err(e) => eprintln!("Failed to preview codec: {}", e),
+                    },
                     _ => {}
                 }
             }
@@ -356,19 +1011,34 @@ impl ImageViewer {
             }
 
             // Handle mouse dragging for panning.
-            if self.window.get_mouse_down(MouseButton::Left) {
+            let dragging = self.window.get_mouse_down(MouseButton::Left);
+            if dragging {
+                drag_start.get_or_insert(frame_start);
                 if let Some((cur_x, cur_y)) = self.window.get_mouse_pos(minifb::MouseMode::Discard) {
                     if let Some((last_x, last_y)) = last_mouse_pos {
-                        let dx = cur_x - last_x;
-                        let dy = cur_y - last_y;
-                        self.pan_x = (self.pan_x + dx / (self.width as f32 * self.zoom)).clamp(0.0, 1.0);
-                        self.pan_y = (self.pan_y + dy / (self.height as f32 * self.zoom)).clamp(0.0, 1.0);
+                        self.pan_x = pan_by(self.pan_x, cur_x - last_x, self.width, self.zoom);
+                        self.pan_y = pan_by(self.pan_y, cur_y - last_y, self.height, self.zoom);
                         needs_update = true;
                     }
                     last_mouse_pos = Some((cur_x, cur_y));
                 }
             } else {
                 last_mouse_pos = None;
+                if let Some(start) = drag_start.take() {
+                    if start != self.view_state() {
+                        self.push_history(start);
+                    }
+                }
+            }
+
+            // Record keyboard and wheel changes; drags are recorded on release.
+            if !history_changed && !dragging && self.view_state() != frame_start {
+                self.push_history(frame_start);
+            }
+
+            if self.notice.as_ref().is_some_and(|(_, shown)| shown.elapsed() >= NOTICE_DURATION) {
+                self.notice = None;
+                self.window.set_title(&self.title());
             }
 
             // Check for window resize.
@@ -396,12 +1066,27 @@ impl ImageViewer {
         println!("H             - Show help");
         println!("I             - Show image info");
         println!("E             - Toggle edge detection");
+        println!("Shift+E       - Cycle edge mode (binary, continuous, direction)");
+        println!("O             - Toggle focus peaking (tints sharp areas red)");
+        println!("[ / ]         - Focus peaking threshold (lower is more sensitive)");
+        println!("N             - Toggle nearest-neighbor / bilinear sampling");
+        println!("F             - Cycle false color palette (grayscale, viridis, jet)");
+        println!("M             - Toggle false colors in screenshots");
         println!("R             - Reset adjustments");
+        println!("Shift+↑ / ↓  - Reset brightness only");
+        println!("Shift+← / →  - Reset contrast only");
+        println!("Shift+, / .  - Reset gamma only");
         println!("+ / -        - Zoom in/out (or use mouse wheel)");
         println!("↑ / ↓        - Adjust brightness");
         println!("← / →        - Adjust contrast");
+        println!("B / C         - Type a brightness / contrast value (Enter to apply)");
+        println!(", / .        - Adjust gamma");
         println!("S             - Save screenshot (screenshot.png)");
+        println!("Ctrl+C        - Copy the hex color under the cursor (or the visible average)");
         println!("P             - Toggle side panel");
+        println!("K             - Cycle compression previews (shows codec and size)");
+        println!("Ctrl+Z / U    - Undo adjustment");
+        println!("Ctrl+Y / Y    - Redo adjustment");
         println!("Drag with left mouse button to pan");
     }
 
@@ -411,20 +1096,417 @@ impl ImageViewer {
         println!("------------------");
         println!("Dimensions: {}x{}", self.width, self.height);
         println!("Color Type: {:?}", self.color_type);
+        match self.codec {
+            Some(preview) => println!("Codec Preview: {:?} ({} of {} bytes)", preview.compression, preview.size, self.source.data.len()),
+            None => println!("Codec Preview: Off"),
+        }
         println!("Zoom: {:.1}x", self.zoom);
         println!("Brightness: {}", self.brightness);
         println!("Contrast: {}", self.contrast);
-        println!("Edge Detection: {}", if self.edge_detection { "On" } else { "Off" });
+        println!("Gamma: {:.1}", self.gamma);
+        println!("Edge Detection: {}", self.edge_label());
+        println!("Focus Peaking: {}", self.peaking_label());
+        println!("Palette: {:?}", self.palette);
+        println!("Sampling: {:?}", self.sampling);
         println!("Side Panel: {}", if self.show_panel { "On" } else { "Off" });
         let (win_w, win_h) = self.window.get_size();
         println!("Window size: {}x{}", win_w, win_h);
     }
 }
 
+/// Returns the digit typed by a number-row or keypad key.
+fn key_digit(key: Key) -> Option<char> {
+    let digit = match key {
+        Key::Key0 | Key::NumPad0 => 0,
+        Key::Key1 | Key::NumPad1 => 1,
+        Key::Key2 | Key::NumPad2 => 2,
+        Key::Key3 | Key::NumPad3 => 3,
+        Key::Key4 | Key::NumPad4 => 4,
+        Key::Key5 | Key::NumPad5 => 5,
+        Key::Key6 | Key::NumPad6 => 6,
+        Key::Key7 | Key::NumPad7 => 7,
+        Key::Key8 | Key::NumPad8 => 8,
+        Key::Key9 | Key::NumPad9 => 9,
+        _ => return None,
+    };
+    char::from_digit(digit, 10)
+}
+
+/// Returns the average color of the pixels of packed RGB `colors` (rows of
+/// `width`) within columns `xs` and rows `ys`, or `None` if the area is empty.
+fn average_color(colors: &[u32], width: usize, xs: Range<usize>, ys: Range<usize>) -> Option<u32> {
+    let count = (xs.len() * ys.len()) as u64;
+    if count == 0 {
+        return None;
+    }
+    let mut sums = [0u64; 3];
+    for y in ys {
+        for &pixel in &colors[y * width + xs.start..y * width + xs.end] {
+            sums[0] += ((pixel >> 16) & 0xFF) as u64;
+            sums[1] += ((pixel >> 8) & 0xFF) as u64;
+            sums[2] += (pixel & 0xFF) as u64;
+        }
+    }
+    let [r, g, b] = sums.map(|sum| ((sum + count / 2) / count) as u32);
+    Some((r << 16) | (g << 8) | b)
+}
+
+/// The system clipboard, opened on first use and then kept open: on X11 the
+/// copied text is only available while the viewer holds the clipboard (or
+/// hands it to a clipboard manager on exit).
+#[derive(Default)]
+struct Clipboard {
+    #[cfg(feature = "clipboard")]
+    inner: Option<arboard::Clipboard>,
+}
+
+impl Clipboard {
+    /// Replaces the clipboard contents with `text`.
+    #[cfg(feature = "clipboard")]
+    fn set_text(&mut self, text: &str) -> Result<(), Box<dyn Error>> {
+        let clipboard = match &mut self.inner {
+            Some(clipboard) => clipboard,
+            None => self.inner.insert(arboard::Clipboard::new()?),
+        };
+        clipboard.set_text(text)?;
+        Ok(())
+    }
+
+    /// Always fails: this build has no clipboard support.
+    #[cfg(not(feature = "clipboard"))]
+    fn set_text(&mut self, _text: &str) -> Result<(), Box<dyn Error>> {
+        Err("built without the clipboard feature".into())
+    }
+}
+
+/// Width in pixels of the divider line in the comparison view.
+const DIVIDER_WIDTH: usize = 2;
+/// Distance in pixels from the divider within which a drag moves it.
+const DIVIDER_GRAB: f32 = 6.0;
+
+/// A split-screen viewer comparing two images.
+///
+/// Both images share one zoom/pan transform; image A is shown left of the
+/// divider and image B right of it.
+pub struct CompareViewer {
+    window: Window,
+    left: Vec<u32>,
+    right: Vec<u32>,
+    left_size: (usize, usize),
+    right_size: (usize, usize),
+    left_name: String,
+    right_name: String,
+    zoom: f32,
+    pan_x: f32,
+    pan_y: f32,
+    divider: f32,              // Divider position as fraction of window width
+}
+
+impl CompareViewer {
+    /// Create a comparison viewer for two images. The window is sized to fit
+    /// the larger of the two.
+    pub fn new(
+        left: CustomImage,
+        left_name: &str,
+        right: CustomImage,
+        right_name: &str,
+    ) -> Result<Self, Box<dyn Error>> {
+        let left_size = (left.width as usize, left.height as usize);
+        let right_size = (right.width as usize, right.height as usize);
+        let width = left_size.0.max(right_size.0);
+        let height = left_size.1.max(right_size.1);
+        let left = display_buffer(left)?;
+        let right = display_buffer(right)?;
+
+        let mut window = Window::new(
+            &format!("Compare - A: {} | B: {}", left_name, right_name),
+            width,
+            height,
+            WindowOptions {
+                scale: Scale::X1,
+                resize: true,
+                ..WindowOptions::default()
+            },
+        ).map_err(|e| format!("Failed to create window: {}", e))?;
+        window.limit_update_rate(Some(std::time::Duration::from_micros(16_600)));
+
+        let mut viewer = CompareViewer {
+            window,
+            left,
+            right,
+            left_size,
+            right_size,
+            left_name: left_name.to_string(),
+            right_name: right_name.to_string(),
+            zoom: 1.0,
+            pan_x: 0.0,
+            pan_y: 0.0,
+            divider: 0.5,
+        };
+        viewer.update_window_buffer()?;
+        Ok(viewer)
+    }
+
+    /// Extent of the shared canvas, which covers both images.
+    fn canvas_size(&self) -> (usize, usize) {
+        (self.left_size.0.max(self.right_size.0), self.left_size.1.max(self.right_size.1))
+    }
+
+    /// Renders both images with the shared transform and draws the divider.
+    fn update_window_buffer(&mut self) -> Result<(), Box<dyn Error>> {
+        let (win_width, win_height) = self.window.get_size();
+        self.window.set_title(&format!(
+            "Compare - A (left): {} | B (right): {} | Zoom: {:.1}x",
+            self.left_name, self.right_name, self.zoom
+        ));
+
+        let (canvas_width, canvas_height) = self.canvas_size();
+        let offset_x = pan_offset(self.pan_x, canvas_width, self.zoom, win_width);
+        let offset_y = pan_offset(self.pan_y, canvas_height, self.zoom, win_height);
+        let divider_x = (self.divider * win_width as f32) as usize;
+
+        let mut new_buffer = vec![0u32; win_width * win_height];
+        for win_y in 0..win_height {
+            for win_x in 0..win_width {
+                let index = win_y * win_width + win_x;
+                if win_x.abs_diff(divider_x) < DIVIDER_WIDTH {
+                    new_buffer[index] = 0xFFFFFF;
+                    continue;
+                }
+                let (buffer, (width, height)) = if win_x < divider_x {
+                    (&self.left, self.left_size)
+                } else {
+                    (&self.right, self.right_size)
+                };
+                let img_x = (win_x as i32 + offset_x) as f32 / self.zoom;
+                let img_y = (win_y as i32 + offset_y) as f32 / self.zoom;
+                if let Some(pixel) = sample_bilinear(buffer, width, height, img_x, img_y) {
+                    new_buffer[index] = pixel;
+                }
+            }
+        }
+
+        self.window.update_with_buffer(&new_buffer, win_width, win_height)
+            .map_err(|e| format!("Window buffer update failed: {}", e))?;
+        Ok(())
+    }
+
+    /// Main loop: handles zoom, panning, and divider dragging.
+    pub fn run(&mut self) -> Result<(), Box<dyn Error>> {
+        self.show_help();
+        let mut last_win_size = self.window.get_size();
+        let mut last_mouse_pos: Option<(f32, f32)> = None;
+        let mut dragging_divider = false;
+
+        while self.window.is_open() && !self.window.is_key_down(Key::Escape) {
+            let mut needs_update = false;
+
+            for key in self.window.get_keys_pressed(KeyRepeat::Yes) {
+                match key {
+                    Key::H => self.show_help(),
+                    Key::R => {
+                        self.zoom = 1.0;
+                        self.pan_x = 0.0;
+                        self.pan_y = 0.0;
+                        self.divider = 0.5;
+                        needs_update = true;
+                    }
+                    Key::Equal | Key::NumPadPlus => { self.zoom = (self.zoom + ZOOM_STEP).min(MAX_ZOOM); needs_update = true; }
+                    Key::Minus | Key::NumPadMinus => { self.zoom = (self.zoom - ZOOM_STEP).max(MIN_ZOOM); needs_update = true; }
+                    Key::Left => { self.divider = (self.divider - 0.05).max(0.0); needs_update = true; }
+                    Key::Right => { self.divider = (self.divider + 0.05).min(1.0); needs_update = true; }
+                    _ => {}
+                }
+            }
+
+            if let Some((_, scroll_y)) = self.window.get_scroll_wheel() {
+                if scroll_y != 0.0 {
+                    self.zoom = (self.zoom + scroll_y * ZOOM_STEP).clamp(MIN_ZOOM, MAX_ZOOM);
+                    needs_update = true;
+                }
+            }
+
+            // Dragging near the divider moves it; dragging elsewhere pans both images.
+            if self.window.get_mouse_down(MouseButton::Left) {
+                if let Some((cur_x, cur_y)) = self.window.get_mouse_pos(minifb::MouseMode::Discard) {
+                    let win_width = self.window.get_size().0.max(1) as f32;
+                    match last_mouse_pos {
+                        None => {
+                            dragging_divider = (cur_x - self.divider * win_width).abs() <= DIVIDER_GRAB;
+                        }
+                        Some(_) if dragging_divider => {
+                            self.divider = (cur_x / win_width).clamp(0.0, 1.0);
+                            needs_update = true;
+                        }
+                        Some((last_x, last_y)) => {
+                            let (canvas_width, canvas_height) = self.canvas_size();
+                            self.pan_x = pan_by(self.pan_x, cur_x - last_x, canvas_width, self.zoom);
+                            self.pan_y = pan_by(self.pan_y, cur_y - last_y, canvas_height, self.zoom);
+                            needs_update = true;
+                        }
+                    }
+                    last_mouse_pos = Some((cur_x, cur_y));
+                }
+            } else {
+                last_mouse_pos = None;
+                dragging_divider = false;
+            }
+
+            let current_size = self.window.get_size();
+            if current_size != last_win_size {
+                last_win_size = current_size;
+                needs_update = true;
+            }
+
+            if needs_update {
+                self.update_window_buffer()?;
+            }
+            self.window.update();
+            std::thread::sleep(std::time::Duration::from_millis(16)); // ~60 FPS
+        }
+        Ok(())
+    }
+
+    /// Displays help information.
+    fn show_help(&self) {
+        println!("\nCompare View Controls:");
+        println!("----------------------");
+        println!("A (left):  {}", self.left_name);
+        println!("B (right): {}", self.right_name);
+        println!("ESC           - Exit");
+        println!("H             - Show help");
+        println!("R             - Reset view");
+        println!("+ / -        - Zoom in/out (or use mouse wheel)");
+        println!("← / →        - Move divider");
+        println!("Drag the divider to move it; drag elsewhere to pan");
+    }
+}
+
+/// Performs bilinear interpolation on one channel.
+fn bilinear_interpolate(p00: u32, p10: u32, p01: u32, p11: u32, fx: f32, fy: f32) -> u32 {
+    let interp0 = p00 as f32 * (1.0 - fx) + p10 as f32 * fx;
+    let interp1 = p01 as f32 * (1.0 - fx) + p11 as f32 * fx;
+    (interp0 * (1.0 - fy) + interp1 * fy).round() as u32
+}
+
+/// Samples an RGB buffer at fractional image coordinates using bilinear
+/// interpolation. Returns `None` outside the image.
+fn sample_bilinear(buffer: &[u32], width: usize, height: usize, img_x: f32, img_y: f32) -> Option<u32> {
+    if img_x < 0.0 || img_y < 0.0 || img_x >= (width - 1) as f32 || img_y >= (height - 1) as f32 {
+        return None;
+    }
+    let x0 = img_x.floor() as usize;
+    let y0 = img_y.floor() as usize;
+    let x1 = (x0 + 1).min(width - 1);
+    let y1 = (y0 + 1).min(height - 1);
+    let fx = img_x - x0 as f32;
+    let fy = img_y - y0 as f32;
+    let p00 = buffer[y0 * width + x0];
+    let p10 = buffer[y0 * width + x1];
+    let p01 = buffer[y1 * width + x0];
+    let p11 = buffer[y1 * width + x1];
+    let r = bilinear_interpolate((p00 >> 16) & 0xFF, (p10 >> 16) & 0xFF,
+                                 (p01 >> 16) & 0xFF, (p11 >> 16) & 0xFF, fx, fy);
+    let g = bilinear_interpolate((p00 >> 8) & 0xFF, (p10 >> 8) & 0xFF,
+                                 (p01 >> 8) & 0xFF, (p11 >> 8) & 0xFF, fx, fy);
+    let b = bilinear_interpolate(p00 & 0xFF, p10 & 0xFF,
+                                 p01 & 0xFF, p11 & 0xFF, fx, fy);
+    Some((r << 16) | (g << 8) | b)
+}
+
+/// Samples an RGB buffer at fractional image coordinates by taking the
+/// nearest pixel. Returns `None` outside the image.
+fn sample_nearest(buffer: &[u32], width: usize, height: usize, img_x: f32, img_y: f32) -> Option<u32> {
+    if img_x < 0.0 || img_y < 0.0 || img_x >= width as f32 || img_y >= height as f32 {
+        return None;
+    }
+    Some(buffer[img_y as usize * width + img_x as usize])
+}
+
+/// Converts a pan fraction (0.0 to 1.0) into a pixel offset along one axis,
+/// clamped so the scaled image never scrolls past its edge.
+fn pan_offset(pan: f32, image_len: usize, zoom: f32, view_len: usize) -> i32 {
+    let scaled_len = (image_len as f32 * zoom) as usize;
+    let max_pan = if scaled_len > view_len { scaled_len as i32 - view_len as i32 } else { 0 };
+    ((pan * scaled_len as f32) as i32).clamp(0, max_pan)
+}
+
+/// Moves a pan fraction by a mouse delta (in window pixels) along one axis.
+fn pan_by(pan: f32, delta: f32, image_len: usize, zoom: f32) -> f32 {
+    (pan + delta / (image_len as f32 * zoom)).clamp(0.0, 1.0)
+}
+
+/// Decompresses an image if needed and converts it to the 32-bit RGB buffer
+/// the viewers draw from.
+///
+/// # Errors
+///
+/// Returns a format error if the image fails to decompress, or
+/// `DataLengthMismatch` if its pixel data does not hold exactly
+/// `width * height` pixels of its color type.
+///
+/// ```rust
+/// use nor_image::format::{CustomImage, ColorType, CompressionType, FormatError};
+/// use nor_image::processing::ParallelImageProcessor;
+/// use nor_image::viewer::display_buffer;
+///
+/// let mut image = CustomImage::new(4, 2, ColorType::Gray, vec![0x40; 8], None, CompressionType::None)?;
+/// ParallelImageProcessor::compress(&mut image, CompressionType::RLE)?;
+/// let opened = CustomImage::from_bytes(&image.to_bytes()?)?;
+/// assert_eq!(display_buffer(opened)?, vec![0x404040; 8]);
+///
+/// let truncated = CustomImage { data: vec![0; 5], compression: CompressionType::None, chunks: None, ..image };
+/// assert!(matches!(
+///     display_buffer(truncated),
+///     Err(FormatError::DataLengthMismatch { expected: 8, actual: 5 })
+/// ));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn display_buffer(mut image: CustomImage) -> Result<Vec<u32>, FormatError> {
+    ParallelImageProcessor::decompress(&mut image)?;
+    let expected = image.width as usize * image.height as usize * image.color_type.channels() as usize;
+    if image.data.len() != expected {
+        return Err(FormatError::DataLengthMismatch {
+            expected,
+            actual: image.data.len(),
+        });
+    }
+    Ok(ImageViewer::convert_to_rgb(&image))
+}
+
 /// Entry point: loads a custom image file and starts the viewer.
-pub fn view_custom_image(path: &str) -> Result<(), Box<dyn Error>> {
-    let bytes = fs::read(path)?;
-    let custom_img = CustomImage::from_bytes(&bytes)?;
+///
+/// With `use_cache`, the decompressed image comes from the in-process image
+/// cache when it is there and is added to it otherwise, so reopening a file
+/// in the same session (e.g. from interactive mode) skips reading and
+/// decoding it. Entries are keyed by path, so a file rewritten since it was
+/// cached shows its old contents.
+pub fn view_custom_image(path: &str, use_cache: bool) -> Result<(), Box<dyn Error>> {
+    let custom_img = if use_cache {
+        Arc::unwrap_or_clone(CachedImageLoader::load_decompressed(path)?)
+    } else {
+        CustomImage::from_bytes(&fs::read(path)?)?
+    };
+    let mut viewer = ImageViewer::new(custom_img)?;
+    viewer.run()
+}
+
+/// Entry point: imports a PNG file without compression and starts the
+/// viewer, e.g. to inspect how each codec would treat it.
+pub fn view_png_image(path: &str) -> Result<(), Box<dyn Error>> {
+    let config = ConversionConfig { use_cache: false, ..ConversionConfig::default() };
+    let custom_img = png_to_custom(path, None, Some(config))?;
     let mut viewer = ImageViewer::new(custom_img)?;
     viewer.run()
 }
+
+/// Entry point: loads two custom image files and starts the comparison viewer.
+///
+/// Compressed images are decompressed by the viewer so their pixels can be
+/// compared.
+pub fn view_comparison(left_path: &str, right_path: &str) -> Result<(), Box<dyn Error>> {
+    let left = CustomImage::from_bytes(&fs::read(left_path)?)?;
+    let right = CustomImage::from_bytes(&fs::read(right_path)?)?;
+    let mut viewer = CompareViewer::new(left, left_path, right, right_path)?;
+    viewer.run()
+}
@@ -0,0 +1,283 @@
+// Copyright 2025 Grish
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Watermark rendering.
+//!
+//! Stamps a line of text or a small logo image onto raw pixel data:
+//! - Text is drawn with a built-in 5x7 bitmap font, white with a dark shadow
+//! - Images are alpha-blended using their own transparency
+//!
+//! Both are anchored to a corner (or the center) and blended at a given
+//! opacity.
+
+use std::path::PathBuf;
+
+use image::{Rgba, RgbaImage};
+use serde::Deserialize;
+
+use crate::converter::ConversionError;
+use crate::format::ColorType;
+
+/// Width of a glyph in the built-in font, in font pixels.
+const GLYPH_WIDTH: u32 = 5;
+/// Height of a glyph in the built-in font, in font pixels.
+const GLYPH_HEIGHT: u32 = 7;
+/// Distance from the image edge to a corner-anchored watermark, in font pixels.
+const MARGIN: u32 = 4;
+
+/// Where a watermark is placed on the image.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WatermarkPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+    Center,
+}
+
+/// What a watermark draws.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WatermarkContent {
+    /// A line of text. Letters are drawn upper-case; characters missing from
+    /// the built-in font are drawn as `?`.
+    Text(String),
+    /// Path to an image (e.g. a PNG logo with transparency).
+    Image(PathBuf),
+}
+
+/// A watermark to stamp onto converted images.
+///
+/// In a TOML config file:
+///
+/// ```toml
+/// [watermark]
+/// content = { text = "SAMPLE" }
+/// position = "bottom-right"
+/// opacity = 0.5
+/// ```
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Watermark {
+    /// Text or image to draw.
+    pub content: WatermarkContent,
+    /// Where to place the watermark.
+    #[serde(default)]
+    pub position: WatermarkPosition,
+    /// Opacity from 0.0 (invisible) to 1.0 (opaque).
+    #[serde(default = "default_opacity")]
+    pub opacity: f32,
+}
+
+fn default_opacity() -> f32 {
+    0.5
+}
+
+impl Watermark {
+    /// Creates a watermark with the default position and opacity.
+    pub fn new(content: WatermarkContent) -> Self {
+        Watermark {
+            content,
+            position: WatermarkPosition::default(),
+            opacity: default_opacity(),
+        }
+    }
+}
+
+/// Blends a watermark into interleaved gray or RGB pixel data in place.
+///
+/// Text is scaled with the image so it stays legible on large images.
+/// Parts of the watermark that fall outside the image are clipped.
+///
+/// # Errors
+///
+/// Returns `InvalidOption` if the opacity is outside 0.0 to 1.0 or the text
+/// is empty, or an image error if the watermark image cannot be loaded.
+///
+/// ```rust
+/// use nor_image::format::ColorType;
+/// use nor_image::watermark::{apply_watermark, Watermark, WatermarkContent, WatermarkPosition};
+///
+/// let mut data = vec![0u8; 100 * 40];
+/// let mut watermark = Watermark::new(WatermarkContent::Text("HI".to_string()));
+/// watermark.position = WatermarkPosition::TopLeft;
+/// watermark.opacity = 1.0;
+/// apply_watermark(&mut data, 100, 40, ColorType::Gray, &watermark)?;
+/// assert!(data.contains(&255));
+/// assert_eq!(data[39 * 100 + 99], 0); // far corner untouched
+/// # Ok::<(), nor_image::converter::ConversionError>(())
+/// ```
+pub fn apply_watermark(
+    data: &mut [u8],
+    width: u32,
+    height: u32,
+    color_type: ColorType,
+    watermark: &Watermark,
+) -> Result<(), ConversionError> {
+    if !(0.0..=1.0).contains(&watermark.opacity) {
+        return Err(ConversionError::InvalidOption(format!(
+            "watermark opacity must be between 0 and 1, got {}",
+            watermark.opacity
+        )));
+    }
+
+    let scale = (width.min(height) / 160).max(1);
+    let overlay = match &watermark.content {
+        WatermarkContent::Text(text) => {
+            if text.trim().is_empty() {
+                return Err(ConversionError::InvalidOption("watermark text is empty".to_string()));
+            }
+            render_text(text, scale)
+        }
+        WatermarkContent::Image(path) => image::open(path)?.into_rgba8(),
+    };
+
+    let margin = MARGIN * scale;
+    let (x0, y0) = anchor(watermark.position, (width, height), overlay.dimensions(), margin);
+    let channels = color_type.channels() as usize;
+    for (ox, oy, pixel) in overlay.enumerate_pixels() {
+        let (x, y) = (x0 + ox as i64, y0 + oy as i64);
+        if x < 0 || y < 0 || x >= width as i64 || y >= height as i64 {
+            continue;
+        }
+        let alpha = pixel[3] as f32 / 255.0 * watermark.opacity;
+        if alpha == 0.0 {
+            continue;
+        }
+        let index = (y as usize * width as usize + x as usize) * channels;
+        let source = match color_type {
+            ColorType::Gray => vec![luma(pixel)],
+            ColorType::Rgb => pixel.0[..3].to_vec(),
+            ColorType::GrayAlpha => vec![luma(pixel), 255],
+        };
+        for (dst, src) in data[index..index + channels].iter_mut().zip(source) {
+            *dst = (*dst as f32 * (1.0 - alpha) + src as f32 * alpha).round() as u8;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the top-left corner of an overlay of `size` placed in an image of
+/// `image` dimensions. May be negative if the overlay is larger than the image.
+fn anchor(position: WatermarkPosition, image: (u32, u32), size: (u32, u32), margin: u32) -> (i64, i64) {
+    let (width, height) = (image.0 as i64, image.1 as i64);
+    let (w, h) = (size.0 as i64, size.1 as i64);
+    let margin = margin as i64;
+    match position {
+        WatermarkPosition::TopLeft => (margin, margin),
+        WatermarkPosition::TopRight => (width - w - margin, margin),
+        WatermarkPosition::BottomLeft => (margin, height - h - margin),
+        WatermarkPosition::BottomRight => (width - w - margin, height - h - margin),
+        WatermarkPosition::Center => ((width - w) / 2, (height - h) / 2),
+    }
+}
+
+/// Renders text as white glyphs with a dark drop shadow, `scale` image
+/// pixels per font pixel.
+pub(crate) fn render_text(text: &str, scale: u32) -> RgbaImage {
+    let chars: Vec<char> = text.chars().collect();
+    let advance = (GLYPH_WIDTH + 1) * scale;
+    let width = advance * chars.len() as u32;
+    let height = (GLYPH_HEIGHT + 1) * scale;
+    let mut overlay = RgbaImage::new(width, height);
+
+    // Shadow first, offset by one font pixel, then the glyphs on top.
+    for (offset, color) in [(scale, Rgba([0, 0, 0, 255])), (0, Rgba([255, 255, 255, 255]))] {
+        for (i, &c) in chars.iter().enumerate() {
+            let rows = glyph(c);
+            for (row, bits) in rows.iter().enumerate() {
+                for col in 0..GLYPH_WIDTH {
+                    if bits & (1 << (GLYPH_WIDTH - 1 - col)) == 0 {
+                        continue;
+                    }
+                    let x = i as u32 * advance + col * scale + offset;
+                    let y = row as u32 * scale + offset;
+                    for dy in 0..scale {
+                        for dx in 0..scale {
+                            overlay.put_pixel(x + dx, y + dy, color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    overlay
+}
+
+/// Converts an RGBA pixel to luma.
+fn luma(pixel: &Rgba<u8>) -> u8 {
+    (0.299 * pixel[0] as f32 + 0.587 * pixel[1] as f32 + 0.114 * pixel[2] as f32).round() as u8
+}
+
+/// Returns the 5x7 bitmap for a character, one row per byte with the
+/// leftmost pixel in bit 4.
+fn glyph(c: char) -> [u8; 7] {
+    match c.to_ascii_uppercase() {
+        'A' => [0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001],
+        'B' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110],
+        'C' => [0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110],
+        'D' => [0b11110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b11110],
+        'E' => [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111],
+        'F' => [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000],
+        'G' => [0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111],
+        'H' => [0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001],
+        'I' => [0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+        'J' => [0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100],
+        'K' => [0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001],
+        'L' => [0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111],
+        'M' => [0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001],
+        'N' => [0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001],
+        'O' => [0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110],
+        'P' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000],
+        'Q' => [0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101],
+        'R' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001],
+        'S' => [0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110],
+        'T' => [0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100],
+        'U' => [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110],
+        'V' => [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100],
+        'W' => [0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010],
+        'X' => [0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001],
+        'Y' => [0b10001, 0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100],
+        'Z' => [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111],
+        '0' => [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110],
+        '1' => [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+        '2' => [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111],
+        '3' => [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110],
+        '4' => [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010],
+        '5' => [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110],
+        '6' => [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110],
+        '7' => [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000],
+        '8' => [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110],
+        '9' => [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100],
+        ' ' => [0; 7],
+        '.' => [0, 0, 0, 0, 0, 0b01100, 0b01100],
+        ',' => [0, 0, 0, 0, 0b01100, 0b00100, 0b01000],
+        ':' => [0, 0b01100, 0b01100, 0, 0b01100, 0b01100, 0],
+        '-' => [0, 0, 0, 0b11111, 0, 0, 0],
+        '_' => [0, 0, 0, 0, 0, 0, 0b11111],
+        '+' => [0, 0b00100, 0b00100, 0b11111, 0b00100, 0b00100, 0],
+        '=' => [0, 0, 0b11111, 0, 0b11111, 0, 0],
+        '!' => [0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0, 0b00100],
+        '\'' => [0b01100, 0b00100, 0b01000, 0, 0, 0, 0],
+        '(' => [0b00010, 0b00100, 0b01000, 0b01000, 0b01000, 0b00100, 0b00010],
+        ')' => [0b01000, 0b00100, 0b00010, 0b00010, 0b00010, 0b00100, 0b01000],
+        '/' => [0, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0],
+        '#' => [0b01010, 0b01010, 0b11111, 0b01010, 0b11111, 0b01010, 0b01010],
+        '&' => [0b01100, 0b10010, 0b10100, 0b01000, 0b10101, 0b10010, 0b01101],
+        '@' => [0b01110, 0b10001, 0b00001, 0b01101, 0b10101, 0b10101, 0b01110],
+        _ => [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0, 0b00100], // '?'
+    }
+}
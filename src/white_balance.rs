@@ -0,0 +1,116 @@
+// Copyright 2025 Grish
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Automatic white balance.
+//!
+//! Removes color casts (e.g. the orange tint of indoor lighting) from
+//! interleaved 8-bit RGB data with the gray-world assumption: the average
+//! color of a typical scene is neutral gray, so each channel is scaled until
+//! its mean matches the mean of all three.
+//! - Channel means come from per-channel 256-bin histograms
+//! - Gains are capped at `MAX_GAIN`, so a channel that is almost absent is
+//!   not amplified into noise
+//! - Scaled values are clamped to 255 instead of overflowing
+//!
+//! Scenes dominated by one color (a field of grass, a blue sky) break the
+//! assumption and come out tinted toward its complement.
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Largest factor a channel is scaled by.
+pub const MAX_GAIN: f32 = 4.0;
+
+/// Returns the per-channel histograms of interleaved RGB data.
+fn channel_histograms(rgb: &[u8]) -> [[u64; 256]; 3] {
+    let mut histograms = [[0u64; 256]; 3];
+    for pixel in rgb.chunks_exact(3) {
+        for (histogram, &value) in histograms.iter_mut().zip(pixel) {
+            histogram[value as usize] += 1;
+        }
+    }
+    histograms
+}
+
+/// Returns the gains that move the channel means of interleaved RGB data to
+/// their common average, as `[red, green, blue]`.
+///
+/// Channels whose mean is zero, and images without pixels or whose channels
+/// are all black, keep a gain of 1.0.
+///
+/// ```rust
+/// use nor_image::white_balance::gray_world_gains;
+///
+/// // An orange cast: red averages 150, green 100 and blue 50.
+/// let rgb = [150, 100, 50].repeat(4);
+/// let [red, green, blue] = gray_world_gains(&rgb);
+/// assert!((red - 2.0 / 3.0).abs() < 1e-6);
+/// assert_eq!((green, blue), (1.0, 2.0));
+///
+/// // Neutral images are left alone.
+/// assert_eq!(gray_world_gains(&[10, 10, 10, 200, 200, 200]), [1.0; 3]);
+/// ```
+pub fn gray_world_gains(rgb: &[u8]) -> [f32; 3] {
+    let pixels = (rgb.len() / 3) as f64;
+    if pixels == 0.0 {
+        return [1.0; 3];
+    }
+    let means = channel_histograms(rgb).map(|histogram| {
+        let sum: u64 = histogram.iter().enumerate().map(|(value, &count)| value as u64 * count).sum();
+        sum as f64 / pixels
+    });
+    let gray = means.iter().sum::<f64>() / 3.0;
+    means.map(|mean| if mean > 0.0 { (gray / mean).min(MAX_GAIN as f64) as f32 } else { 1.0 })
+}
+
+/// White-balances interleaved RGB data in place with `gray_world_gains`,
+/// rounding and clamping each scaled value to 0-255. Returns the gains used.
+///
+/// ```rust
+/// use nor_image::white_balance::auto_white_balance;
+///
+/// // A warm gray card next to a darker warm patch: both become neutral.
+/// let mut rgb = vec![180, 150, 120, 90, 75, 60];
+/// auto_white_balance(&mut rgb);
+/// assert_eq!(rgb, vec![150, 150, 150, 75, 75, 75]);
+///
+/// // Bright values clamp instead of wrapping around.
+/// let mut rgb = vec![100, 100, 250, 100, 100, 0, 100, 100, 0];
+/// let gains = auto_white_balance(&mut rgb);
+/// assert!(gains[2] > 1.0);
+/// assert_eq!(rgb[2], 255);
+/// ```
+pub fn auto_white_balance(rgb: &mut [u8]) -> [f32; 3] {
+    let gains = gray_world_gains(rgb);
+    if gains == [1.0; 3] {
+        return gains;
+    }
+    let luts = gains.map(|gain| {
+        let mut lut = [0u8; 256];
+        for (value, out) in lut.iter_mut().enumerate() {
+            *out = (value as f32 * gain).round().min(255.0) as u8;
+        }
+        lut
+    });
+    #[cfg(feature = "parallel")]
+    let pixels = rgb.par_chunks_exact_mut(3);
+    #[cfg(not(feature = "parallel"))]
+    let pixels = rgb.chunks_exact_mut(3);
+    pixels.for_each(|pixel| {
+        for (value, lut) in pixel.iter_mut().zip(&luts) {
+            *value = lut[*value as usize];
+        }
+    });
+    gains
+}